@@ -1,12 +1,20 @@
 use std::{
-    collections::HashMap,
-    fmt,
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::Path,
     sync::{Arc, Mutex},
 };
 
-use crate::{GUIState, Program, web_search::WebSource};
+use crate::{
+    GUIState, Program,
+    backend::BackendKind,
+    context_budget::ContextSummary,
+    structured_output::FormatCheck,
+    web_search::{WebSource, redact_secret},
+};
 use chrono::Local;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 #[derive(Clone, Debug)]
 pub enum Correspondence {
@@ -16,13 +24,202 @@ pub enum Correspondence {
         thinking_seconds: Option<u64>,
         sources: Vec<WebSource>,
         web_search_used: bool,
+        stats: Option<GenerationStats>,
+        /// Set when the answer was requested with a structured output format.
+        format_check: Option<FormatCheck>,
+        /// Regenerated answers to the same user turn. The active answer lives
+        /// in the fields above; `alternatives` holds the others in order.
+        alternatives: Vec<ResponseVariant>,
+        active_variant: usize,
     },
     User {
         text: String,
         images: Vec<ChatImage>,
+        /// Conversations forked by editing this turn. Each branch starts with
+        /// its own version of this message; the active branch is the rest of
+        /// the visible chat, and `branches` holds the others in order.
+        branches: Vec<Vec<Correspondence>>,
+        active_branch: usize,
     },
 }
 
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResponseVariant {
+    pub text: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub thinking_seconds: Option<u64>,
+    #[serde(default)]
+    pub sources: Vec<WebSource>,
+    #[serde(default)]
+    pub web_search_used: bool,
+    #[serde(default)]
+    pub stats: Option<GenerationStats>,
+    #[serde(default)]
+    pub format_check: Option<FormatCheck>,
+}
+
+/// Token counts and timings Ollama reports with the last line of a response.
+/// Field names match Ollama's; durations are in nanoseconds.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct GenerationStats {
+    pub prompt_eval_count: u64,
+    pub prompt_eval_duration: u64,
+    pub eval_count: u64,
+    pub eval_duration: u64,
+    pub load_duration: u64,
+    pub total_duration: u64,
+}
+
+impl GenerationStats {
+    /// Reads the statistics from a final `/api/chat` or `/api/generate`
+    /// object. Streamed lines before the last one carry none.
+    pub fn from_response(value: &serde_json::Value) -> Option<Self> {
+        value.get("eval_count")?;
+        serde_json::from_value(value.clone()).ok()
+    }
+
+    pub fn tokens_per_second(&self) -> Option<f64> {
+        (self.eval_duration > 0)
+            .then(|| self.eval_count as f64 * 1_000_000_000.0 / self.eval_duration as f64)
+    }
+
+    pub fn load_seconds(&self) -> f64 {
+        self.load_duration as f64 / 1_000_000_000.0
+    }
+}
+
+impl Correspondence {
+    pub fn user(text: String, images: Vec<ChatImage>) -> Self {
+        Correspondence::User {
+            text,
+            images,
+            branches: Vec::new(),
+            active_branch: 0,
+        }
+    }
+
+    pub fn bot(text: String, model: Option<String>) -> Self {
+        Correspondence::Bot {
+            text,
+            model,
+            thinking_seconds: None,
+            sources: Vec::new(),
+            web_search_used: false,
+            stats: None,
+            format_check: None,
+            alternatives: Vec::new(),
+            active_variant: 0,
+        }
+    }
+
+    /// Returns `(active, total)` for edited user turns, counting the active branch.
+    pub fn branch_position(&self) -> Option<(usize, usize)> {
+        match self {
+            Correspondence::User {
+                branches,
+                active_branch,
+                ..
+            } => Some((*active_branch, branches.len() + 1)),
+            Correspondence::Bot { .. } => None,
+        }
+    }
+
+    /// Returns `(active, total)` for bot answers, counting the active one.
+    pub fn variant_position(&self) -> Option<(usize, usize)> {
+        match self {
+            Correspondence::Bot {
+                alternatives,
+                active_variant,
+                ..
+            } => Some((*active_variant, alternatives.len() + 1)),
+            Correspondence::User { .. } => None,
+        }
+    }
+
+    /// Every answer stored for this turn in display order.
+    pub fn response_variants(&self) -> Vec<ResponseVariant> {
+        let Correspondence::Bot {
+            text,
+            model,
+            thinking_seconds,
+            sources,
+            web_search_used,
+            stats,
+            format_check,
+            alternatives,
+            active_variant,
+        } = self
+        else {
+            return Vec::new();
+        };
+        let mut variants = alternatives.clone();
+        variants.insert(
+            (*active_variant).min(variants.len()),
+            ResponseVariant {
+                text: text.clone(),
+                model: model.clone(),
+                thinking_seconds: *thinking_seconds,
+                sources: sources.clone(),
+                web_search_used: *web_search_used,
+                stats: *stats,
+                format_check: format_check.clone(),
+            },
+        );
+        variants
+    }
+
+    /// Makes `variant` the displayed answer. Returns false if nothing changed.
+    pub fn select_response_variant(&mut self, variant: usize) -> bool {
+        let mut variants = self.response_variants();
+        let Correspondence::Bot {
+            text,
+            model,
+            thinking_seconds,
+            sources,
+            web_search_used,
+            stats,
+            format_check,
+            alternatives,
+            active_variant,
+        } = self
+        else {
+            return false;
+        };
+        if variant == *active_variant || variant >= variants.len() {
+            return false;
+        }
+        let selected = variants.remove(variant);
+        *text = selected.text;
+        *model = selected.model;
+        *thinking_seconds = selected.thinking_seconds;
+        *sources = selected.sources;
+        *web_search_used = selected.web_search_used;
+        *stats = selected.stats;
+        *format_check = selected.format_check;
+        *alternatives = variants;
+        *active_variant = variant;
+        true
+    }
+
+    /// Keeps the answers of `previous` as earlier variants of this answer,
+    /// which stays active as the newest one.
+    pub fn append_to_variants_of(&mut self, previous: &Correspondence) {
+        let earlier = previous.response_variants();
+        if let Correspondence::Bot {
+            alternatives,
+            active_variant,
+            ..
+        } = self
+        {
+            *active_variant = earlier.len();
+            *alternatives = earlier;
+        }
+    }
+}
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(tag = "role", content = "text", rename_all = "lowercase")]
 pub enum StoredMessage {
@@ -38,6 +235,127 @@ pub struct SavedChat {
     #[serde(default)]
     pub pinned: bool,
     pub context: Vec<String>,
+    /// The visible path stays flattened so older chat files remain readable.
+    #[serde(flatten)]
+    pub thread: SavedThread,
+    /// `None` lets chats saved before 0.5.2 inherit the global default.
+    #[serde(default)]
+    pub web_search_enabled: Option<bool>,
+    #[serde(default)]
+    pub context_summary: Option<ContextSummary>,
+    /// Empty for chats saved before settings were remembered per chat.
+    #[serde(default)]
+    pub settings: ChatSettings,
+}
+
+/// The generation settings a chat was last used with. Unset fields inherit
+/// whatever is currently selected.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ChatSettings {
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub temperature: Option<f32>,
+    pub thinking_level: Option<ThinkingLevel>,
+    pub max_response_tokens: Option<u32>,
+    pub context_tokens: Option<u32>,
+    /// The structured-output panel text. Unlike the fields above it is never
+    /// inherited, so a schema stays with the chat it was written for.
+    pub response_format: Option<String>,
+    /// This chat's keep-alive override. Also never inherited; `None` uses the
+    /// global keep-alive setting.
+    pub keep_alive: Option<String>,
+}
+
+impl ChatSettings {
+    pub fn capture(user_info: &UserInformation, system_prompt: &SystemPrompt) -> Self {
+        Self {
+            model: user_info.model.clone(),
+            system_prompt: system_prompt.system_prompt.clone(),
+            temperature: Some(user_info.temperature),
+            thinking_level: Some(user_info.thinking_level),
+            max_response_tokens: Some(user_info.max_response_tokens),
+            context_tokens: Some(user_info.context_tokens),
+            response_format: user_info.response_format.clone(),
+            keep_alive: user_info.keep_alive.clone(),
+        }
+    }
+
+    /// Applies every remembered value except the model, which needs a
+    /// capability lookup and is switched by the caller.
+    pub fn apply(&self, user_info: &mut UserInformation, system_prompt: &mut SystemPrompt) {
+        if let Some(prompt) = &self.system_prompt
+            && system_prompt.system_prompts_as_hashmap.contains_key(prompt)
+        {
+            system_prompt.system_prompt = Some(prompt.clone());
+        }
+        if let Some(temperature) = self.temperature {
+            user_info.temperature = temperature;
+        }
+        if let Some(level) = self.thinking_level {
+            user_info.thinking_level = level;
+        }
+        if let Some(tokens) = self.max_response_tokens {
+            user_info.max_response_tokens = tokens;
+        }
+        if let Some(tokens) = self.context_tokens {
+            user_info.context_tokens = tokens;
+        }
+        user_info.response_format = self.response_format.clone();
+        user_info.keep_alive = self.keep_alive.clone();
+    }
+
+    /// Settings remembered by this chat that differ from `defaults`, as
+    /// `(label, value)` pairs for the chat header.
+    pub fn overrides(&self, defaults: &ChatSettings) -> Vec<(&'static str, String)> {
+        let mut overrides = Vec::new();
+        if self.model.is_some() && self.model != defaults.model {
+            overrides.push(("Model", self.model.clone().unwrap_or_default()));
+        }
+        if self.system_prompt.is_some() && self.system_prompt != defaults.system_prompt {
+            overrides.push((
+                "System prompt",
+                self.system_prompt.clone().unwrap_or_default(),
+            ));
+        }
+        if let Some(temperature) = self.temperature
+            && self.temperature != defaults.temperature
+        {
+            overrides.push(("Temperature", format!("{:.1}", temperature / 10.0)));
+        }
+        if let Some(level) = self.thinking_level
+            && self.thinking_level != defaults.thinking_level
+        {
+            overrides.push(("Thinking", level.to_string()));
+        }
+        if let Some(tokens) = self.max_response_tokens
+            && self.max_response_tokens != defaults.max_response_tokens
+        {
+            overrides.push(("Maximum response", tokens.to_string()));
+        }
+        if let Some(tokens) = self.context_tokens
+            && self.context_tokens != defaults.context_tokens
+        {
+            overrides.push(("Context window", tokens.to_string()));
+        }
+        if let Some(format) = &self.response_format {
+            let kind = if format.trim().eq_ignore_ascii_case("json") {
+                "JSON"
+            } else {
+                "JSON Schema"
+            };
+            overrides.push(("Structured output", kind.to_string()));
+        }
+        if let Some(keep_alive) = &self.keep_alive {
+            overrides.push(("Keep alive", keep_alive.clone()));
+        }
+        overrides
+    }
+}
+
+/// A stored run of messages. Inactive branches nest further threads.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct SavedThread {
     pub messages: Vec<StoredMessage>,
     /// Metadata is kept separately so older `role`/`text` chat files remain readable.
     #[serde(default)]
@@ -48,42 +366,52 @@ pub struct SavedChat {
     pub sources: Vec<Vec<WebSource>>,
     #[serde(default)]
     pub web_search_used: Vec<bool>,
-    /// `None` lets chats saved before 0.5.2 inherit the global default.
     #[serde(default)]
-    pub web_search_enabled: Option<bool>,
+    pub stats: Vec<Option<GenerationStats>>,
+    #[serde(default)]
+    pub format_checks: Vec<Option<FormatCheck>>,
+    /// Inactive regenerated answers per message, parallel to `messages`.
+    #[serde(default)]
+    pub alternatives: Vec<Vec<ResponseVariant>>,
+    #[serde(default)]
+    pub active_variants: Vec<usize>,
+    /// Inactive edit branches per message, parallel to `messages`.
+    #[serde(default)]
+    pub branches: Vec<Vec<SavedThread>>,
+    #[serde(default)]
+    pub active_branches: Vec<usize>,
+    /// Attachments per message, parallel to `messages`.
+    #[serde(default)]
+    pub images: Vec<Vec<StoredImage>>,
 }
 
-impl SavedChat {
-    pub fn from_current(
-        id: String,
-        title: String,
-        chat: &CurrentChat,
-        web_search_enabled: bool,
-    ) -> Self {
+/// An attachment saved beside the chat file under the SHA-256 of its bytes,
+/// so identical images shared by several chats are written once.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct StoredImage {
+    pub name: String,
+    pub mime_type: String,
+    pub digest: String,
+}
+
+impl SavedThread {
+    pub fn from_messages(messages: &[Correspondence]) -> Self {
         Self {
-            id,
-            title,
-            updated_at: Local::now().to_rfc3339(),
-            pinned: false,
-            context: chat.chats.clone(),
-            messages: chat
-                .messages
+            messages: messages
                 .iter()
                 .map(|message| match message {
                     Correspondence::User { text, .. } => StoredMessage::User(text.clone()),
                     Correspondence::Bot { text, .. } => StoredMessage::Bot(text.clone()),
                 })
                 .collect(),
-            models: chat
-                .messages
+            models: messages
                 .iter()
                 .map(|message| match message {
                     Correspondence::Bot { model, .. } => model.clone(),
                     Correspondence::User { .. } => None,
                 })
                 .collect(),
-            thinking_seconds: chat
-                .messages
+            thinking_seconds: messages
                 .iter()
                 .map(|message| match message {
                     Correspondence::Bot {
@@ -92,16 +420,14 @@ impl SavedChat {
                     Correspondence::User { .. } => None,
                 })
                 .collect(),
-            sources: chat
-                .messages
+            sources: messages
                 .iter()
                 .map(|message| match message {
                     Correspondence::Bot { sources, .. } => sources.clone(),
                     Correspondence::User { .. } => Vec::new(),
                 })
                 .collect(),
-            web_search_used: chat
-                .messages
+            web_search_used: messages
                 .iter()
                 .map(|message| {
                     matches!(
@@ -113,39 +439,164 @@ impl SavedChat {
                     )
                 })
                 .collect(),
+            stats: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::Bot { stats, .. } => *stats,
+                    Correspondence::User { .. } => None,
+                })
+                .collect(),
+            format_checks: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::Bot { format_check, .. } => format_check.clone(),
+                    Correspondence::User { .. } => None,
+                })
+                .collect(),
+            alternatives: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::Bot { alternatives, .. } => alternatives.clone(),
+                    Correspondence::User { .. } => Vec::new(),
+                })
+                .collect(),
+            active_variants: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::Bot { active_variant, .. } => *active_variant,
+                    Correspondence::User { .. } => 0,
+                })
+                .collect(),
+            branches: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::User { branches, .. } => branches
+                        .iter()
+                        .map(|branch| Self::from_messages(branch))
+                        .collect(),
+                    Correspondence::Bot { .. } => Vec::new(),
+                })
+                .collect(),
+            active_branches: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::User { active_branch, .. } => *active_branch,
+                    Correspondence::Bot { .. } => 0,
+                })
+                .collect(),
+            images: messages
+                .iter()
+                .map(|message| match message {
+                    Correspondence::User { images, .. } => images
+                        .iter()
+                        .map(|image| StoredImage {
+                            name: image.name.clone(),
+                            mime_type: image.mime_type.clone(),
+                            digest: image.digest.clone(),
+                        })
+                        .collect(),
+                    Correspondence::Bot { .. } => Vec::new(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Restores the messages, reading attachments from `image_dir`. Images
+    /// whose files are missing are dropped rather than failing the chat.
+    pub fn to_messages(&self, image_dir: &Path) -> Vec<Correspondence> {
+        self.messages
+            .iter()
+            .enumerate()
+            .map(|(index, message)| match message {
+                StoredMessage::User(text) => Correspondence::User {
+                    text: text.clone(),
+                    images: self
+                        .images
+                        .get(index)
+                        .map(|images| {
+                            images
+                                .iter()
+                                .filter_map(|image| ChatImage::load_stored(image_dir, image))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    branches: self
+                        .branches
+                        .get(index)
+                        .map(|branches| {
+                            branches
+                                .iter()
+                                .map(|branch| branch.to_messages(image_dir))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    active_branch: self.active_branches.get(index).copied().unwrap_or(0),
+                },
+                StoredMessage::Bot(text) => Correspondence::Bot {
+                    text: text.clone(),
+                    model: self.models.get(index).cloned().flatten(),
+                    thinking_seconds: self.thinking_seconds.get(index).copied().flatten(),
+                    sources: self.sources.get(index).cloned().unwrap_or_default(),
+                    web_search_used: self.web_search_used.get(index).copied().unwrap_or(false),
+                    stats: self.stats.get(index).copied().flatten(),
+                    format_check: self.format_checks.get(index).cloned().flatten(),
+                    alternatives: self.alternatives.get(index).cloned().unwrap_or_default(),
+                    active_variant: self.active_variants.get(index).copied().unwrap_or(0),
+                },
+            })
+            .collect()
+    }
+
+    /// Adds every attachment digest in this thread and its branches.
+    pub fn collect_image_digests(&self, digests: &mut HashSet<String>) {
+        for image in self.images.iter().flatten() {
+            digests.insert(image.digest.clone());
+        }
+        for branch in self.branches.iter().flatten() {
+            branch.collect_image_digests(digests);
+        }
+    }
+}
+
+impl SavedChat {
+    pub fn from_current(
+        id: String,
+        title: String,
+        chat: &CurrentChat,
+        web_search_enabled: bool,
+        settings: ChatSettings,
+    ) -> Self {
+        Self {
+            id,
+            title,
+            updated_at: Local::now().to_rfc3339(),
+            pinned: false,
+            context: chat.chats.clone(),
+            thread: SavedThread::from_messages(&chat.messages),
             web_search_enabled: Some(web_search_enabled),
+            context_summary: chat.context_summary.clone(),
+            settings,
         }
     }
 
-    pub fn to_current(&self) -> CurrentChat {
+    pub fn to_current(&self, image_dir: &Path) -> CurrentChat {
         CurrentChat {
             chats: self.context.clone(),
-            messages: self
-                .messages
-                .iter()
-                .enumerate()
-                .map(|(index, message)| match message {
-                    StoredMessage::User(text) => Correspondence::User {
-                        text: text.clone(),
-                        images: Vec::new(),
-                    },
-                    StoredMessage::Bot(text) => Correspondence::Bot {
-                        text: text.clone(),
-                        model: self.models.get(index).cloned().flatten(),
-                        thinking_seconds: self.thinking_seconds.get(index).copied().flatten(),
-                        sources: self.sources.get(index).cloned().unwrap_or_default(),
-                        web_search_used: self.web_search_used.get(index).copied().unwrap_or(false),
-                    },
-                })
-                .collect(),
+            messages: self.thread.to_messages(image_dir),
             bot_responding: false,
+            context_summary: self.context_summary.clone(),
         }
     }
 }
 
 #[cfg(test)]
 mod saved_chat_tests {
-    use super::{Correspondence, CurrentChat, SavedChat};
+    use std::path::Path;
+
+    use super::{
+        ChatSettings, Correspondence, CurrentChat, FormatCheck, GenerationStats, SavedChat,
+        ThinkingLevel,
+    };
 
     #[test]
     fn old_saved_chats_default_to_unpinned() {
@@ -159,11 +610,55 @@ mod saved_chat_tests {
 
         let chat: SavedChat = serde_json::from_str(json).unwrap();
         assert!(!chat.pinned);
-        assert!(chat.models.is_empty());
-        assert!(chat.thinking_seconds.is_empty());
-        assert!(chat.sources.is_empty());
-        assert!(chat.web_search_used.is_empty());
+        assert!(chat.thread.models.is_empty());
+        assert!(chat.thread.thinking_seconds.is_empty());
+        assert!(chat.thread.sources.is_empty());
+        assert!(chat.thread.web_search_used.is_empty());
+        assert!(chat.thread.stats.is_empty());
         assert_eq!(chat.web_search_enabled, None);
+        assert!(chat.thread.alternatives.is_empty());
+        assert!(chat.thread.active_variants.is_empty());
+        assert!(chat.thread.branches.is_empty());
+        assert!(chat.thread.active_branches.is_empty());
+        assert!(chat.thread.images.is_empty());
+        assert_eq!(chat.settings, ChatSettings::default());
+    }
+
+    #[test]
+    fn chat_settings_round_trip_and_report_only_differences() {
+        let settings = ChatSettings {
+            model: Some("coder".into()),
+            system_prompt: Some("Code reviewer".into()),
+            temperature: Some(2.0),
+            thinking_level: Some(ThinkingLevel::High),
+            max_response_tokens: Some(4_096),
+            context_tokens: Some(32_768),
+            response_format: None,
+            keep_alive: None,
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        assert!(json.contains(r#""thinking_level":"high""#));
+        assert_eq!(
+            serde_json::from_str::<ChatSettings>(&json).unwrap(),
+            settings
+        );
+
+        let defaults = ChatSettings {
+            model: Some("coder".into()),
+            temperature: Some(7.0),
+            ..settings.clone()
+        };
+        let overrides = settings.overrides(&defaults);
+        assert_eq!(overrides, [("Temperature", "0.2".to_string())]);
+        let structured = ChatSettings {
+            response_format: Some("json".into()),
+            ..defaults.clone()
+        };
+        assert_eq!(
+            structured.overrides(&defaults),
+            [("Structured output", "JSON".to_string())]
+        );
+        assert!(ChatSettings::default().overrides(&defaults).is_empty());
     }
 
     #[test]
@@ -171,10 +666,7 @@ mod saved_chat_tests {
         let current = CurrentChat {
             chats: vec![],
             messages: vec![
-                Correspondence::User {
-                    text: "Question".into(),
-                    images: Vec::new(),
-                },
+                Correspondence::user("Question".into(), Vec::new()),
                 Correspondence::Bot {
                     text: "<think>Work</think>Answer".into(),
                     model: Some("model-a".into()),
@@ -184,14 +676,28 @@ mod saved_chat_tests {
                         url: "https://example.com".into(),
                     }],
                     web_search_used: true,
+                    stats: Some(GenerationStats {
+                        eval_count: 42,
+                        ..GenerationStats::default()
+                    }),
+                    format_check: Some(FormatCheck::Invalid(vec!["$ is missing \"age\"".into()])),
+                    alternatives: Vec::new(),
+                    active_variant: 0,
                 },
             ],
             bot_responding: false,
+            context_summary: None,
         };
 
-        let saved = SavedChat::from_current("chat-1".into(), "Question".into(), &current, true);
+        let saved = SavedChat::from_current(
+            "chat-1".into(),
+            "Question".into(),
+            &current,
+            true,
+            ChatSettings::default(),
+        );
         assert_eq!(saved.web_search_enabled, Some(true));
-        let reopened = saved.to_current();
+        let reopened = saved.to_current(Path::new(""));
         assert!(matches!(
             &reopened.messages[1],
             Correspondence::Bot {
@@ -199,8 +705,94 @@ mod saved_chat_tests {
                 thinking_seconds: Some(30),
                 sources,
                 web_search_used: true,
+                stats: Some(GenerationStats { eval_count: 42, .. }),
+                format_check: Some(FormatCheck::Invalid(errors)),
                 ..
-            } if model == "model-a" && sources.len() == 1
+            } if model == "model-a" && sources.len() == 1 && errors.len() == 1
+        ));
+    }
+
+    #[test]
+    fn regenerated_variants_survive_switching_and_saving() {
+        let first = Correspondence::bot("First".into(), Some("model-a".into()));
+        let mut second = Correspondence::bot("Second".into(), Some("model-b".into()));
+        second.append_to_variants_of(&first);
+        assert_eq!(second.variant_position(), Some((1, 2)));
+
+        assert!(second.select_response_variant(0));
+        assert!(matches!(
+            &second,
+            Correspondence::Bot { text, model: Some(model), .. }
+                if text == "First" && model == "model-a"
+        ));
+
+        let current = CurrentChat {
+            chats: vec![],
+            messages: vec![Correspondence::user("Question".into(), Vec::new()), second],
+            bot_responding: false,
+            context_summary: None,
+        };
+        let reopened = SavedChat::from_current(
+            "chat-1".into(),
+            "Question".into(),
+            &current,
+            false,
+            ChatSettings::default(),
+        )
+        .to_current(Path::new(""));
+        assert_eq!(reopened.messages[1].variant_position(), Some((0, 2)));
+        let texts = reopened.messages[1]
+            .response_variants()
+            .into_iter()
+            .map(|variant| variant.text)
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["First", "Second"]);
+    }
+
+    #[test]
+    fn editing_a_turn_keeps_the_old_branch_reachable() {
+        let mut chat = CurrentChat {
+            chats: vec![],
+            messages: vec![
+                Correspondence::user("First".into(), Vec::new()),
+                Correspondence::bot("One".into(), None),
+                Correspondence::user("Second".into(), Vec::new()),
+                Correspondence::bot("Two".into(), None),
+            ],
+            bot_responding: false,
+            context_summary: None,
+        };
+
+        assert!(chat.fork_at(0, "Rephrased".into()));
+        assert_eq!(chat.messages.len(), 1);
+        assert_eq!(chat.messages[0].branch_position(), Some((1, 2)));
+        chat.push_message(Correspondence::bot("New".into(), None));
+        chat.generate_and_push("Rephrased".into(), "New".into());
+
+        let reopened = SavedChat::from_current(
+            "chat-1".into(),
+            "First".into(),
+            &chat,
+            false,
+            ChatSettings::default(),
+        )
+        .to_current(Path::new(""));
+        let mut chat = reopened;
+        assert!(chat.select_branch(0, 0));
+        assert_eq!(chat.messages.len(), 4);
+        assert_eq!(chat.messages[0].branch_position(), Some((0, 2)));
+        assert_eq!(
+            chat.chats,
+            [
+                "User: First\nAI Language Model: One",
+                "User: Second\nAI Language Model: Two"
+            ]
+        );
+
+        assert!(chat.select_branch(0, 1));
+        assert!(matches!(
+            &chat.messages[..],
+            [Correspondence::User { text, .. }, Correspondence::Bot { .. }] if text == "Rephrased"
         ));
     }
 }
@@ -214,6 +806,51 @@ pub struct ChatImage {
     /// Recreating a handle in every `view` assigns a new cache id each frame,
     /// which can make previews flash and then disappear.
     pub preview_handle: iced::widget::image::Handle,
+    /// SHA-256 of `bytes` in hex, the attachment's file name in the image
+    /// folder. Computed once when the image is loaded, not on every save.
+    pub digest: String,
+}
+
+impl ChatImage {
+    pub fn new(
+        name: String,
+        mime_type: String,
+        bytes: Vec<u8>,
+        preview_handle: iced::widget::image::Handle,
+    ) -> Self {
+        let digest = Sha256::digest(&bytes)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        Self {
+            name,
+            mime_type,
+            bytes,
+            preview_handle,
+            digest,
+        }
+    }
+
+    pub fn load_stored(image_dir: &Path, stored: &StoredImage) -> Option<Self> {
+        // chats.json can be edited by hand; only a digest names a stored file.
+        let is_digest = stored.digest.len() == 64
+            && stored
+                .digest
+                .bytes()
+                .all(|byte| matches!(byte, b'0'..=b'9' | b'a'..=b'f'));
+        if !is_digest {
+            return None;
+        }
+        let bytes = fs::read(image_dir.join(&stored.digest)).ok()?;
+        let preview_handle = crate::decoded_image_handle(&bytes).ok()?;
+        Some(Self {
+            name: stored.name.clone(),
+            mime_type: stored.mime_type.clone(),
+            bytes,
+            preview_handle,
+            digest: stored.digest.clone(),
+        })
+    }
 }
 
 #[derive(Clone)]
@@ -273,6 +910,8 @@ pub struct CurrentChat {
     pub chats: Vec<String>,
     pub messages: Vec<Correspondence>,
     pub bot_responding: bool,
+    /// Stands in for the oldest messages once they no longer fit the context window.
+    pub context_summary: Option<ContextSummary>,
 }
 impl CurrentChat {
     fn push_chat(&mut self, chat: String) {
@@ -288,13 +927,118 @@ impl CurrentChat {
         let new_message = Self::generate_new_message(user_message, bot_response);
         self.push_chat(new_message);
     }
-    pub fn unravel(&self) -> String {
-        self.chats.join("\n")
-    }
-
     pub fn push_message(&mut self, correspondence: Correspondence) {
         self.messages.push(correspondence);
     }
+
+    /// Rebuilds `chats` from the visible path, pairing each user turn with
+    /// the answer that follows it.
+    pub fn rebuild_context(&mut self) {
+        let mut chats = Vec::new();
+        let mut user_text = None;
+        for message in &self.messages {
+            match message {
+                Correspondence::User { text, .. } => user_text = Some(text.clone()),
+                Correspondence::Bot { text, .. } => {
+                    if let Some(user_text) = user_text.take() {
+                        let (_, visible) = crate::split_thinking_text(text);
+                        chats.push(Self::generate_new_message(user_text, visible));
+                    }
+                }
+            }
+        }
+        self.chats = chats;
+    }
+
+    /// Drops everything after the user turn at `index` and returns the answer
+    /// that directly followed it.
+    pub fn take_response_after(&mut self, index: usize) -> Option<Correspondence> {
+        self.forget_summary_from(index + 1);
+        let removed = self
+            .messages
+            .split_off((index + 1).min(self.messages.len()));
+        self.rebuild_context();
+        removed
+            .into_iter()
+            .next()
+            .filter(|message| matches!(message, Correspondence::Bot { .. }))
+    }
+
+    /// Replaces the user turn at `index` with `text` on a new branch. The
+    /// previous path from that turn onwards stays reachable as a sibling.
+    pub fn fork_at(&mut self, index: usize, text: String) -> bool {
+        let Some(Correspondence::User { images, .. }) = self.messages.get(index) else {
+            return false;
+        };
+        let images = images.clone();
+        self.forget_summary_from(index);
+        let branches = self.take_branches_at(index);
+        self.messages.push(Correspondence::User {
+            text,
+            images,
+            active_branch: branches.len(),
+            branches,
+        });
+        self.rebuild_context();
+        true
+    }
+
+    /// Shows `branch` of the user turn at `index`. Returns false if nothing changed.
+    pub fn select_branch(&mut self, index: usize, branch: usize) -> bool {
+        let Some((active, total)) = self
+            .messages
+            .get(index)
+            .and_then(Correspondence::branch_position)
+        else {
+            return false;
+        };
+        if branch == active || branch >= total {
+            return false;
+        }
+        self.forget_summary_from(index);
+        let mut branches = self.take_branches_at(index);
+        let mut selected = branches.remove(branch);
+        if let Some(Correspondence::User {
+            branches: siblings,
+            active_branch,
+            ..
+        }) = selected.first_mut()
+        {
+            *siblings = branches;
+            *active_branch = branch;
+        }
+        self.messages.extend(selected);
+        self.rebuild_context();
+        true
+    }
+
+    /// Drops the summary if it covers messages from `index` on, which were
+    /// just replaced by another variant or branch.
+    pub fn forget_summary_from(&mut self, index: usize) {
+        if self
+            .context_summary
+            .as_ref()
+            .is_some_and(|summary| summary.covers > index)
+        {
+            self.context_summary = None;
+        }
+    }
+
+    /// Removes the path from `index` and returns every branch at that turn,
+    /// with the removed path back in its position.
+    fn take_branches_at(&mut self, index: usize) -> Vec<Vec<Correspondence>> {
+        let mut current = self.messages.split_off(index);
+        let (mut branches, active) = match current.first_mut() {
+            Some(Correspondence::User {
+                branches,
+                active_branch,
+                ..
+            }) => (std::mem::take(branches), std::mem::take(active_branch)),
+            _ => (Vec::new(), 0),
+        };
+        branches.insert(active.min(branches.len()), current);
+        branches
+    }
 }
 
 // AppState keeps information on certain important information
@@ -378,7 +1122,7 @@ impl SystemPrompt {
         let system_prompt_as_string: String = match system_prompt.system_prompt {
             Some(system_prompt) => system_prompt,
             None => {
-                println!("Error getting system prompt");
+                eprintln!("Error getting system prompt");
                 Channels::send_request_to_channel(
                     Arc::clone(&program.channels.debug_channel),
                     DebugMessage {
@@ -399,7 +1143,7 @@ impl SystemPrompt {
                 .get(&system_prompt_as_string)
                 .cloned()
         } else {
-            println!("system prompt is None");
+            eprintln!("system prompt is None");
             Channels::send_request_to_channel(
                 Arc::clone(&program.channels.debug_channel),
                 DebugMessage {
@@ -412,10 +1156,177 @@ impl SystemPrompt {
     }
 }
 
-#[derive(Clone, Debug)]
+/// A named Ollama server. The active one lives in `UserInformation`; all of
+/// them are kept in settings.json.
+#[derive(Clone, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct HostLocation {
+    pub name: String,
+    /// The API the server speaks.
+    pub backend: BackendKind,
+    /// `http` or `https`.
+    pub scheme: String,
     pub ip: String,
     pub port: String,
+    /// Sent as a bearer token with every request when set.
+    pub api_key: Option<String>,
+    /// PEM file of extra certificate authorities trusted for this server,
+    /// for proxies with a private or self-signed certificate.
+    pub ca_certificate: Option<String>,
+    /// PEM file holding a client certificate and its private key.
+    pub client_certificate: Option<String>,
+    /// Sent with every request, e.g. headers a reverse proxy requires.
+    pub headers: Vec<(String, String)>,
+    /// Models the server listed last, shown while a fresh list loads.
+    pub models: Vec<String>,
+    pub last_model: Option<String>,
+}
+
+impl Default for HostLocation {
+    fn default() -> Self {
+        Self {
+            name: "Local".to_string(),
+            backend: BackendKind::Ollama,
+            scheme: "http".to_string(),
+            ip: "127.0.0.1".to_string(),
+            port: "11434".to_string(),
+            api_key: None,
+            ca_certificate: None,
+            client_certificate: None,
+            headers: Vec::new(),
+            models: Vec::new(),
+            last_model: None,
+        }
+    }
+}
+
+// Credentials must not reach logs through `{:?}`.
+impl fmt::Debug for HostLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HostLocation")
+            .field("name", &self.name)
+            .field("backend", &self.backend)
+            .field("base_url", &self.base_url())
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("ca_certificate", &self.ca_certificate)
+            .field("client_certificate", &self.client_certificate)
+            .field(
+                "headers",
+                &self
+                    .headers
+                    .iter()
+                    .map(|(name, _)| (name.as_str(), "<redacted>"))
+                    .collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Header values shorter than this are not redacted, since masking them
+/// would also mask unrelated text such as status codes.
+const MIN_REDACTED_HEADER_LEN: usize = 4;
+
+impl HostLocation {
+    pub fn base_url(&self) -> String {
+        format!("{}://{}:{}", self.scheme, self.ip, self.port)
+    }
+
+    /// Replaces the API key and extra header values in `text`, for errors
+    /// and logs that may echo a request back.
+    pub fn redact(&self, text: &str) -> String {
+        let secrets = self.api_key.iter().map(|key| key.trim()).chain(
+            self.headers
+                .iter()
+                .map(|(_, value)| value.trim())
+                .filter(|value| value.len() >= MIN_REDACTED_HEADER_LEN),
+        );
+        secrets.fold(text.to_string(), |text, secret| {
+            redact_secret(&text, Some(secret))
+        })
+    }
+
+    fn request_headers(&self) -> Result<reqwest::header::HeaderMap, String> {
+        use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderName, HeaderValue};
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            if name.trim().is_empty() {
+                continue;
+            }
+            let name = HeaderName::from_bytes(name.trim().as_bytes())
+                .map_err(|_| format!("Invalid header name: {}", name.trim()))?;
+            let mut value = HeaderValue::from_str(value.trim())
+                .map_err(|_| format!("Invalid value for header {name}"))?;
+            value.set_sensitive(true);
+            headers.insert(name, value);
+        }
+        if let Some(api_key) = self.api_key.as_deref().filter(|key| !key.trim().is_empty()) {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", api_key.trim()))
+                .map_err(|_| "The API key contains characters a header cannot carry".to_string())?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+
+    fn read_pem(path: &str, what: &str) -> Result<Vec<u8>, String> {
+        fs::read(path).map_err(|error| format!("Could not read the {what} {path}: {error}"))
+    }
+
+    fn root_certificates(&self) -> Result<Vec<reqwest::Certificate>, String> {
+        match self
+            .ca_certificate
+            .as_deref()
+            .filter(|path| !path.is_empty())
+        {
+            Some(path) => {
+                let pem = Self::read_pem(path, "CA certificate")?;
+                reqwest::Certificate::from_pem_bundle(&pem)
+                    .map_err(|error| format!("Invalid CA certificate {path}: {error}"))
+            }
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn identity(&self) -> Result<Option<reqwest::Identity>, String> {
+        match self
+            .client_certificate
+            .as_deref()
+            .filter(|path| !path.is_empty())
+        {
+            Some(path) => {
+                let pem = Self::read_pem(path, "client certificate")?;
+                reqwest::Identity::from_pem(&pem)
+                    .map(Some)
+                    .map_err(|error| format!("Invalid client certificate {path}: {error}"))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// A client for this server's API, carrying its credentials and TLS
+    /// settings. Fails when a certificate or header cannot be used.
+    pub fn client(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .default_headers(self.request_headers()?)
+            .tls_certs_merge(self.root_certificates()?);
+        if let Some(identity) = self.identity()? {
+            builder = builder.identity(identity);
+        }
+        builder.build().map_err(|error| error.to_string())
+    }
+
+    /// A blocking client without a timeout, for uploads that take minutes.
+    pub fn blocking_client(&self) -> Result<reqwest::blocking::Client, String> {
+        let mut builder = reqwest::blocking::Client::builder()
+            .default_headers(self.request_headers()?)
+            .tls_certs_merge(self.root_certificates()?)
+            .timeout(None);
+        if let Some(identity) = self.identity()? {
+            builder = builder.identity(identity);
+        }
+        builder.build().map_err(|error| error.to_string())
+    }
 }
 
 // UserInformation saves certain important information about the program specific to the current user
@@ -439,6 +1350,10 @@ pub struct UserInformation {
     pub current_chat_history_enabled: bool,
     pub ip_address: HostLocation,
     pub language: Language,
+    /// Text of the open chat's structured-output panel, if it is in use.
+    pub response_format: Option<String>,
+    /// The open chat's keep-alive override, if it has one.
+    pub keep_alive: Option<String>,
 }
 
 #[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
@@ -458,7 +1373,8 @@ impl fmt::Display for Language {
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ThinkingLevel {
     #[default]
     Off,
@@ -592,3 +1508,58 @@ mod dynamic_prompt_tests {
         assert!(prompt.contains("Prefer concise answers."));
     }
 }
+
+#[cfg(test)]
+mod host_location_tests {
+    use super::HostLocation;
+
+    fn proxied_host() -> HostLocation {
+        HostLocation {
+            scheme: "https".to_string(),
+            api_key: Some("sk-secret-token".to_string()),
+            headers: vec![
+                ("X-Proxy-Token".to_string(), "proxy-pass".to_string()),
+                ("X-Tenant".to_string(), "a".to_string()),
+            ],
+            ..HostLocation::default()
+        }
+    }
+
+    #[test]
+    fn credentials_are_redacted_from_diagnostics() {
+        let host = proxied_host();
+        assert_eq!(
+            host.redact("401 for Bearer sk-secret-token and proxy-pass at a"),
+            "401 for Bearer <redacted> and <redacted> at a"
+        );
+        let debug = format!("{host:?}");
+        assert!(!debug.contains("sk-secret-token"));
+        assert!(!debug.contains("proxy-pass"));
+        assert!(debug.contains("https://127.0.0.1:11434"));
+    }
+
+    #[test]
+    fn unusable_headers_and_certificates_are_reported() {
+        assert!(proxied_host().client().is_ok());
+
+        let bad_header = HostLocation {
+            headers: vec![("Bad Header".to_string(), "value".to_string())],
+            ..HostLocation::default()
+        };
+        assert_eq!(
+            bad_header.client().unwrap_err(),
+            "Invalid header name: Bad Header"
+        );
+
+        let missing_ca = HostLocation {
+            ca_certificate: Some("/nonexistent/ca.pem".to_string()),
+            ..HostLocation::default()
+        };
+        assert!(
+            missing_ca
+                .client()
+                .unwrap_err()
+                .starts_with("Could not read the CA certificate")
+        );
+    }
+}
@@ -0,0 +1,639 @@
+//! The servers chats can run on.
+//!
+//! The rest of the app speaks Ollama's `/api/chat` format: requests are built
+//! as Ollama bodies and streamed replies are read as Ollama NDJSON lines. An
+//! OpenAI-compatible server (llama.cpp, vLLM, …) is supported by translating
+//! at this boundary, so the chat and web-search loops stay backend-agnostic.
+
+use std::{collections::VecDeque, fmt, sync::Arc};
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{app::HostLocation, model_manager};
+
+const VERSION_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BackendKind {
+    #[default]
+    Ollama,
+    OpenAiCompatible,
+}
+
+impl BackendKind {
+    pub const ALL: [Self; 2] = [Self::Ollama, Self::OpenAiCompatible];
+}
+
+impl fmt::Display for BackendKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ollama => "Ollama",
+            Self::OpenAiCompatible => "OpenAI-compatible",
+        })
+    }
+}
+
+#[async_trait]
+pub trait ModelBackend: Send + Sync {
+    /// Names of the models the server offers.
+    async fn list_models(&self) -> Result<Vec<String>, String>;
+
+    /// `/api/show` details for capability detection, or `None` when the
+    /// server does not describe its models.
+    async fn model_info(&self, model: &str) -> Option<Value>;
+
+    /// A short health summary such as `Online (v0.12.3)` or `Offline`.
+    async fn status(&self) -> String;
+
+    /// Prepares a chat request from an Ollama `/api/chat` body.
+    fn chat_request(&self, body: &Value) -> Result<reqwest::RequestBuilder, String>;
+
+    /// A decoder turning the streamed reply into Ollama chat lines.
+    fn chat_stream(&self) -> ChatStream;
+
+    /// Converts a complete, non-streamed reply into an Ollama chat object.
+    fn chat_reply(&self, reply: Value) -> Value;
+}
+
+pub fn for_host(host: &HostLocation) -> Arc<dyn ModelBackend> {
+    match host.backend {
+        BackendKind::Ollama => Arc::new(OllamaBackend { host: host.clone() }),
+        BackendKind::OpenAiCompatible => Arc::new(OpenAiBackend { host: host.clone() }),
+    }
+}
+
+/// The message of an error reply. Ollama sends `{"error": "…"}`, OpenAI
+/// servers `{"error": {"message": "…"}}`.
+pub fn error_detail(body: &str) -> Option<String> {
+    let value = serde_json::from_str::<Value>(body).ok()?;
+    let error = value.get("error")?;
+    error
+        .as_str()
+        .or_else(|| error.get("message")?.as_str())
+        .map(str::to_string)
+}
+
+pub struct OllamaBackend {
+    host: HostLocation,
+}
+
+#[async_trait]
+impl ModelBackend for OllamaBackend {
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let models = model_manager::list_models(self.host.clone()).await?;
+        Ok(models.into_iter().map(|model| model.name).collect())
+    }
+
+    async fn model_info(&self, model: &str) -> Option<Value> {
+        let response = self
+            .host
+            .client()
+            .ok()?
+            .post(format!("{}/api/show", self.host.base_url()))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.json::<Value>().await.ok()
+    }
+
+    async fn status(&self) -> String {
+        let client = match self.host.client() {
+            Ok(client) => client,
+            Err(error) => return format!("Offline ({error})"),
+        };
+        let response = match client
+            .get(format!("{}/api/version", self.host.base_url()))
+            .timeout(VERSION_TIMEOUT)
+            .send()
+            .await
+        {
+            Ok(response) => response,
+            Err(error) => {
+                println!(
+                    "Failed to reach API at {}: {}",
+                    self.host.base_url(),
+                    self.host.redact(&error.to_string())
+                );
+                return "Offline".to_string();
+            }
+        };
+        if !response.status().is_success() {
+            return "Offline".to_string();
+        }
+        match response.json::<Value>().await {
+            Ok(json) => match json.get("version").and_then(Value::as_str) {
+                Some(version) => format!("Online (v{version})"),
+                None => "Online (unknown version)".to_string(),
+            },
+            Err(_) => "Online (version parse error)".to_string(),
+        }
+    }
+
+    fn chat_request(&self, body: &Value) -> Result<reqwest::RequestBuilder, String> {
+        Ok(self
+            .host
+            .client()?
+            .post(format!("{}/api/chat", self.host.base_url()))
+            .json(body))
+    }
+
+    fn chat_stream(&self) -> ChatStream {
+        ChatStream::new(StreamFormat::Ndjson)
+    }
+
+    fn chat_reply(&self, reply: Value) -> Value {
+        reply
+    }
+}
+
+pub struct OpenAiBackend {
+    host: HostLocation,
+}
+
+#[async_trait]
+impl ModelBackend for OpenAiBackend {
+    async fn list_models(&self) -> Result<Vec<String>, String> {
+        let response = self
+            .host
+            .client()?
+            .get(format!("{}/v1/models", self.host.base_url()))
+            .send()
+            .await
+            .map_err(|error| error.to_string())?;
+        let status = response.status();
+        let body = response.text().await.map_err(|error| error.to_string())?;
+        if !status.is_success() {
+            return Err(error_detail(&body).unwrap_or_else(|| format!("HTTP {status}")));
+        }
+        let body = serde_json::from_str::<Value>(&body).map_err(|error| error.to_string())?;
+        Ok(body["data"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|model| model["id"].as_str().map(str::to_string))
+            .collect())
+    }
+
+    async fn model_info(&self, _model: &str) -> Option<Value> {
+        None
+    }
+
+    async fn status(&self) -> String {
+        // There is no version endpoint; listing models proves the server
+        // answers and accepts the credentials.
+        match tokio::time::timeout(VERSION_TIMEOUT, self.list_models()).await {
+            Ok(Ok(_)) => "Online (OpenAI-compatible)".to_string(),
+            _ => "Offline".to_string(),
+        }
+    }
+
+    fn chat_request(&self, body: &Value) -> Result<reqwest::RequestBuilder, String> {
+        Ok(self
+            .host
+            .client()?
+            .post(format!("{}/v1/chat/completions", self.host.base_url()))
+            .json(&openai_chat_body(body)))
+    }
+
+    fn chat_stream(&self) -> ChatStream {
+        ChatStream::new(StreamFormat::ServerSentEvents)
+    }
+
+    fn chat_reply(&self, reply: Value) -> Value {
+        let message = &reply["choices"][0]["message"];
+        let mut line = chat_line(
+            message["content"].as_str().unwrap_or_default(),
+            reasoning_text(message),
+        );
+        line["done"] = Value::Bool(true);
+        add_usage(&mut line, reply.get("usage"));
+        line
+    }
+}
+
+/// Options with a direct OpenAI (or llama.cpp/vLLM extension) equivalent.
+/// Ollama-only ones such as `num_ctx` and `mirostat` have none and are left out.
+const OPENAI_OPTIONS: [&str; 9] = [
+    "temperature",
+    "top_p",
+    "top_k",
+    "min_p",
+    "repeat_penalty",
+    "presence_penalty",
+    "frequency_penalty",
+    "seed",
+    "stop",
+];
+
+/// Translates an Ollama `/api/chat` body into a `/v1/chat/completions` one.
+pub fn openai_chat_body(body: &Value) -> Value {
+    let mut request = serde_json::json!({
+        "model": body["model"],
+        "messages": openai_messages(body["messages"].as_array().map_or(&[], Vec::as_slice)),
+        "stream": body["stream"].as_bool().unwrap_or(false),
+    });
+    if request["stream"] == true {
+        request["stream_options"] = serde_json::json!({ "include_usage": true });
+    }
+    if let Some(options) = body["options"].as_object() {
+        for key in OPENAI_OPTIONS {
+            if let Some(value) = options.get(key) {
+                request[key] = value.clone();
+            }
+        }
+        if let Some(limit) = options.get("num_predict").and_then(Value::as_i64)
+            && limit > 0
+        {
+            request["max_tokens"] = limit.into();
+        }
+    }
+    if let Some(tools) = body.get("tools") {
+        request["tools"] = tools.clone();
+    }
+    match body.get("format") {
+        Some(Value::String(format)) if format == "json" => {
+            request["response_format"] = serde_json::json!({ "type": "json_object" });
+        }
+        Some(schema @ Value::Object(_)) => {
+            request["response_format"] = serde_json::json!({
+                "type": "json_schema",
+                "json_schema": { "name": "response", "schema": schema },
+            });
+        }
+        _ => {}
+    }
+    if let Some(effort) = body["think"].as_str() {
+        request["reasoning_effort"] = effort.into();
+    }
+    request
+}
+
+fn openai_messages(messages: &[Value]) -> Vec<Value> {
+    // Ollama answers tool calls by position; OpenAI by call id.
+    let mut pending_call_ids = VecDeque::new();
+    messages
+        .iter()
+        .enumerate()
+        .map(|(position, message)| {
+            let role = message["role"].as_str().unwrap_or("user");
+            let content = message["content"].as_str().unwrap_or_default();
+            match role {
+                "tool" => serde_json::json!({
+                    "role": "tool",
+                    "tool_call_id": pending_call_ids.pop_front().unwrap_or_default(),
+                    "content": content,
+                }),
+                "assistant" => {
+                    let mut translated = serde_json::json!({
+                        "role": "assistant",
+                        "content": content,
+                    });
+                    let calls = message["tool_calls"]
+                        .as_array()
+                        .map_or(&[][..], Vec::as_slice);
+                    if !calls.is_empty() {
+                        translated["tool_calls"] = calls
+                            .iter()
+                            .enumerate()
+                            .map(|(index, call)| {
+                                let id = call["id"].as_str().map_or_else(
+                                    || format!("call_{position}_{index}"),
+                                    str::to_string,
+                                );
+                                pending_call_ids.push_back(id.clone());
+                                let arguments = match &call["function"]["arguments"] {
+                                    Value::String(arguments) => arguments.clone(),
+                                    arguments => arguments.to_string(),
+                                };
+                                serde_json::json!({
+                                    "id": id,
+                                    "type": "function",
+                                    "function": {
+                                        "name": call["function"]["name"],
+                                        "arguments": arguments,
+                                    },
+                                })
+                            })
+                            .collect();
+                    }
+                    translated
+                }
+                _ => {
+                    let images = message["images"].as_array().map_or(&[][..], Vec::as_slice);
+                    if images.is_empty() {
+                        return serde_json::json!({ "role": role, "content": content });
+                    }
+                    let mut parts = vec![serde_json::json!({ "type": "text", "text": content })];
+                    parts.extend(images.iter().filter_map(Value::as_str).map(|image| {
+                        serde_json::json!({
+                            "type": "image_url",
+                            "image_url": {
+                                "url": format!("data:{};base64,{image}", image_mime_type(image)),
+                            },
+                        })
+                    }));
+                    serde_json::json!({ "role": role, "content": parts })
+                }
+            }
+        })
+        .collect()
+}
+
+/// Guesses the type of base64 image data from its first bytes.
+fn image_mime_type(base64: &str) -> &'static str {
+    if base64.starts_with("/9j/") {
+        "image/jpeg"
+    } else if base64.starts_with("R0lGOD") {
+        "image/gif"
+    } else if base64.starts_with("UklGR") {
+        "image/webp"
+    } else {
+        "image/png"
+    }
+}
+
+fn reasoning_text(message: &Value) -> &str {
+    // llama.cpp and vLLM name the field differently.
+    message["reasoning_content"]
+        .as_str()
+        .or_else(|| message["reasoning"].as_str())
+        .unwrap_or_default()
+}
+
+/// An Ollama `/api/chat` stream line carrying `content` and `thinking`.
+fn chat_line(content: &str, thinking: &str) -> Value {
+    let mut line = serde_json::json!({
+        "model": "",
+        "created_at": "",
+        "message": { "role": "assistant", "content": content },
+        "done": false,
+    });
+    if !thinking.is_empty() {
+        line["message"]["thinking"] = thinking.into();
+    }
+    line
+}
+
+fn add_usage(line: &mut Value, usage: Option<&Value>) {
+    let Some(usage) = usage.filter(|usage| usage.is_object()) else {
+        return;
+    };
+    line["prompt_eval_count"] = usage["prompt_tokens"].as_u64().unwrap_or_default().into();
+    line["eval_count"] = usage["completion_tokens"]
+        .as_u64()
+        .unwrap_or_default()
+        .into();
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StreamFormat {
+    Ndjson,
+    ServerSentEvents,
+}
+
+/// Splits a streamed reply into Ollama chat lines. Bytes are pushed as they
+/// arrive; `finish` flushes what remains once the body ends.
+#[derive(Debug)]
+pub struct ChatStream {
+    format: StreamFormat,
+    buffer: String,
+    done_reason: Option<String>,
+    usage: Option<Value>,
+    finished: bool,
+}
+
+impl ChatStream {
+    fn new(format: StreamFormat) -> Self {
+        Self {
+            format,
+            buffer: String::new(),
+            done_reason: None,
+            usage: None,
+            finished: false,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<Value, serde_json::Error>> {
+        self.buffer.push_str(&String::from_utf8_lossy(bytes));
+        let mut lines = Vec::new();
+        while let Some(newline) = self.buffer.find('\n') {
+            let line = self.buffer[..newline].trim().to_string();
+            self.buffer.drain(..=newline);
+            if !line.is_empty() {
+                lines.extend(self.decode(&line));
+            }
+        }
+        lines
+    }
+
+    /// Decodes a final line without a trailing newline and, for SSE, emits
+    /// the closing `done` line if `[DONE]` never came.
+    pub fn finish(&mut self) -> Vec<Result<Value, serde_json::Error>> {
+        let rest = std::mem::take(&mut self.buffer);
+        let mut lines = match rest.trim() {
+            "" => Vec::new(),
+            line => self.decode(line),
+        };
+        if self.format == StreamFormat::ServerSentEvents && !self.finished {
+            lines.extend(self.done_line());
+        }
+        lines
+    }
+
+    fn decode(&mut self, line: &str) -> Vec<Result<Value, serde_json::Error>> {
+        match self.format {
+            StreamFormat::Ndjson => vec![serde_json::from_str(line)],
+            StreamFormat::ServerSentEvents => {
+                // Comments, `event:` and `id:` fields carry nothing to show.
+                let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+                    return Vec::new();
+                };
+                if data == "[DONE]" {
+                    return self.done_line().into_iter().collect();
+                }
+                match serde_json::from_str::<Value>(data) {
+                    Ok(chunk) => self.decode_chunk(chunk),
+                    Err(error) => vec![Err(error)],
+                }
+            }
+        }
+    }
+
+    fn decode_chunk(&mut self, chunk: Value) -> Vec<Result<Value, serde_json::Error>> {
+        if let Some(error) = chunk.get("error") {
+            let message = error
+                .as_str()
+                .or_else(|| error["message"].as_str())
+                .unwrap_or("request failed");
+            return vec![Ok(serde_json::json!({ "error": message }))];
+        }
+        if chunk.get("usage").is_some_and(Value::is_object) {
+            self.usage = chunk.get("usage").cloned();
+        }
+        let Some(choice) = chunk["choices"].get(0) else {
+            return Vec::new();
+        };
+        if let Some(reason) = choice["finish_reason"].as_str() {
+            self.done_reason = Some(reason.to_string());
+        }
+        let delta = &choice["delta"];
+        let content = delta["content"].as_str().unwrap_or_default();
+        let thinking = reasoning_text(delta);
+        let calls = delta["tool_calls"]
+            .as_array()
+            .map_or(&[][..], Vec::as_slice);
+        if content.is_empty() && thinking.is_empty() && calls.is_empty() {
+            return Vec::new();
+        }
+        let mut line = chat_line(content, thinking);
+        if !calls.is_empty() {
+            // Fragments of one call share an `index`; the web-search loop
+            // joins them the same way as Ollama's partial calls.
+            line["message"]["tool_calls"] = calls
+                .iter()
+                .map(|call| {
+                    let mut function = serde_json::json!({ "index": call["index"] });
+                    for key in ["name", "arguments"] {
+                        if let Some(value) =
+                            call["function"].get(key).filter(|value| value.is_string())
+                        {
+                            function[key] = value.clone();
+                        }
+                    }
+                    let mut translated = serde_json::json!({ "function": function });
+                    if let Some(id) = call["id"].as_str() {
+                        translated["id"] = id.into();
+                    }
+                    translated
+                })
+                .collect();
+        }
+        vec![Ok(line)]
+    }
+
+    fn done_line(&mut self) -> Option<Result<Value, serde_json::Error>> {
+        if self.finished {
+            return None;
+        }
+        self.finished = true;
+        let mut line = chat_line("", "");
+        line["done"] = Value::Bool(true);
+        line["done_reason"] = match self.done_reason.as_deref() {
+            Some("length") => "length",
+            _ => "stop",
+        }
+        .into();
+        add_usage(&mut line, self.usage.as_ref());
+        Some(Ok(line))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(stream: &mut ChatStream, text: &str) -> Vec<Value> {
+        let mut lines = stream.push(text.as_bytes());
+        lines.extend(stream.finish());
+        lines.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn ollama_bodies_become_chat_completions() {
+        let body = serde_json::json!({
+            "model": "qwen",
+            "stream": true,
+            "think": false,
+            "format": "json",
+            "options": { "temperature": 0.2, "num_ctx": 8192, "num_predict": 256, "top_k": 40 },
+            "messages": [
+                { "role": "system", "content": "Be brief." },
+                { "role": "user", "content": "What is this?", "images": ["iVBORw0KGgo"] },
+                { "role": "assistant", "content": "", "thinking": "search first",
+                  "tool_calls": [{ "function": { "name": "web_search", "arguments": { "query": "x" } } }] },
+                { "role": "tool", "tool_name": "web_search", "content": "{}" },
+            ],
+        });
+        let request = openai_chat_body(&body);
+        assert_eq!(request["max_tokens"], 256);
+        assert_eq!(request["top_k"], 40);
+        assert!(request.get("num_ctx").is_none());
+        assert_eq!(request["response_format"]["type"], "json_object");
+        assert_eq!(request["stream_options"]["include_usage"], true);
+
+        let messages = request["messages"].as_array().unwrap();
+        assert_eq!(
+            messages[1]["content"][1]["image_url"]["url"],
+            "data:image/png;base64,iVBORw0KGgo"
+        );
+        assert!(messages[2].get("thinking").is_none());
+        assert_eq!(
+            messages[2]["tool_calls"][0]["function"]["arguments"],
+            r#"{"query":"x"}"#
+        );
+        assert_eq!(
+            messages[3]["tool_call_id"],
+            messages[2]["tool_calls"][0]["id"]
+        );
+    }
+
+    #[test]
+    fn server_sent_events_become_ollama_lines() {
+        let mut stream = ChatStream::new(StreamFormat::ServerSentEvents);
+        let lines = decode_all(
+            &mut stream,
+            concat!(
+                ": keep-alive\n",
+                "data: {\"choices\":[{\"delta\":{\"role\":\"assistant\",\"reasoning_content\":\"hm\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{\"content\":\"Hi\"}}]}\n\n",
+                "data: {\"choices\":[{\"delta\":{},\"finish_reason\":\"length\"}]}\n\n",
+                "data: {\"choices\":[],\"usage\":{\"prompt_tokens\":7,\"completion_tokens\":2}}\n\n",
+                "data: [DONE]\n\n",
+            ),
+        );
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["message"]["thinking"], "hm");
+        assert_eq!(lines[1]["message"]["content"], "Hi");
+        assert_eq!(lines[2]["done"], true);
+        assert_eq!(lines[2]["done_reason"], "length");
+        assert_eq!(lines[2]["eval_count"], 2);
+    }
+
+    #[test]
+    fn streamed_tool_call_fragments_keep_their_index_and_id() {
+        let mut stream = ChatStream::new(StreamFormat::ServerSentEvents);
+        let lines = decode_all(
+            &mut stream,
+            concat!(
+                "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"id\":\"call_a\",\"type\":\"function\",\"function\":{\"name\":\"web_search\",\"arguments\":\"\"}}]}}]}\n",
+                "data: {\"choices\":[{\"delta\":{\"tool_calls\":[{\"index\":0,\"function\":{\"arguments\":\"{\\\"query\\\":\\\"x\\\"}\"}}]}}]}\n",
+            ),
+        );
+        assert_eq!(lines[0]["message"]["tool_calls"][0]["id"], "call_a");
+        assert_eq!(lines[0]["message"]["tool_calls"][0]["function"]["index"], 0);
+        assert_eq!(
+            lines[1]["message"]["tool_calls"][0]["function"]["arguments"],
+            r#"{"query":"x"}"#
+        );
+        // The body ended without `[DONE]`; the turn is still closed.
+        assert_eq!(lines[2]["done"], true);
+    }
+
+    #[test]
+    fn ndjson_accepts_a_final_line_without_newline() {
+        let mut stream = ChatStream::new(StreamFormat::Ndjson);
+        let lines = decode_all(&mut stream, "{\"done\":false}\n{\"done\":true}");
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            error_detail(r#"{"error":{"message":"bad key"}}"#).as_deref(),
+            Some("bad key")
+        );
+    }
+}
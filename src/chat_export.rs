@@ -0,0 +1,755 @@
+//! Exports chats to Markdown, self-contained HTML and JSON.
+//!
+//! The JSON export is the documented interchange format (see the README):
+//!
+//! ```json
+//! {
+//!   "format": "ollama-gui-chats",
+//!   "version": 1,
+//!   "exported_at": "2026-10-17T09:30:00+02:00",
+//!   "chats": [{
+//!     "id": "…", "title": "…", "updated_at": "…", "pinned": false,
+//!     "messages": [
+//!       { "role": "user", "content": "…",
+//!         "images": [{ "name": "…", "mime_type": "image/png", "data": "<base64>" }] },
+//!       { "role": "assistant", "content": "…", "thinking": "…", "model": "…",
+//!         "thinking_seconds": 4, "sources": [{ "title": "…", "url": "…" }],
+//!         "stats": { "eval_count": 120, … } }
+//!     ]
+//!   }]
+//! }
+//! ```
+//!
+//! Only the active branch and answer of each turn are exported.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use pulldown_cmark::{CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
+use two_face::re_exports::syntect::highlighting::{Theme, ThemeSet};
+use two_face::re_exports::syntect::html::highlighted_html_for_string;
+use two_face::re_exports::syntect::parsing::SyntaxSet;
+
+use crate::app::{ChatImage, Correspondence, GenerationStats, SavedChat};
+use crate::web_search::WebSource;
+use crate::{normalize_code_fence_languages, split_thinking_text};
+
+pub const FORMAT_NAME: &str = "ollama-gui-chats";
+pub const FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 3] = [Self::Markdown, Self::Html, Self::Json];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Markdown => "md",
+            Self::Html => "html",
+            Self::Json => "json",
+        }
+    }
+
+    /// Picks the format from the file name chosen in the save dialog. A name
+    /// without a known extension is saved as Markdown.
+    pub fn for_path(mut path: PathBuf) -> (PathBuf, Self) {
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let format = match extension.as_deref() {
+            Some("md" | "markdown") => return (path, Self::Markdown),
+            Some("html" | "htm") => return (path, Self::Html),
+            Some("json") => return (path, Self::Json),
+            _ => Self::Markdown,
+        };
+        path.set_extension(format.extension());
+        (path, format)
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Markdown => "Markdown",
+            Self::Html => "HTML",
+            Self::Json => "JSON",
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExportScope {
+    OpenChat,
+    AllChats,
+}
+
+/// One chat as it is exported: the saved metadata plus the visible thread
+/// with its images loaded.
+#[derive(Clone, Debug)]
+pub struct ChatDocument {
+    pub id: String,
+    pub title: String,
+    pub updated_at: String,
+    pub pinned: bool,
+    pub messages: Vec<Correspondence>,
+}
+
+impl ChatDocument {
+    pub fn from_saved(chat: &SavedChat, image_dir: &Path) -> Self {
+        Self {
+            id: chat.id.clone(),
+            title: chat.title.clone(),
+            updated_at: chat.updated_at.clone(),
+            pinned: chat.pinned,
+            messages: chat.to_current(image_dir).messages,
+        }
+    }
+}
+
+/// Renders the chats in `format`. `exported_at` is recorded in the JSON file.
+pub fn export(chats: &[ChatDocument], format: ExportFormat, exported_at: &str) -> String {
+    match format {
+        ExportFormat::Markdown => to_markdown(chats),
+        ExportFormat::Html => to_html(chats),
+        ExportFormat::Json => to_json(chats, exported_at),
+    }
+}
+
+/// A file name for the save dialog, derived from the chat title.
+pub fn suggested_file_name(title: &str) -> String {
+    let name = title
+        .chars()
+        .map(|character| {
+            if character.is_alphanumeric() || character == '-' {
+                character
+            } else {
+                ' '
+            }
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-");
+    if name.is_empty() {
+        "chat".to_string()
+    } else {
+        name
+    }
+}
+
+/// `2026-10-17T09:30:00+02:00` becomes `2026-10-17 09:30`; anything that is
+/// not RFC 3339 is shown as saved.
+fn display_time(timestamp: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(timestamp)
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|_| timestamp.to_string())
+}
+
+fn thinking_summary(thinking_seconds: Option<u64>) -> String {
+    match thinking_seconds {
+        Some(seconds) => format!("Thinking ({seconds} s)"),
+        None => "Thinking".to_string(),
+    }
+}
+
+fn data_url(image: &ChatImage) -> String {
+    format!(
+        "data:{};base64,{}",
+        image.mime_type,
+        BASE64.encode(&image.bytes)
+    )
+}
+
+fn to_markdown(chats: &[ChatDocument]) -> String {
+    let mut output = String::new();
+    for (index, chat) in chats.iter().enumerate() {
+        if index > 0 {
+            output.push_str("\n---\n\n");
+        }
+        output.push_str(&format!(
+            "# {}\n\n_Updated {}_\n",
+            chat.title,
+            display_time(&chat.updated_at)
+        ));
+        for message in &chat.messages {
+            match message {
+                Correspondence::User { text, images, .. } => {
+                    output.push_str("\n## You\n\n");
+                    output.push_str(text.trim_end());
+                    output.push('\n');
+                    for image in images {
+                        output.push_str(&format!("\n_Attached image: {}_\n", image.name));
+                    }
+                }
+                Correspondence::Bot {
+                    text,
+                    model,
+                    thinking_seconds,
+                    sources,
+                    ..
+                } => {
+                    match model {
+                        Some(model) => output.push_str(&format!("\n## Assistant · {model}\n\n")),
+                        None => output.push_str("\n## Assistant\n\n"),
+                    }
+                    let (thinking, visible) = split_thinking_text(text);
+                    if !thinking.trim().is_empty() {
+                        output.push_str(&format!(
+                            "<details>\n<summary>{}</summary>\n\n{}\n\n</details>\n\n",
+                            thinking_summary(*thinking_seconds),
+                            thinking.trim()
+                        ));
+                    }
+                    output.push_str(visible.trim());
+                    output.push('\n');
+                    if !sources.is_empty() {
+                        output.push_str("\n**Sources**\n\n");
+                        for (number, source) in sources.iter().enumerate() {
+                            output.push_str(&format!(
+                                "{}. [{}]({})\n",
+                                number + 1,
+                                source.title,
+                                source.url
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+const HTML_STYLE: &str = "\
+body{margin:0;background:#f6f7f9;color:#1d2330;font:15px/1.6 system-ui,-apple-system,'Segoe UI',sans-serif}
+main{max-width:860px;margin:0 auto;padding:32px 20px}
+article.chat{margin-bottom:48px}
+h1{font-size:24px;margin:0}
+.meta{color:#6b7280;font-size:13px;margin:4px 0 20px}
+.message{background:#fff;border:1px solid #e3e6eb;border-radius:10px;padding:14px 18px;margin:12px 0}
+.message.user{background:#eef3ff;border-color:#d6e0fb}
+.message h2{font-size:13px;letter-spacing:.04em;text-transform:uppercase;color:#4b5563;margin:0 0 6px}
+.message h2 .model{text-transform:none;letter-spacing:0;color:#6b7280;font-weight:normal}
+.user .content{white-space:pre-wrap}
+pre{padding:12px;border-radius:8px;overflow-x:auto;font-size:13px;border:1px solid #e3e6eb}
+code{font-family:ui-monospace,SFMono-Regular,Menlo,Consolas,monospace}
+details.thinking{color:#4b5563;border-left:3px solid #d1d5db;padding-left:12px;margin-bottom:10px}
+details.thinking summary{cursor:pointer}
+.images img{max-width:100%;max-height:420px;border-radius:8px;margin-top:8px}
+a.citation{text-decoration:none;font-size:12px;vertical-align:super}
+.sources{font-size:13px;color:#4b5563}
+table{border-collapse:collapse}td,th{border:1px solid #e3e6eb;padding:4px 8px}";
+
+fn to_html(chats: &[ChatDocument]) -> String {
+    let page_title = match chats {
+        [chat] => chat.title.as_str(),
+        _ => "Ollama GUI chats",
+    };
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\">\n\
+         <title>{}</title>\n<style>\n{HTML_STYLE}\n</style>\n</head>\n<body>\n<main>\n",
+        escape_html(page_title)
+    );
+    for chat in chats {
+        output.push_str(&format!(
+            "<article class=\"chat\">\n<h1>{}</h1>\n<p class=\"meta\">Updated {}</p>\n",
+            escape_html(&chat.title),
+            escape_html(&display_time(&chat.updated_at))
+        ));
+        for message in &chat.messages {
+            match message {
+                Correspondence::User { text, images, .. } => {
+                    output.push_str(&format!(
+                        "<section class=\"message user\">\n<h2>You</h2>\n\
+                         <div class=\"content\">{}</div>\n",
+                        escape_html(text.trim_end())
+                    ));
+                    if !images.is_empty() {
+                        output.push_str("<div class=\"images\">");
+                        for image in images {
+                            output.push_str(&format!(
+                                "<img src=\"{}\" alt=\"{}\">",
+                                data_url(image),
+                                escape_html(&image.name)
+                            ));
+                        }
+                        output.push_str("</div>\n");
+                    }
+                    output.push_str("</section>\n");
+                }
+                Correspondence::Bot {
+                    text,
+                    model,
+                    thinking_seconds,
+                    sources,
+                    ..
+                } => {
+                    let model = model
+                        .as_deref()
+                        .map(|model| {
+                            format!(" <span class=\"model\">{}</span>", escape_html(model))
+                        })
+                        .unwrap_or_default();
+                    output.push_str(&format!(
+                        "<section class=\"message assistant\">\n<h2>Assistant{model}</h2>\n"
+                    ));
+                    let (thinking, visible) = split_thinking_text(text);
+                    if !thinking.trim().is_empty() {
+                        output.push_str(&format!(
+                            "<details class=\"thinking\"><summary>{}</summary>\n{}</details>\n",
+                            thinking_summary(*thinking_seconds),
+                            markdown_to_html(thinking.trim(), &[])
+                        ));
+                    }
+                    output.push_str(&format!(
+                        "<div class=\"content\">\n{}</div>\n",
+                        markdown_to_html(visible.trim(), sources)
+                    ));
+                    if !sources.is_empty() {
+                        output.push_str("<ol class=\"sources\">\n");
+                        for source in sources {
+                            if is_safe_link(&source.url) {
+                                output.push_str(&format!(
+                                    "<li><a href=\"{}\">{}</a></li>\n",
+                                    escape_html(&source.url),
+                                    escape_html(&source.title)
+                                ));
+                            } else {
+                                output.push_str(&format!(
+                                    "<li>{} ({})</li>\n",
+                                    escape_html(&source.title),
+                                    escape_html(&source.url)
+                                ));
+                            }
+                        }
+                        output.push_str("</ol>\n");
+                    }
+                    output.push_str("</section>\n");
+                }
+            }
+        }
+        output.push_str("</article>\n");
+    }
+    output.push_str("</main>\n</body>\n</html>\n");
+    output
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for character in text.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(two_face::syntax::extra_newlines);
+static CODE_THEME: LazyLock<Theme> = LazyLock::new(|| {
+    ThemeSet::load_defaults()
+        .themes
+        .remove("InspiredGitHub")
+        .unwrap_or_default()
+});
+
+fn highlight_code(code: &str, language: &str) -> String {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+    highlighted_html_for_string(code, &SYNTAXES, syntax, &CODE_THEME)
+        .unwrap_or_else(|_| format!("<pre><code>{}</code></pre>\n", escape_html(code)))
+}
+
+/// Only these schemes become links. Anything else — `javascript:`, `data:`,
+/// `file:` or a relative path — stays text, since it would either run code or
+/// point somewhere unrelated once the file is opened elsewhere.
+fn is_safe_link(url: &str) -> bool {
+    let scheme = url
+        .trim_start()
+        .split_once(':')
+        .map(|(scheme, _)| scheme.to_ascii_lowercase());
+    matches!(scheme.as_deref(), Some("http" | "https" | "mailto"))
+}
+
+/// Turns `[n]` citations into links to the matching web source.
+fn link_citations(text: &str, sources: &[WebSource]) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        output.push_str(&escape_html(&rest[..start]));
+        let after = &rest[start + 1..];
+        let citation = after.find(']').and_then(|end| {
+            let number = after[..end].parse::<usize>().ok()?;
+            let source = sources.get(number.checked_sub(1)?)?;
+            is_safe_link(&source.url).then_some((end, number, source))
+        });
+        match citation {
+            Some((end, number, source)) => {
+                output.push_str(&format!(
+                    "<a class=\"citation\" href=\"{}\" title=\"{}\">[{number}]</a>",
+                    escape_html(&source.url),
+                    escape_html(&source.title)
+                ));
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('[');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(&escape_html(rest));
+    output
+}
+
+/// Renders model Markdown with highlighted code blocks. Raw HTML from the
+/// model is shown as text so an export never runs markup it did not write;
+/// links are kept only for safe schemes, and images become links so the page
+/// never loads anything from the network.
+fn markdown_to_html(markdown: &str, sources: &[WebSource]) -> String {
+    let markdown = normalize_code_fence_languages(markdown);
+    let options =
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS;
+    let mut events = Vec::new();
+    let mut text = String::new();
+    let mut code: Option<(String, String)> = None;
+    // Open links and images: whether an `<a>` was written, the URL, and the
+    // event count at the start so an image without alt text can show its URL.
+    let mut links: Vec<(bool, String, usize)> = Vec::new();
+    let flush_text = |text: &mut String, events: &mut Vec<Event<'static>>| {
+        if !text.is_empty() {
+            events.push(Event::Html(link_citations(text, sources).into()));
+            text.clear();
+        }
+    };
+    for event in Parser::new_ext(&markdown, options) {
+        if let Some((language, body)) = &mut code {
+            match event {
+                Event::End(TagEnd::CodeBlock) => {
+                    events.push(Event::Html(highlight_code(body, language).into()));
+                    code = None;
+                }
+                Event::Text(part) => body.push_str(&part),
+                _ => {}
+            }
+            continue;
+        }
+        match event {
+            Event::Text(part) | Event::Html(part) | Event::InlineHtml(part) => text.push_str(&part),
+            Event::Start(
+                Tag::Link {
+                    link_type,
+                    dest_url,
+                    title,
+                    ..
+                }
+                | Tag::Image {
+                    link_type,
+                    dest_url,
+                    title,
+                    ..
+                },
+            ) => {
+                flush_text(&mut text, &mut events);
+                let url = match link_type {
+                    LinkType::Email => format!("mailto:{dest_url}"),
+                    _ => dest_url.to_string(),
+                };
+                let safe = is_safe_link(&url);
+                if safe {
+                    let title = if title.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" title=\"{}\"", escape_html(&title))
+                    };
+                    events.push(Event::Html(
+                        format!("<a href=\"{}\"{title}>", escape_html(&url)).into(),
+                    ));
+                }
+                links.push((safe, url, events.len()));
+            }
+            Event::End(TagEnd::Link | TagEnd::Image) => {
+                flush_text(&mut text, &mut events);
+                if let Some((safe, url, start)) = links.pop() {
+                    if events.len() == start {
+                        events.push(Event::Html(escape_html(&url).into()));
+                    }
+                    if safe {
+                        events.push(Event::Html("</a>".into()));
+                    }
+                }
+            }
+            Event::Start(Tag::CodeBlock(kind)) => {
+                flush_text(&mut text, &mut events);
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code = Some((language, String::new()));
+            }
+            event => {
+                flush_text(&mut text, &mut events);
+                events.push(event.into_static());
+            }
+        }
+    }
+    flush_text(&mut text, &mut events);
+    let mut html = String::new();
+    pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    html
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportFile {
+    pub format: String,
+    pub version: u32,
+    #[serde(default)]
+    pub exported_at: String,
+    pub chats: Vec<ExportedChat>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportedChat {
+    #[serde(default)]
+    pub id: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub updated_at: String,
+    #[serde(default)]
+    pub pinned: bool,
+    pub messages: Vec<ExportedMessage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "role", rename_all = "lowercase")]
+pub enum ExportedMessage {
+    User {
+        content: String,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        images: Vec<ExportedImage>,
+    },
+    Assistant {
+        content: String,
+        #[serde(default, skip_serializing_if = "String::is_empty")]
+        thinking: String,
+        #[serde(default)]
+        model: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        thinking_seconds: Option<u64>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        sources: Vec<WebSource>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        stats: Option<GenerationStats>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ExportedImage {
+    pub name: String,
+    pub mime_type: String,
+    /// Base64 without a `data:` prefix.
+    pub data: String,
+}
+
+impl ExportedMessage {
+    fn from_message(message: &Correspondence) -> Self {
+        match message {
+            Correspondence::User { text, images, .. } => Self::User {
+                content: text.clone(),
+                images: images
+                    .iter()
+                    .map(|image| ExportedImage {
+                        name: image.name.clone(),
+                        mime_type: image.mime_type.clone(),
+                        data: BASE64.encode(&image.bytes),
+                    })
+                    .collect(),
+            },
+            Correspondence::Bot {
+                text,
+                model,
+                thinking_seconds,
+                sources,
+                stats,
+                ..
+            } => {
+                let (thinking, content) = split_thinking_text(text);
+                Self::Assistant {
+                    content,
+                    thinking,
+                    model: model.clone(),
+                    thinking_seconds: *thinking_seconds,
+                    sources: sources.clone(),
+                    stats: *stats,
+                }
+            }
+        }
+    }
+}
+
+fn to_json(chats: &[ChatDocument], exported_at: &str) -> String {
+    let file = ExportFile {
+        format: FORMAT_NAME.to_string(),
+        version: FORMAT_VERSION,
+        exported_at: exported_at.to_string(),
+        chats: chats
+            .iter()
+            .map(|chat| ExportedChat {
+                id: chat.id.clone(),
+                title: chat.title.clone(),
+                updated_at: chat.updated_at.clone(),
+                pinned: chat.pinned,
+                messages: chat
+                    .messages
+                    .iter()
+                    .map(ExportedMessage::from_message)
+                    .collect(),
+            })
+            .collect(),
+    };
+    serde_json::to_string_pretty(&file).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_chat() -> ChatDocument {
+        let image = ChatImage::new(
+            "cat.png".into(),
+            "image/png".into(),
+            vec![1, 2, 3],
+            iced::widget::image::Handle::from_bytes(vec![1, 2, 3]),
+        );
+        ChatDocument {
+            id: "chat-1".into(),
+            title: "Rust <tips>".into(),
+            updated_at: "2026-10-17T09:30:00+02:00".into(),
+            pinned: false,
+            messages: vec![
+                Correspondence::User {
+                    text: "What is new?".into(),
+                    images: vec![image],
+                    branches: Vec::new(),
+                    active_branch: 0,
+                },
+                Correspondence::Bot {
+                    text: "<think>Check the notes.</think>See [1] and <b>this</b>.\n\n```rust\nfn main() {}\n```".into(),
+                    model: Some("llama3.2".into()),
+                    thinking_seconds: Some(3),
+                    sources: vec![WebSource {
+                        title: "Release notes".into(),
+                        url: "https://example.com/notes".into(),
+                    }],
+                    web_search_used: true,
+                    stats: None,
+                    format_check: None,
+                    alternatives: Vec::new(),
+                    active_variant: 0,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn markdown_folds_thinking_and_lists_sources() {
+        let markdown = export(&[sample_chat()], ExportFormat::Markdown, "");
+        assert!(markdown.starts_with("# Rust <tips>\n\n_Updated 2026-10-17 09:30_\n"));
+        assert!(markdown.contains("## Assistant · llama3.2"));
+        assert!(markdown.contains(
+            "<details>\n<summary>Thinking (3 s)</summary>\n\nCheck the notes.\n\n</details>"
+        ));
+        assert!(markdown.contains("1. [Release notes](https://example.com/notes)"));
+        assert!(markdown.contains("_Attached image: cat.png_"));
+        assert_eq!(
+            ExportFormat::for_path(PathBuf::from("notes")),
+            (PathBuf::from("notes.md"), ExportFormat::Markdown)
+        );
+        assert_eq!(
+            ExportFormat::for_path(PathBuf::from("a.HTML")).1,
+            ExportFormat::Html
+        );
+    }
+
+    #[test]
+    fn html_keeps_only_safe_links_and_no_remote_images() {
+        let sources = vec![WebSource {
+            title: "Bad".into(),
+            url: "javascript:alert(1)".into(),
+        }];
+        let html = markdown_to_html(
+            "[click](javascript:alert(1)) [docs](https://example.com/docs \"Docs\") \
+             ![chart](https://host/pixel) ![](https://host/blank) <me@example.com> [1]",
+            &sources,
+        );
+        assert!(!html.contains("javascript:"), "{html}");
+        assert!(!html.contains("<img"), "{html}");
+        assert!(html.contains("click"));
+        assert!(html.contains("<a href=\"https://example.com/docs\" title=\"Docs\">docs</a>"));
+        assert!(html.contains("<a href=\"https://host/pixel\">chart</a>"));
+        assert!(html.contains("<a href=\"https://host/blank\">https://host/blank</a>"));
+        assert!(html.contains("<a href=\"mailto:me@example.com\">me@example.com</a>"));
+        assert!(html.contains("[1]") && !html.contains("citation"));
+    }
+
+    #[test]
+    fn html_is_self_contained_and_json_round_trips() {
+        let html = export(&[sample_chat()], ExportFormat::Html, "");
+        assert!(html.contains("<title>Rust &lt;tips&gt;</title>"));
+        assert!(html.contains("<img src=\"data:image/png;base64,AQID\" alt=\"cat.png\">"));
+        assert!(html.contains(
+            "<a class=\"citation\" href=\"https://example.com/notes\" title=\"Release notes\">[1]</a>"
+        ));
+        assert!(html.contains("&lt;b&gt;this&lt;/b&gt;"));
+        assert!(!html.contains("<b>this</b>"));
+        assert!(html.contains("<pre style="));
+        assert!(html.contains("<details class=\"thinking\"><summary>Thinking (3 s)</summary>"));
+
+        let json = export(
+            &[sample_chat()],
+            ExportFormat::Json,
+            "2026-10-17T10:00:00+02:00",
+        );
+        let file: ExportFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(file.format, FORMAT_NAME);
+        assert_eq!(file.version, FORMAT_VERSION);
+        assert_eq!(
+            file.chats[0].messages[1],
+            ExportedMessage::Assistant {
+                content: "See [1] and <b>this</b>.\n\n```rust\nfn main() {}\n```".into(),
+                thinking: "Check the notes.".into(),
+                model: Some("llama3.2".into()),
+                thinking_seconds: Some(3),
+                sources: vec![WebSource {
+                    title: "Release notes".into(),
+                    url: "https://example.com/notes".into(),
+                }],
+                stats: None,
+            }
+        );
+        let ExportedMessage::User { images, .. } = &file.chats[0].messages[0] else {
+            panic!("the first message is the user's");
+        };
+        assert_eq!(images[0].data, "AQID");
+    }
+}
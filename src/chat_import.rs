@@ -0,0 +1,569 @@
+//! Reads conversations exported by other front-ends — ChatGPT's
+//! `conversations.json`, Open WebUI chat exports — and this app's own JSON
+//! export, so they can be previewed and merged into the saved chats.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use chrono::{Local, TimeZone};
+use serde_json::Value;
+
+use crate::app::{ChatImage, Correspondence};
+use crate::chat_export::{self, ExportFile, ExportedMessage};
+use crate::{chat_image_from_bytes, chat_title};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImportSource {
+    ChatGpt,
+    OpenWebUi,
+    OllamaGui,
+}
+
+impl fmt::Display for ImportSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ChatGpt => "ChatGPT",
+            Self::OpenWebUi => "Open WebUI",
+            Self::OllamaGui => "Ollama GUI",
+        })
+    }
+}
+
+/// A conversation read from an export, before it gets an id of its own.
+#[derive(Clone, Debug)]
+pub struct ImportedChat {
+    pub title: String,
+    pub updated_at: String,
+    pub pinned: bool,
+    pub messages: Vec<Correspondence>,
+    /// Whether the chat is ticked in the preview.
+    pub selected: bool,
+}
+
+impl ImportedChat {
+    fn new(title: String, updated_at: Option<String>, messages: Vec<Correspondence>) -> Self {
+        let title = match title.trim() {
+            "" => chat_title(&messages),
+            title => title.to_string(),
+        };
+        Self {
+            title,
+            updated_at: updated_at.unwrap_or_else(|| Local::now().to_rfc3339()),
+            pinned: false,
+            messages,
+            selected: true,
+        }
+    }
+
+    /// Distinct model labels in the order they first answered.
+    pub fn models(&self) -> Vec<&str> {
+        let mut models = Vec::new();
+        for message in &self.messages {
+            if let Correspondence::Bot {
+                model: Some(model), ..
+            } = message
+                && !models.contains(&model.as_str())
+            {
+                models.push(model.as_str());
+            }
+        }
+        models
+    }
+}
+
+/// An export that has been read and is waiting for the user to pick chats.
+#[derive(Clone, Debug)]
+pub struct ImportPreview {
+    pub source: ImportSource,
+    pub path: PathBuf,
+    pub chats: Vec<ImportedChat>,
+}
+
+/// Detects the export format and reads every conversation with messages in it.
+pub fn parse_export(data: &str) -> Result<(ImportSource, Vec<ImportedChat>), String> {
+    let value: Value = serde_json::from_str(data)
+        .map_err(|error| format!("The file is not valid JSON: {error}"))?;
+    let (source, chats) = match &value {
+        Value::Object(object)
+            if object.get("format").and_then(Value::as_str) == Some(chat_export::FORMAT_NAME) =>
+        {
+            (ImportSource::OllamaGui, own_chats(value)?)
+        }
+        // Open WebUI exports a single chat as one object.
+        Value::Object(object) if object.get("chat").is_some_and(Value::is_object) => {
+            (ImportSource::OpenWebUi, vec![open_webui_chat(&value)])
+        }
+        Value::Array(items) if items.iter().any(|item| item.get("mapping").is_some()) => (
+            ImportSource::ChatGpt,
+            items.iter().map(chatgpt_chat).collect(),
+        ),
+        Value::Array(items) if items.iter().any(|item| item.get("chat").is_some()) => (
+            ImportSource::OpenWebUi,
+            items.iter().map(open_webui_chat).collect(),
+        ),
+        _ => {
+            return Err("This is not a ChatGPT, Open WebUI or Ollama GUI chat export.".to_string());
+        }
+    };
+    let chats = chats
+        .into_iter()
+        .filter(|chat| !chat.messages.is_empty())
+        .collect::<Vec<_>>();
+    if chats.is_empty() {
+        return Err(format!("The {source} export contains no messages."));
+    }
+    Ok((source, chats))
+}
+
+/// Exports store seconds, milliseconds or fractional seconds since the epoch.
+fn timestamp(value: Option<&Value>) -> Option<String> {
+    let value = value?.as_f64()?;
+    let milliseconds = if value > 1e15 {
+        value / 1e6
+    } else if value > 1e12 {
+        value
+    } else {
+        value * 1000.0
+    };
+    Local
+        .timestamp_millis_opt(milliseconds as i64)
+        .single()
+        .map(|time| time.to_rfc3339())
+}
+
+fn text(value: Option<&Value>) -> String {
+    value
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn with_thinking(thinking: &str, content: &str) -> String {
+    if thinking.trim().is_empty() {
+        content.to_string()
+    } else {
+        format!("<think>{}</think>{content}", thinking.trim())
+    }
+}
+
+fn decode_image(name: String, data: &str) -> Option<ChatImage> {
+    let data = data.split_once("base64,").map_or(data, |(_, data)| data);
+    chat_image_from_bytes(name, BASE64.decode(data.trim()).ok()?).ok()
+}
+
+/// Appends an answer, merging it into the previous one when a front-end split
+/// a single reply (tool calls, reasoning) into several assistant messages.
+fn push_answer(messages: &mut Vec<Correspondence>, answer: String, answer_model: Option<String>) {
+    if let Some(Correspondence::Bot { text, model, .. }) = messages.last_mut() {
+        if !answer.trim().is_empty() {
+            if !text.is_empty() {
+                text.push_str("\n\n");
+            }
+            text.push_str(&answer);
+        }
+        if model.is_none() {
+            *model = answer_model;
+        }
+    } else {
+        messages.push(Correspondence::bot(answer, answer_model));
+    }
+}
+
+fn own_chats(value: Value) -> Result<Vec<ImportedChat>, String> {
+    let file: ExportFile = serde_json::from_value(value)
+        .map_err(|error| format!("The export could not be read: {error}"))?;
+    if file.version > chat_export::FORMAT_VERSION {
+        return Err(format!(
+            "The export uses format version {}; this version of the app reads up to {}.",
+            file.version,
+            chat_export::FORMAT_VERSION
+        ));
+    }
+    Ok(file
+        .chats
+        .into_iter()
+        .map(|chat| {
+            let messages = chat
+                .messages
+                .into_iter()
+                .map(|message| match message {
+                    ExportedMessage::User { content, images } => Correspondence::user(
+                        content,
+                        images
+                            .into_iter()
+                            .filter_map(|image| decode_image(image.name, &image.data))
+                            .collect(),
+                    ),
+                    ExportedMessage::Assistant {
+                        content,
+                        thinking,
+                        model,
+                        thinking_seconds: seconds,
+                        sources: web_sources,
+                        stats: generation_stats,
+                    } => {
+                        let mut answer =
+                            Correspondence::bot(with_thinking(&thinking, &content), model);
+                        if let Correspondence::Bot {
+                            thinking_seconds,
+                            sources,
+                            web_search_used,
+                            stats,
+                            ..
+                        } = &mut answer
+                        {
+                            *thinking_seconds = seconds;
+                            *web_search_used = !web_sources.is_empty();
+                            *sources = web_sources;
+                            *stats = generation_stats;
+                        }
+                        answer
+                    }
+                })
+                .collect();
+            let mut imported = ImportedChat::new(
+                chat.title,
+                Some(chat.updated_at).filter(|time| !time.is_empty()),
+                messages,
+            );
+            imported.pinned = chat.pinned;
+            imported
+        })
+        .collect())
+}
+
+/// ChatGPT keeps every edit and regeneration in a tree; the conversation as
+/// last shown is the path from `current_node` back to the root.
+fn chatgpt_chat(conversation: &Value) -> ImportedChat {
+    let empty = serde_json::Map::new();
+    let mapping = conversation
+        .get("mapping")
+        .and_then(Value::as_object)
+        .unwrap_or(&empty);
+    let mut path = Vec::new();
+    let mut node_id = conversation.get("current_node").and_then(Value::as_str);
+    while let Some(id) = node_id {
+        let Some(node) = mapping.get(id) else { break };
+        if path.len() > mapping.len() {
+            break;
+        }
+        path.push(node);
+        node_id = node.get("parent").and_then(Value::as_str);
+    }
+    path.reverse();
+
+    let default_model = conversation
+        .get("default_model_slug")
+        .and_then(Value::as_str)
+        .map(str::to_string);
+    let mut messages = Vec::new();
+    let mut thinking = String::new();
+    for message in path.iter().filter_map(|node| node.get("message")) {
+        let metadata = message.get("metadata");
+        if metadata
+            .and_then(|metadata| metadata.get("is_visually_hidden_from_conversation"))
+            .and_then(Value::as_bool)
+            == Some(true)
+        {
+            continue;
+        }
+        let role = message
+            .get("author")
+            .and_then(|author| author.get("role"))
+            .and_then(Value::as_str);
+        let content = message.get("content");
+        let content_type = content
+            .and_then(|content| content.get("content_type"))
+            .and_then(Value::as_str);
+        let parts = content
+            .and_then(|content| content.get("parts"))
+            .and_then(Value::as_array)
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            })
+            .unwrap_or_default();
+        match (role, content_type) {
+            (Some("user"), Some("text" | "multimodal_text")) if !parts.trim().is_empty() => {
+                messages.push(Correspondence::user(parts, Vec::new()));
+            }
+            (Some("assistant"), Some("thoughts")) => {
+                for thought in content
+                    .and_then(|content| content.get("thoughts"))
+                    .and_then(Value::as_array)
+                    .into_iter()
+                    .flatten()
+                {
+                    thinking.push_str(&text(thought.get("content")));
+                    thinking.push_str("\n\n");
+                }
+            }
+            (Some("assistant"), Some("text" | "multimodal_text")) if !parts.trim().is_empty() => {
+                let model = metadata
+                    .and_then(|metadata| metadata.get("model_slug"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string)
+                    .or_else(|| default_model.clone());
+                push_answer(&mut messages, with_thinking(&thinking, &parts), model);
+                thinking.clear();
+            }
+            _ => {}
+        }
+    }
+    ImportedChat::new(
+        text(conversation.get("title")),
+        timestamp(conversation.get("update_time"))
+            .or_else(|| timestamp(conversation.get("create_time"))),
+        messages,
+    )
+}
+
+/// Open WebUI embeds reasoning in the answer as
+/// `<details type="reasoning" duration="3"><summary>…</summary>> quoted</details>`.
+fn split_open_webui_reasoning(content: &str) -> (String, String, Option<u64>) {
+    let Some(start) = content.find("<details type=\"reasoning\"") else {
+        return (String::new(), content.to_string(), None);
+    };
+    let Some(end) = content[start..].find("</details>").map(|end| start + end) else {
+        return (String::new(), content.to_string(), None);
+    };
+    let block = &content[start..end];
+    let seconds = block
+        .split_once("duration=\"")
+        .and_then(|(_, rest)| rest.split('"').next())
+        .and_then(|duration| duration.parse().ok());
+    let body = block.split_once("</summary>").map_or("", |(_, body)| body);
+    let thinking = body
+        .lines()
+        .map(|line| line.strip_prefix('>').map_or(line, str::trim_start))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let answer = format!(
+        "{}{}",
+        &content[..start],
+        &content[end + "</details>".len()..]
+    );
+    (
+        thinking.trim().to_string(),
+        answer.trim().to_string(),
+        seconds,
+    )
+}
+
+fn open_webui_images(message: &Value) -> Vec<ChatImage> {
+    message
+        .get("files")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter(|file| file.get("type").and_then(Value::as_str) == Some("image"))
+        .enumerate()
+        .filter_map(|(index, file)| {
+            let url = file.get("url").and_then(Value::as_str)?;
+            let extension = url
+                .strip_prefix("data:image/")
+                .and_then(|rest| rest.split(';').next())
+                .unwrap_or("png");
+            let name = file
+                .get("name")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("image-{}.{extension}", index + 1));
+            decode_image(name, url.strip_prefix("data:")?)
+        })
+        .collect()
+}
+
+/// Follows `history.currentId` back through `parentId` so only the branch the
+/// user last saw is imported; older exports only have `messages`.
+fn open_webui_chat(entry: &Value) -> ImportedChat {
+    let chat = entry.get("chat").unwrap_or(&Value::Null);
+    let history = chat
+        .get("history")
+        .and_then(|history| history.get("messages"))
+        .and_then(Value::as_object);
+    let mut thread = Vec::new();
+    if let Some(history) = history {
+        let by_id = history.iter().collect::<HashMap<_, _>>();
+        let mut id = chat
+            .get("history")
+            .and_then(|history| history.get("currentId"))
+            .and_then(Value::as_str);
+        while let Some(current) = id {
+            let Some(message) = by_id.get(&current.to_string()) else {
+                break;
+            };
+            if thread.len() > history.len() {
+                break;
+            }
+            thread.push(*message);
+            id = message.get("parentId").and_then(Value::as_str);
+        }
+        thread.reverse();
+    }
+    if thread.is_empty() {
+        thread = chat
+            .get("messages")
+            .and_then(Value::as_array)
+            .map(|messages| messages.iter().collect())
+            .unwrap_or_default();
+    }
+
+    let mut messages = Vec::new();
+    for message in thread {
+        let content = text(message.get("content"));
+        match message.get("role").and_then(Value::as_str) {
+            Some("user") => {
+                messages.push(Correspondence::user(content, open_webui_images(message)))
+            }
+            Some("assistant") => {
+                let model = message
+                    .get("modelName")
+                    .or_else(|| message.get("model"))
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                let (thinking, answer, seconds) = split_open_webui_reasoning(&content);
+                let mut reply = Correspondence::bot(with_thinking(&thinking, &answer), model);
+                if let Correspondence::Bot {
+                    thinking_seconds, ..
+                } = &mut reply
+                {
+                    *thinking_seconds = seconds;
+                }
+                messages.push(reply);
+            }
+            _ => {}
+        }
+    }
+    let title = entry
+        .get("title")
+        .or_else(|| chat.get("title"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    let mut imported = ImportedChat::new(
+        title,
+        timestamp(entry.get("updated_at")).or_else(|| timestamp(chat.get("timestamp"))),
+        messages,
+    );
+    imported.pinned = entry.get("pinned").and_then(Value::as_bool) == Some(true);
+    imported
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_export::{ChatDocument, ExportFormat};
+    use serde_json::json;
+
+    fn texts(chat: &ImportedChat) -> Vec<&str> {
+        chat.messages
+            .iter()
+            .map(|message| match message {
+                Correspondence::User { text, .. } | Correspondence::Bot { text, .. } => {
+                    text.as_str()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn chatgpt_and_open_webui_follow_the_visible_branch() {
+        let chatgpt = json!([{
+            "title": "Rust help",
+            "update_time": 1760000000.5,
+            "current_node": "c",
+            "default_model_slug": "gpt-4o",
+            "mapping": {
+                "root": {"message": null, "parent": null},
+                "s": {"parent": "root", "message": {"author": {"role": "system"},
+                    "content": {"content_type": "text", "parts": [""]}}},
+                "a": {"parent": "s", "message": {"author": {"role": "user"},
+                    "content": {"content_type": "text", "parts": ["What is a trait?"]}}},
+                "old": {"parent": "a", "message": {"author": {"role": "assistant"},
+                    "content": {"content_type": "text", "parts": ["Regenerated away"]}}},
+                "t": {"parent": "a", "message": {"author": {"role": "assistant"},
+                    "content": {"content_type": "thoughts", "thoughts": [{"content": "Keep it short."}]}}},
+                "c": {"parent": "t", "message": {"author": {"role": "assistant"},
+                    "metadata": {"model_slug": "o3"},
+                    "content": {"content_type": "text", "parts": ["A shared interface."]}}}
+            }
+        }]);
+        let (source, chats) = parse_export(&chatgpt.to_string()).unwrap();
+        assert_eq!(source, ImportSource::ChatGpt);
+        assert_eq!(chats[0].title, "Rust help");
+        assert_eq!(
+            texts(&chats[0]),
+            [
+                "What is a trait?",
+                "<think>Keep it short.</think>A shared interface."
+            ]
+        );
+        assert_eq!(chats[0].models(), ["o3"]);
+        assert!(chats[0].updated_at.starts_with("2025-10-"));
+
+        let open_webui = json!([{
+            "title": "Llamas",
+            "updated_at": 1760000000,
+            "pinned": true,
+            "chat": {"history": {"currentId": "m3", "messages": {
+                "m1": {"id": "m1", "parentId": null, "role": "user", "content": "Hi"},
+                "m2": {"id": "m2", "parentId": "m1", "role": "assistant", "model": "llama3.2:latest",
+                    "content": "<details type=\"reasoning\" done=\"true\" duration=\"2\">\n<summary>Thought for 2 seconds</summary>\n> Greet back.\n</details>\nHello!"},
+                "m3": {"id": "m3", "parentId": "m2", "role": "user", "content": "Bye"},
+                "m4": {"id": "m4", "parentId": "m2", "role": "user", "content": "Edited away"}
+            }}}
+        }]);
+        let (source, chats) = parse_export(&open_webui.to_string()).unwrap();
+        assert_eq!(source, ImportSource::OpenWebUi);
+        assert_eq!(
+            texts(&chats[0]),
+            ["Hi", "<think>Greet back.</think>Hello!", "Bye"]
+        );
+        assert!(chats[0].pinned);
+        assert!(matches!(
+            chats[0].messages[1],
+            Correspondence::Bot {
+                thinking_seconds: Some(2),
+                ..
+            }
+        ));
+
+        assert!(parse_export("[]").is_err());
+        assert!(parse_export("{\"format\": \"other\"}").is_err());
+    }
+
+    #[test]
+    fn own_json_export_reads_back() {
+        let mut answer =
+            Correspondence::bot("<think>Plan</think>Answer".into(), Some("llama3.2".into()));
+        if let Correspondence::Bot { sources, .. } = &mut answer {
+            sources.push(crate::web_search::WebSource {
+                title: "Docs".into(),
+                url: "https://example.com".into(),
+            });
+        }
+        let document = ChatDocument {
+            id: "chat-1".into(),
+            title: "Saved".into(),
+            updated_at: "2026-10-17T09:30:00+02:00".into(),
+            pinned: false,
+            messages: vec![Correspondence::user("Question".into(), Vec::new()), answer],
+        };
+        let json = chat_export::export(&[document], ExportFormat::Json, "");
+        let (source, chats) = parse_export(&json).unwrap();
+        assert_eq!(source, ImportSource::OllamaGui);
+        assert_eq!(chats[0].title, "Saved");
+        assert_eq!(chats[0].updated_at, "2026-10-17T09:30:00+02:00");
+        assert_eq!(texts(&chats[0]), ["Question", "<think>Plan</think>Answer"]);
+        assert!(matches!(
+            &chats[0].messages[1],
+            Correspondence::Bot { sources, web_search_used: true, .. } if sources.len() == 1
+        ));
+    }
+}
@@ -0,0 +1,417 @@
+//! Full-text search over saved chat titles and messages.
+//!
+//! The index maps words to the messages that contain them. Each chat is
+//! indexed together with a version stamp, so `sync` only re-reads chats that
+//! changed since the last search. Superseded entries are left in place as
+//! tombstones and swept up once they outnumber the live ones.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+use crate::app::{SavedChat, StoredMessage};
+use crate::split_thinking_text;
+
+/// Marks a posting that comes from the chat title rather than a message.
+const TITLE: u32 = u32::MAX;
+const TITLE_WEIGHT: f32 = 3.0;
+/// Words shorter than this only match exactly, so one typed letter does not
+/// walk half the vocabulary.
+const MIN_PREFIX_LENGTH: usize = 2;
+const SNIPPET_BEFORE: usize = 40;
+const SNIPPET_AFTER: usize = 110;
+
+#[derive(Clone, Copy, Debug)]
+struct Posting {
+    document: u32,
+    message: u32,
+    count: u32,
+}
+
+#[derive(Clone, Debug)]
+struct IndexedChat {
+    version: String,
+    document: u32,
+    postings: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct SearchIndex {
+    chats: HashMap<String, IndexedChat>,
+    /// Chat id per document number; `None` once the chat was re-indexed or
+    /// deleted.
+    documents: Vec<Option<String>>,
+    postings: BTreeMap<String, Vec<Posting>>,
+    live_postings: usize,
+    dead_postings: usize,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchHit {
+    pub chat_id: String,
+    pub title: String,
+    /// Position of the best matching message in the visible thread; `None`
+    /// when only the title matched.
+    pub message: Option<usize>,
+    pub snippet: String,
+}
+
+/// Lowercase words with their byte offset in `text`.
+fn words(text: &str) -> impl Iterator<Item = (usize, String)> + '_ {
+    let mut start = None;
+    text.char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+        .filter_map(move |(index, character)| {
+            if character.is_alphanumeric() {
+                start.get_or_insert(index);
+                None
+            } else {
+                start
+                    .take()
+                    .map(|start| (start, text[start..index].to_lowercase()))
+            }
+        })
+}
+
+fn visible_text(message: &StoredMessage) -> String {
+    match message {
+        StoredMessage::User(text) => text.clone(),
+        StoredMessage::Bot(text) => split_thinking_text(text).1,
+    }
+}
+
+fn chat_version(chat: &SavedChat) -> String {
+    format!(
+        "{}\u{0}{}\u{0}{}",
+        chat.updated_at,
+        chat.title,
+        chat.thread.messages.len()
+    )
+}
+
+impl SearchIndex {
+    /// Brings the index up to date with `chats`, re-indexing only chats whose
+    /// version changed and dropping chats that no longer exist.
+    pub fn sync(&mut self, chats: &[SavedChat]) {
+        let mut present = HashSet::with_capacity(chats.len());
+        for chat in chats {
+            present.insert(chat.id.as_str());
+            let version = chat_version(chat);
+            if self
+                .chats
+                .get(&chat.id)
+                .is_some_and(|indexed| indexed.version == version)
+            {
+                continue;
+            }
+            self.retire(&chat.id);
+            self.add(chat, version);
+        }
+        let removed = self
+            .chats
+            .keys()
+            .filter(|id| !present.contains(id.as_str()))
+            .cloned()
+            .collect::<Vec<_>>();
+        for id in removed {
+            self.retire(&id);
+        }
+        if self.dead_postings > self.live_postings.max(10_000) {
+            *self = Self::default();
+            self.sync(chats);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chats.is_empty()
+    }
+
+    fn retire(&mut self, id: &str) {
+        if let Some(indexed) = self.chats.remove(id) {
+            self.documents[indexed.document as usize] = None;
+            self.live_postings -= indexed.postings;
+            self.dead_postings += indexed.postings;
+        }
+    }
+
+    fn add(&mut self, chat: &SavedChat, version: String) {
+        let document = self.documents.len() as u32;
+        self.documents.push(Some(chat.id.clone()));
+        let mut added = 0;
+        let texts = std::iter::once((TITLE, chat.title.clone())).chain(
+            chat.thread
+                .messages
+                .iter()
+                .enumerate()
+                .map(|(index, message)| (index as u32, visible_text(message))),
+        );
+        for (message, text) in texts {
+            let mut found = words(&text).map(|(_, word)| word).collect::<Vec<_>>();
+            found.sort_unstable();
+            for run in found.chunk_by(|a, b| a == b) {
+                let posting = Posting {
+                    document,
+                    message,
+                    count: run.len() as u32,
+                };
+                match self.postings.get_mut(run[0].as_str()) {
+                    Some(postings) => postings.push(posting),
+                    None => {
+                        self.postings.insert(run[0].clone(), vec![posting]);
+                    }
+                }
+                added += 1;
+            }
+        }
+        self.live_postings += added;
+        self.chats.insert(
+            chat.id.clone(),
+            IndexedChat {
+                version,
+                document,
+                postings: added,
+            },
+        );
+    }
+
+    /// Finds chats containing every word of `query`. Words also match as
+    /// prefixes, so results follow along while a word is being typed. Chats
+    /// are ranked by how often and where the words occur, with title matches
+    /// counting most.
+    pub fn search(&self, chats: &[SavedChat], query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut terms = words(query).map(|(_, word)| word).collect::<Vec<_>>();
+        terms.sort_unstable();
+        terms.dedup();
+        terms.truncate(64);
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        #[derive(Default)]
+        struct Matches {
+            terms: u64,
+            score: f32,
+            messages: HashMap<u32, (u64, f32)>,
+        }
+        let mut documents = HashMap::<u32, Matches>::new();
+        for (term_index, term) in terms.iter().enumerate() {
+            let bit = 1u64 << term_index;
+            let prefix = term.chars().count() >= MIN_PREFIX_LENGTH;
+            let matching = self
+                .postings
+                .range::<str, _>((Bound::Included(term.as_str()), Bound::Unbounded))
+                .take_while(|(word, _)| {
+                    if prefix {
+                        word.starts_with(term.as_str())
+                    } else {
+                        word.as_str() == term
+                    }
+                });
+            for (word, postings) in matching {
+                let weight = if word == term { 2.0 } else { 1.0 };
+                for posting in postings {
+                    if self.documents[posting.document as usize].is_none() {
+                        continue;
+                    }
+                    let mut score = weight * (1.0 + (posting.count as f32).ln());
+                    if posting.message == TITLE {
+                        score *= TITLE_WEIGHT;
+                    }
+                    let matches = documents.entry(posting.document).or_default();
+                    matches.terms |= bit;
+                    matches.score += score;
+                    let message = matches.messages.entry(posting.message).or_default();
+                    message.0 |= bit;
+                    message.1 += score;
+                }
+            }
+        }
+
+        let all_terms = if terms.len() == 64 {
+            u64::MAX
+        } else {
+            (1u64 << terms.len()) - 1
+        };
+        let by_id = chats
+            .iter()
+            .map(|chat| (chat.id.as_str(), chat))
+            .collect::<HashMap<_, _>>();
+        let mut ranked = documents
+            .into_iter()
+            .filter(|(_, matches)| matches.terms == all_terms)
+            .filter_map(|(document, matches)| {
+                let id = self.documents[document as usize].as_deref()?;
+                let chat = *by_id.get(id)?;
+                // The message that holds the most query words wins, then the
+                // one where they occur most often.
+                let best = matches
+                    .messages
+                    .iter()
+                    .filter(|(message, _)| **message != TITLE)
+                    .max_by(|(a_index, a), (b_index, b)| {
+                        a.0.count_ones()
+                            .cmp(&b.0.count_ones())
+                            .then(a.1.total_cmp(&b.1))
+                            .then(b_index.cmp(a_index))
+                    })
+                    .map(|(message, _)| *message as usize);
+                let bonus = best
+                    .and_then(|best| matches.messages.get(&(best as u32)))
+                    .filter(|(found, _)| *found == all_terms)
+                    .map_or(0.0, |_| 2.0);
+                Some((matches.score + bonus, chat, best))
+            })
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| {
+            b.0.total_cmp(&a.0)
+                .then_with(|| b.1.updated_at.cmp(&a.1.updated_at))
+        });
+        ranked
+            .into_iter()
+            .take(limit)
+            .map(|(_, chat, message)| {
+                let text = message
+                    .and_then(|message| chat.thread.messages.get(message))
+                    .map(visible_text)
+                    .unwrap_or_else(|| chat.title.clone());
+                SearchHit {
+                    chat_id: chat.id.clone(),
+                    title: chat.title.clone(),
+                    message,
+                    snippet: snippet(&text, &terms),
+                }
+            })
+            .collect()
+    }
+}
+
+/// A single-line excerpt around the first word that matches a term.
+fn snippet(text: &str, terms: &[String]) -> String {
+    let start = words(text)
+        .find(|(_, word)| terms.iter().any(|term| word.starts_with(term.as_str())))
+        .map_or(0, |(start, _)| start);
+    let before = text[..start].chars().rev().take(SNIPPET_BEFORE).count();
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .nth(before.saturating_sub(1))
+        .map_or(start, |(index, _)| index);
+    let to = text[start..]
+        .char_indices()
+        .nth(SNIPPET_AFTER)
+        .map_or(text.len(), |(index, _)| start + index);
+    let excerpt = text[from..to]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "{}{excerpt}{}",
+        if from > 0 { "…" } else { "" },
+        if to < text.len() { "…" } else { "" }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{ChatSettings, Correspondence, CurrentChat};
+
+    fn chat(id: &str, title: &str, messages: &[&str], updated_at: &str) -> SavedChat {
+        let messages = messages
+            .iter()
+            .enumerate()
+            .map(|(index, text)| {
+                if index % 2 == 0 {
+                    Correspondence::user(text.to_string(), Vec::new())
+                } else {
+                    Correspondence::bot(text.to_string(), None)
+                }
+            })
+            .collect();
+        let current = CurrentChat {
+            chats: vec![],
+            messages,
+            bot_responding: false,
+            context_summary: None,
+        };
+        let mut chat = SavedChat::from_current(
+            id.into(),
+            title.into(),
+            &current,
+            false,
+            ChatSettings::default(),
+        );
+        chat.updated_at = updated_at.into();
+        chat
+    }
+
+    #[test]
+    fn search_ranks_chats_and_points_at_the_matching_message() {
+        let chats = vec![
+            chat(
+                "a",
+                "Trip planning",
+                &[
+                    "Where should we go?",
+                    "<think>borrow</think>Try Lisbon in spring.",
+                ],
+                "2026-01-01",
+            ),
+            chat(
+                "b",
+                "Borrow checker",
+                &[
+                    "Explain lifetimes",
+                    "The borrow checker tracks how long each borrow lives.",
+                ],
+                "2026-01-02",
+            ),
+        ];
+        let mut index = SearchIndex::default();
+        index.sync(&chats);
+
+        let hits = index.search(&chats, "BORROW", 10);
+        assert_eq!(hits.len(), 1, "thinking text is not searched");
+        assert_eq!(hits[0].chat_id, "b");
+        assert_eq!(hits[0].message, Some(1));
+        assert_eq!(
+            hits[0].snippet,
+            "The borrow checker tracks how long each borrow lives."
+        );
+
+        let hits = index.search(&chats, "lisb spring", 10);
+        assert_eq!(hits[0].chat_id, "a");
+        assert_eq!(hits[0].message, Some(1));
+        assert!(index.search(&chats, "lisbon lifetimes", 10).is_empty());
+        assert_eq!(index.search(&chats, "trip", 10)[0].message, None);
+
+        let long = format!("{} needle {}", "word ".repeat(30), "tail ".repeat(40));
+        let excerpt = snippet(&long, &["needle".to_string()]);
+        assert!(excerpt.starts_with('…') && excerpt.ends_with('…'));
+        assert!(excerpt.contains("needle"));
+    }
+
+    #[test]
+    fn sync_reindexes_only_changed_chats() {
+        let mut chats = vec![
+            chat("a", "First", &["apples"], "2026-01-01"),
+            chat("b", "Second", &["bananas"], "2026-01-01"),
+        ];
+        let mut index = SearchIndex::default();
+        index.sync(&chats);
+        assert_eq!(index.documents.len(), 2);
+
+        index.sync(&chats);
+        assert_eq!(index.documents.len(), 2, "unchanged chats are kept");
+
+        chats[1] = chat("b", "Second", &["bananas", "cherries"], "2026-01-02");
+        index.sync(&chats);
+        assert_eq!(index.documents.len(), 3);
+        assert_eq!(index.search(&chats, "cherries", 10)[0].chat_id, "b");
+        assert_eq!(index.search(&chats, "bananas", 10).len(), 1);
+
+        chats.remove(0);
+        index.sync(&chats);
+        assert!(index.search(&chats, "apples", 10).is_empty());
+        assert_eq!(index.chats.len(), 1);
+    }
+}
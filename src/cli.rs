@@ -0,0 +1,334 @@
+//! `ollama-gui ask`: sends one prompt with the configuration the GUI uses
+//! (settings, server profile, system prompts, content filter, web search and
+//! chat storage) and streams the answer to stdout.
+
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+use crate::{Message, Program, PromptOrigin, split_thinking_text};
+
+pub const COMMAND: &str = "ask";
+
+const USAGE: &str = "\
+Usage: ollama-gui ask [--model NAME] [--system-prompt NAME] [--chat ID] [--stdin] [QUESTION]
+
+Sends QUESTION with the settings of the desktop app and prints the answer.
+The question is read from stdin when it is omitted or `-`; with --stdin, stdin
+is read as well and appended to QUESTION.
+
+Options:
+  -m, --model NAME           Model to use instead of the chat's or the server's last one
+  -s, --system-prompt NAME   System prompt profile from config/defaultprompts.json
+  -c, --chat ID              Continue a saved chat instead of starting a new one
+      --stdin                Also read stdin and append it to QUESTION
+  -h, --help                 Show this help";
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct AskArgs {
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub chat: Option<String>,
+    /// Append stdin to the question given as arguments.
+    pub stdin: bool,
+    pub question: Option<String>,
+}
+
+/// Parses the arguments after `ask`. Options accept `--name value` and
+/// `--name=value`; the remaining words form the question.
+pub fn parse_ask_args(args: &[String]) -> Result<AskArgs, String> {
+    let mut parsed = AskArgs::default();
+    let mut words = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline_value) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+        let slot = match flag {
+            "-m" | "--model" => &mut parsed.model,
+            "-s" | "--system-prompt" => &mut parsed.system_prompt,
+            "-c" | "--chat" => &mut parsed.chat,
+            "--stdin" if inline_value.is_none() => {
+                parsed.stdin = true;
+                continue;
+            }
+            "--" => {
+                words.extend(args.by_ref().cloned());
+                break;
+            }
+            flag if flag.starts_with('-') && flag != "-" => {
+                return Err(format!("unknown option {flag}"));
+            }
+            _ => {
+                words.push(arg.clone());
+                continue;
+            }
+        };
+        let value = match inline_value {
+            Some(value) => value,
+            None => args
+                .next()
+                .cloned()
+                .ok_or_else(|| format!("{flag} needs a value"))?,
+        };
+        *slot = Some(value);
+    }
+    parsed.question = (!words.is_empty()).then(|| words.join(" "));
+    Ok(parsed)
+}
+
+/// Joins the question from the arguments with piped input, so
+/// `git diff | ollama-gui ask --stdin "Review this"` sends both.
+fn combine_question(question: Option<&str>, piped: Option<&str>) -> Option<String> {
+    let question = question.filter(|question| *question != "-").map(str::trim);
+    let piped = piped.map(str::trim).filter(|piped| !piped.is_empty());
+    match (question, piped) {
+        (Some(question), Some(piped)) => Some(format!("{question}\n\n{piped}")),
+        (Some(question), None) => Some(question.to_string()),
+        (None, piped) => piped.map(str::to_string),
+    }
+    .filter(|question| !question.is_empty())
+}
+
+/// Reads stdin only when the question is missing or `-`, or `--stdin` asks
+/// for it, so a question passed as an argument never waits on an open pipe.
+fn read_question(question: Option<&str>, append_stdin: bool) -> Result<String, String> {
+    let needs_stdin = question.is_none_or(|question| question == "-");
+    let piped = if needs_stdin || append_stdin {
+        let mut input = String::new();
+        io::stdin()
+            .lock()
+            .read_to_string(&mut input)
+            .map_err(|error| format!("could not read stdin: {error}"))?;
+        Some(input)
+    } else {
+        None
+    };
+    combine_question(question, piped.as_deref())
+        .ok_or_else(|| "no question given; pass one or pipe it through stdin".to_string())
+}
+
+/// Writes the part of each streamed snapshot that has not been printed yet.
+/// Reasoning stays in the saved chat and is not printed.
+#[derive(Default)]
+struct AnswerPrinter {
+    printed: String,
+}
+
+impl AnswerPrinter {
+    fn show(&mut self, text: &str, out: &mut impl Write) -> io::Result<()> {
+        let (_, visible) = split_thinking_text(text);
+        match visible.strip_prefix(self.printed.as_str()) {
+            Some(new_text) => out.write_all(new_text.as_bytes())?,
+            // A web-search turn that became a tool call was replaced by the
+            // answer; what was already printed cannot be taken back.
+            None => write!(out, "\n\n{visible}")?,
+        }
+        self.printed = visible;
+        out.flush()
+    }
+
+    fn finish(&self, out: &mut impl Write) -> io::Result<()> {
+        if !self.printed.is_empty() && !self.printed.ends_with('\n') {
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+}
+
+/// Runs `ask` with the arguments that follow it and returns the exit code.
+pub fn run(args: &[String]) -> i32 {
+    attach_parent_console();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{USAGE}");
+        return 0;
+    }
+    match ask(args) {
+        Ok(()) => 0,
+        Err(error) => {
+            eprintln!("ollama-gui ask: {error}");
+            1
+        }
+    }
+}
+
+fn ask(args: &[String]) -> Result<(), String> {
+    let args = parse_ask_args(args)?;
+    let question = read_question(args.question.as_deref(), args.stdin)?;
+
+    let (mut program, _) = Program::boot();
+    if program.debug_message.is_error {
+        eprintln!("{}", program.debug_message.message);
+    }
+
+    match &args.chat {
+        Some(id) if program.saved_chats.iter().any(|chat| &chat.id == id) => {
+            drop(program.update(Message::OpenChat(id.clone())));
+        }
+        Some(id) => return Err(format!("no saved chat with the id {id}")),
+        None => drop(program.update(Message::NewChat)),
+    }
+    let model = args
+        .model
+        .or_else(|| program.user_information.model.clone())
+        .or_else(|| program.user_information.ip_address.last_model.clone())
+        .ok_or("no model selected; pass --model NAME")?;
+    if program.user_information.model.as_ref() != Some(&model) {
+        drop(program.select_model(model));
+    }
+    if let Some(name) = args.system_prompt {
+        let prompts = &program.system_prompt.system_prompts_as_hashmap;
+        if !prompts.contains_key(&name) {
+            let mut names = prompts.keys().cloned().collect::<Vec<_>>();
+            names.sort();
+            return Err(format!(
+                "unknown system prompt {name}; available: {}",
+                names.join(", ")
+            ));
+        }
+        program.system_prompt.system_prompt = Some(name);
+    }
+
+    let chat_id = program.current_chat_id.clone();
+    let Some(generation) = program.prepare_prompt_in(
+        chat_id.clone(),
+        program.user_information.clone(),
+        false,
+        program.web_search_for_chat,
+        question,
+        Vec::new(),
+        PromptOrigin::Composer,
+    ) else {
+        let debug_message = program.channels.debug_channel.lock().unwrap().1.try_recv();
+        return Err(debug_message
+            .map(|message| message.message)
+            .unwrap_or_else(|_| program.debug_message.message.clone()));
+    };
+    let renders = program.active_prompts[&chat_id].render_receiver.clone();
+
+    let runtime = tokio::runtime::Runtime::new()
+        .map_err(|error| format!("could not start the async runtime: {error}"))?;
+    let generation = runtime.spawn(generation);
+    let mut printer = AnswerPrinter::default();
+    let mut stdout = io::stdout().lock();
+    let print_error = |error: io::Error| format!("could not write the answer: {error}");
+    while !generation.is_finished() {
+        if let Ok(render) = renders.recv_timeout(Duration::from_millis(50)) {
+            printer
+                .show(&render.text, &mut stdout)
+                .map_err(print_error)?;
+        }
+    }
+    // The renderer sends its last snapshot once the generation has finished.
+    while let Ok(render) = renders.recv() {
+        printer
+            .show(&render.text, &mut stdout)
+            .map_err(print_error)?;
+    }
+    printer.finish(&mut stdout).map_err(print_error)?;
+    let _ = runtime.block_on(generation);
+
+    while let Ok((_, notice)) = program.chat_notice_receiver.try_recv() {
+        eprintln!("{}", program.redact_server_secrets(&notice.message));
+    }
+    let logs = program
+        .channels
+        .logging_channel
+        .lock()
+        .unwrap()
+        .1
+        .try_iter()
+        .collect::<Vec<_>>();
+    for log in logs {
+        program.record_log(log);
+    }
+    program.finish_prompt(&chat_id);
+    eprintln!("Chat: {chat_id}");
+
+    if printer.printed.trim().is_empty() {
+        Err("the model did not return an answer".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+/// The app is built as a Windows GUI program, which starts without a
+/// console; borrow the terminal's so output reaches it.
+#[cfg(windows)]
+fn attach_parent_console() {
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+    // SAFETY: AttachConsole takes no pointers and fails harmlessly when
+    // there is no parent console or one is already attached.
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn ask_arguments_and_piped_input_form_the_request() {
+        let parsed = parse_ask_args(&args(&[
+            "--model",
+            "llama3.2",
+            "--system-prompt=reviewer",
+            "-c",
+            "chat-1",
+            "--stdin",
+            "Is",
+            "this",
+            "safe?",
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            AskArgs {
+                model: Some("llama3.2".into()),
+                system_prompt: Some("reviewer".into()),
+                chat: Some("chat-1".into()),
+                stdin: true,
+                question: Some("Is this safe?".into()),
+            }
+        );
+        assert!(parse_ask_args(&args(&["--model"])).is_err());
+        assert!(parse_ask_args(&args(&["--verbose", "hi"])).is_err());
+        assert!(parse_ask_args(&args(&["--stdin=yes", "hi"])).is_err());
+        assert!(!parse_ask_args(&args(&["hi"])).unwrap().stdin);
+        assert_eq!(
+            parse_ask_args(&args(&["--", "--model"])).unwrap().question,
+            Some("--model".into())
+        );
+
+        assert_eq!(
+            combine_question(Some("Review this"), Some("fn main() {}\n")).as_deref(),
+            Some("Review this\n\nfn main() {}")
+        );
+        assert_eq!(
+            combine_question(Some("-"), Some("from stdin")).as_deref(),
+            Some("from stdin")
+        );
+        assert_eq!(combine_question(None, Some("  \n")), None);
+    }
+
+    #[test]
+    fn printer_streams_only_new_visible_text() {
+        let mut printer = AnswerPrinter::default();
+        let mut out = Vec::new();
+        printer.show("<think>plan</think>Hel", &mut out).unwrap();
+        printer.show("<think>plan</think>Hello", &mut out).unwrap();
+        printer.finish(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Hello\n");
+    }
+}
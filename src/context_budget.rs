@@ -0,0 +1,236 @@
+//! Keeps chat history inside the model's context window.
+//!
+//! Token counts are estimated rather than tokenized: Ollama does not expose a
+//! tokenizer endpoint, and a conservative character ratio is close enough to
+//! decide which of the oldest turns must leave the prompt.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{self, ModelBackend};
+
+const CHARS_PER_TOKEN: usize = 4;
+const MESSAGE_OVERHEAD_TOKENS: usize = 4;
+/// Vision models differ widely; this matches common CLIP-style encoders.
+const IMAGE_TOKENS: usize = 768;
+const MAX_SUMMARY_TOKENS: usize = 1_024;
+const SUMMARY_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// A rolling summary of the oldest messages of a chat. `covers` counts the
+/// leading messages of the visible path that the summary replaces.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ContextSummary {
+    pub covers: usize,
+    pub text: String,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HistoryPlan {
+    /// Leading history messages that no longer fit verbatim.
+    pub dropped: usize,
+    /// Room left for a summary of the dropped messages.
+    pub summary_tokens: usize,
+}
+
+pub fn estimate_text_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(CHARS_PER_TOKEN)
+}
+
+pub fn estimate_message_tokens(message: &serde_json::Value) -> usize {
+    let text = message
+        .get("content")
+        .and_then(serde_json::Value::as_str)
+        .unwrap_or_default();
+    let images = message
+        .get("images")
+        .and_then(serde_json::Value::as_array)
+        .map_or(0, Vec::len);
+    MESSAGE_OVERHEAD_TOKENS + estimate_text_tokens(text) + images * IMAGE_TOKENS
+}
+
+/// Decides how much of `history` (oldest first) can be sent alongside the
+/// system prompt and the new turn. The response reservation is capped at half
+/// the window so an oversized "Maximum response" never hides all history.
+pub fn plan_history(
+    system_prompt: &str,
+    history: &[serde_json::Value],
+    new_turn: &serde_json::Value,
+    context_tokens: u32,
+    max_response_tokens: u32,
+) -> HistoryPlan {
+    let window = context_tokens as usize;
+    let budget = window
+        .saturating_sub((max_response_tokens as usize).min(window / 2))
+        .saturating_sub(MESSAGE_OVERHEAD_TOKENS + estimate_text_tokens(system_prompt))
+        .saturating_sub(estimate_message_tokens(new_turn));
+    let total = history.iter().map(estimate_message_tokens).sum::<usize>();
+    if total <= budget {
+        return HistoryPlan {
+            dropped: 0,
+            summary_tokens: 0,
+        };
+    }
+
+    let summary_tokens = (budget / 8).min(MAX_SUMMARY_TOKENS);
+    let mut remaining = budget - summary_tokens;
+    let kept = history
+        .iter()
+        .rev()
+        .take_while(|message| {
+            let tokens = estimate_message_tokens(message);
+            let fits = tokens <= remaining;
+            remaining = remaining.saturating_sub(tokens);
+            fits
+        })
+        .count();
+    let mut dropped = history.len() - kept;
+    // Verbatim history must open with a user turn, not an orphaned answer.
+    while dropped < history.len() && history[dropped]["role"] != "user" {
+        dropped += 1;
+    }
+    HistoryPlan {
+        dropped,
+        summary_tokens,
+    }
+}
+
+/// The system message that stands in for summarised turns.
+pub fn summary_message(summary: &str) -> serde_json::Value {
+    serde_json::json!({
+        "role": "system",
+        "content": format!("Summary of the earlier conversation:\n{summary}"),
+    })
+}
+
+fn summary_request_messages(
+    previous_summary: Option<&str>,
+    messages: &[serde_json::Value],
+    transcript_tokens: usize,
+) -> Vec<serde_json::Value> {
+    let mut transcript = String::new();
+    if let Some(previous) = previous_summary {
+        transcript.push_str(&format!("Earlier summary:\n{previous}\n\n"));
+    }
+    for message in messages {
+        let speaker = match message["role"].as_str() {
+            Some("assistant") => "Assistant",
+            _ => "User",
+        };
+        let text = message["content"].as_str().unwrap_or_default();
+        transcript.push_str(&format!("{speaker}: {text}\n\n"));
+    }
+    // Keep the most recent part if even the dropped turns exceed the window.
+    let max_chars = transcript_tokens * CHARS_PER_TOKEN;
+    let skip = transcript.chars().count().saturating_sub(max_chars);
+    let transcript = transcript.chars().skip(skip).collect::<String>();
+
+    vec![
+        serde_json::json!({
+            "role": "system",
+            "content": "Summarise the conversation you are given so it can replace it as context. Keep names, decisions, facts, open questions, and any instructions the user gave. Write plain prose without preamble.",
+        }),
+        serde_json::json!({"role": "user", "content": transcript}),
+    ]
+}
+
+/// Asks the chat model for a summary of `messages`, folding in an earlier
+/// summary so long chats are condensed incrementally.
+pub async fn summarise_history(
+    backend: &dyn ModelBackend,
+    model: &str,
+    previous_summary: Option<&str>,
+    messages: &[serde_json::Value],
+    summary_tokens: usize,
+    context_tokens: u32,
+) -> Result<String, String> {
+    let transcript_tokens = (context_tokens as usize)
+        .saturating_sub(summary_tokens)
+        .saturating_sub(256);
+    let body = serde_json::json!({
+        "model": model,
+        "messages": summary_request_messages(previous_summary, messages, transcript_tokens),
+        "stream": false,
+        "think": false,
+        "options": {
+            "num_ctx": context_tokens,
+            "num_predict": summary_tokens.max(64),
+        },
+    });
+    let response = backend
+        .chat_request(&body)?
+        .timeout(SUMMARY_TIMEOUT)
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    let status = response.status();
+    let text = response.text().await.map_err(|error| error.to_string())?;
+    if !status.is_success() {
+        return Err(backend::error_detail(&text).unwrap_or_else(|| status.to_string()));
+    }
+    let value = backend.chat_reply(
+        serde_json::from_str::<serde_json::Value>(&text).map_err(|error| error.to_string())?,
+    );
+    let content = value["message"]["content"].as_str().unwrap_or_default();
+    let (_, summary) = crate::split_thinking_text(content);
+    let summary = summary.trim();
+    if summary.is_empty() {
+        return Err("the model returned an empty summary".to_string());
+    }
+    Ok(summary.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate_message_tokens, plan_history, summary_request_messages};
+
+    fn turn(role: &str, text: &str) -> serde_json::Value {
+        serde_json::json!({"role": role, "content": text})
+    }
+
+    #[test]
+    fn short_history_is_sent_unchanged() {
+        let history = vec![turn("user", "Hi"), turn("assistant", "Hello")];
+        let plan = plan_history("Be brief.", &history, &turn("user", "Next"), 4_096, 512);
+        assert_eq!(plan.dropped, 0);
+    }
+
+    #[test]
+    fn oldest_turns_leave_first_and_kept_history_opens_with_user() {
+        let long = "x".repeat(4_000);
+        let history = vec![
+            turn("user", &long),
+            turn("assistant", &long),
+            turn("user", "Recent question"),
+            turn("assistant", &long),
+            turn("user", "Latest question"),
+            turn("assistant", "Latest answer"),
+        ];
+        let plan = plan_history("", &history, &turn("user", "Now"), 3_000, 1_000);
+        assert!(plan.dropped >= 2);
+        assert_eq!(history[plan.dropped]["role"], "user");
+        let kept = history[plan.dropped..]
+            .iter()
+            .map(estimate_message_tokens)
+            .sum::<usize>();
+        assert!(kept + plan.summary_tokens <= 3_000 - 1_000);
+    }
+
+    #[test]
+    fn attachments_count_towards_the_budget() {
+        let with_image = serde_json::json!({"role": "user", "content": "", "images": ["a"]});
+        assert!(estimate_message_tokens(&with_image) > estimate_message_tokens(&turn("user", "")));
+    }
+
+    #[test]
+    fn summary_transcript_keeps_the_newest_text_when_too_long() {
+        let messages = vec![
+            turn("user", &"old ".repeat(1_000)),
+            turn("assistant", "newest"),
+        ];
+        let request = summary_request_messages(Some("before"), &messages, 10);
+        let transcript = request[1]["content"].as_str().unwrap();
+        assert!(transcript.contains("newest"));
+        assert!(transcript.chars().count() <= 40);
+    }
+}
@@ -0,0 +1,607 @@
+//! Opt-in HTTP API on 127.0.0.1 that lets editors and scripts drive the
+//! running app. Each request becomes a `Message::ControlApi`, so it goes
+//! through the same update paths as the buttons in the window.
+//!
+//! Every request needs `Authorization: Bearer <token>`. Endpoints:
+//!
+//! - `GET /v1/chats` lists saved chats.
+//! - `POST /v1/chats` opens a new chat.
+//! - `GET /v1/chats/{id}` returns a chat's messages.
+//! - `POST /v1/chats/{id}/open` opens a chat in the window.
+//! - `POST /v1/chats/{id}/messages` and `POST /v1/messages` (open chat) send
+//!   `{"prompt": "…", "images": [{"path": "…"} | {"name": "…", "data": "<base64>"}]}`
+//!   and stream the answer as NDJSON: `{"delta": "…"}` lines, then
+//!   `{"done": true, "response": "…"}`.
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::net::Ipv4Addr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
+use iced::futures::channel::mpsc;
+use iced::futures::{SinkExt, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::{mpsc as tokio_mpsc, oneshot};
+use tokio::time::{Instant, timeout_at};
+
+use crate::app::{ChatImage, Correspondence, SavedChat};
+use crate::{chat_image_from_bytes, load_chat_image, split_thinking_text};
+
+pub const DEFAULT_PORT: u16 = 11500;
+const MAX_HEADER_BYTES: usize = 16 * 1024;
+/// Leaves room for a few base64-encoded attachments.
+const MAX_BODY_BYTES: usize = 96 * 1024 * 1024;
+/// How long a client gets to send its whole request, so idle or slow
+/// connections cannot hold on to a task.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ControlApiSettings {
+    pub enabled: bool,
+    pub port: u16,
+    pub token: String,
+}
+
+impl Default for ControlApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: DEFAULT_PORT,
+            token: String::new(),
+        }
+    }
+}
+
+/// A random 64-character hex token. `RandomState` is seeded from the
+/// operating system, which avoids pulling in a random number crate.
+pub fn new_token() -> String {
+    let mut hasher = Sha256::new();
+    for index in 0..4_u8 {
+        hasher.update(RandomState::new().hash_one(index).to_le_bytes());
+    }
+    hasher.update(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos()
+            .to_le_bytes(),
+    );
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum ImageInput {
+    Path { path: PathBuf },
+    Inline { name: String, data: String },
+}
+
+impl ImageInput {
+    pub fn load(&self) -> Result<ChatImage, String> {
+        match self {
+            Self::Path { path } => load_chat_image(path),
+            Self::Inline { name, data } => {
+                let bytes = BASE64
+                    .decode(data.trim())
+                    .map_err(|error| format!("Image {name} is not valid base64: {error}"))?;
+                chat_image_from_bytes(name.clone(), bytes)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlCommand {
+    ListChats,
+    NewChat,
+    OpenChat(String),
+    ReadChat(String),
+    /// `None` sends into the chat open in the window.
+    SendPrompt {
+        chat_id: Option<String>,
+        prompt: String,
+        images: Vec<ImageInput>,
+    },
+}
+
+pub enum ControlReply {
+    Json(u16, Value),
+    /// NDJSON lines, written until the sender is dropped.
+    Stream(tokio_mpsc::UnboundedReceiver<Value>),
+}
+
+impl ControlReply {
+    pub fn ok(value: Value) -> Self {
+        Self::Json(200, value)
+    }
+
+    pub fn error(status: u16, message: impl Into<String>) -> Self {
+        Self::Json(status, json!({ "error": message.into() }))
+    }
+}
+
+/// A routed request waiting for the app's reply. Messages must be `Clone`,
+/// so the reply channel is shared and taken by the first `reply`.
+#[derive(Clone)]
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    responder: Arc<Mutex<Option<oneshot::Sender<ControlReply>>>>,
+}
+
+impl ControlRequest {
+    pub fn new(command: ControlCommand) -> (Self, oneshot::Receiver<ControlReply>) {
+        let (sender, receiver) = oneshot::channel();
+        let request = Self {
+            command,
+            responder: Arc::new(Mutex::new(Some(sender))),
+        };
+        (request, receiver)
+    }
+
+    pub fn reply(&self, reply: ControlReply) {
+        if let Some(sender) = self.responder.lock().unwrap().take() {
+            let _ = sender.send(reply);
+        }
+    }
+}
+
+impl fmt::Debug for ControlRequest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ControlRequest")
+            .field("command", &self.command)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Forwards the visible part of a streaming answer to one API client.
+pub struct ResponseStream {
+    pub chat_id: String,
+    sender: tokio_mpsc::UnboundedSender<Value>,
+    sent: String,
+}
+
+impl ResponseStream {
+    pub fn new(chat_id: String) -> (Self, tokio_mpsc::UnboundedReceiver<Value>) {
+        let (sender, receiver) = tokio_mpsc::unbounded_channel();
+        let stream = Self {
+            chat_id,
+            sender,
+            sent: String::new(),
+        };
+        (stream, receiver)
+    }
+
+    /// Sends what was added since the last call and returns `false` once the
+    /// client has gone away.
+    pub fn progress(&mut self, response_text: &str) -> bool {
+        let (_, visible) = split_thinking_text(response_text);
+        let event = match visible.strip_prefix(self.sent.as_str()) {
+            Some("") => return !self.sender.is_closed(),
+            Some(delta) => json!({ "delta": delta }),
+            // A web-search turn that became a tool call was discarded.
+            None => json!({ "reset": true, "delta": visible }),
+        };
+        self.sent = visible;
+        self.sender.send(event).is_ok()
+    }
+
+    pub fn finish(mut self, response: Option<&Correspondence>, error: Option<String>) {
+        let mut done = json!({ "done": true });
+        if let Some(Correspondence::Bot { text, model, .. }) = response {
+            self.progress(text);
+            done["response"] = Value::String(self.sent.clone());
+            done["model"] = json!(model);
+        }
+        if let Some(error) = error {
+            done["error"] = Value::String(error);
+        }
+        let _ = self.sender.send(done);
+    }
+}
+
+pub fn chat_list(chats: &[SavedChat], open_chat_id: &str) -> Value {
+    chats
+        .iter()
+        .map(|chat| {
+            json!({
+                "id": chat.id,
+                "title": chat.title,
+                "updated_at": chat.updated_at,
+                "pinned": chat.pinned,
+                "open": chat.id == open_chat_id,
+            })
+        })
+        .collect()
+}
+
+pub fn message_json(message: &Correspondence) -> Value {
+    match message {
+        Correspondence::User { text, images, .. } => json!({
+            "role": "user",
+            "content": text,
+            "images": images.iter().map(|image| image.name.clone()).collect::<Vec<_>>(),
+        }),
+        Correspondence::Bot { text, model, .. } => {
+            let (thinking, content) = split_thinking_text(text);
+            json!({
+                "role": "assistant",
+                "content": content,
+                "thinking": thinking,
+                "model": model,
+            })
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct PromptBody {
+    #[serde(default)]
+    prompt: String,
+    #[serde(default)]
+    images: Vec<ImageInput>,
+}
+
+/// Maps a method and path to a command. Errors carry an HTTP status.
+pub fn route(method: &str, path: &str, body: &[u8]) -> Result<ControlCommand, (u16, String)> {
+    let path = path.split('?').next().unwrap_or_default();
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    let send_prompt = |chat_id: Option<&str>| {
+        let body = serde_json::from_slice::<PromptBody>(body)
+            .map_err(|error| (400, format!("Invalid request body: {error}")))?;
+        Ok(ControlCommand::SendPrompt {
+            chat_id: chat_id.map(str::to_string),
+            prompt: body.prompt,
+            images: body.images,
+        })
+    };
+    match (method, segments.as_slice()) {
+        ("GET", ["v1", "chats"]) => Ok(ControlCommand::ListChats),
+        ("POST", ["v1", "chats"]) => Ok(ControlCommand::NewChat),
+        ("GET", ["v1", "chats", id]) => Ok(ControlCommand::ReadChat(id.to_string())),
+        ("POST", ["v1", "chats", id, "open"]) => Ok(ControlCommand::OpenChat(id.to_string())),
+        ("POST", ["v1", "chats", id, "messages"]) => send_prompt(Some(id)),
+        ("POST", ["v1", "messages"]) => send_prompt(None),
+        _ => Err((404, format!("No endpoint for {method} {path}"))),
+    }
+}
+
+/// Compares in constant time so the token cannot be guessed byte by byte.
+fn token_matches(authorization: Option<&str>, token: &str) -> bool {
+    let Some(given) = authorization.and_then(|value| value.trim().strip_prefix("Bearer ")) else {
+        return false;
+    };
+    !token.is_empty()
+        && given.len() == token.len()
+        && given
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |difference, (left, right)| difference | (left ^ right))
+            == 0
+}
+
+/// Listens on 127.0.0.1 for as long as the subscription is active.
+pub fn serve(
+    settings: &(u16, String),
+) -> impl Stream<Item = Result<ControlRequest, String>> + use<> {
+    let (port, token) = settings.clone();
+    iced::stream::channel(16, async move |mut output| {
+        let listener = match TcpListener::bind((Ipv4Addr::LOCALHOST, port)).await {
+            Ok(listener) => listener,
+            Err(error) => {
+                let _ = output
+                    .send(Err(format!(
+                        "Could not listen on 127.0.0.1:{port}: {error}"
+                    )))
+                    .await;
+                return;
+            }
+        };
+        loop {
+            let Ok((stream, _)) = listener.accept().await else {
+                continue;
+            };
+            tokio::spawn(handle_connection(stream, token.clone(), output.clone()));
+        }
+    })
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    body: Vec<u8>,
+}
+
+/// Reads into `chunk`, failing once `deadline` has passed.
+async fn read_before(
+    stream: &mut TcpStream,
+    chunk: &mut [u8],
+    deadline: Instant,
+) -> Result<usize, (u16, String)> {
+    match timeout_at(deadline, stream.read(chunk)).await {
+        Ok(read) => read.map_err(|error| (400, error.to_string())),
+        Err(_) => Err((408, "Timed out reading the request".to_string())),
+    }
+}
+
+/// Reads one request. The bearer token is checked as soon as the headers are
+/// in, so an unauthenticated client is turned away before it can send a body.
+async fn read_request(
+    stream: &mut TcpStream,
+    token: &str,
+    timeout: Duration,
+) -> Result<HttpRequest, (u16, String)> {
+    let deadline = Instant::now() + timeout;
+    let mut buffer = Vec::new();
+    let mut chunk = [0_u8; 8192];
+    let header_end = loop {
+        if let Some(end) = buffer.windows(4).position(|bytes| bytes == b"\r\n\r\n") {
+            break end;
+        }
+        if buffer.len() > MAX_HEADER_BYTES {
+            return Err((431, "Request headers are too large".to_string()));
+        }
+        let read = read_before(stream, &mut chunk, deadline).await?;
+        if read == 0 {
+            return Err((400, "Incomplete request".to_string()));
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).to_string();
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let method = request_line.next().unwrap_or_default().to_string();
+    let path = request_line.next().unwrap_or_default().to_string();
+    let mut content_length = 0;
+    let mut authorization = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| (400, "Invalid Content-Length".to_string()))?;
+        } else if name.eq_ignore_ascii_case("authorization") {
+            authorization = Some(value.trim().to_string());
+        }
+    }
+    if !token_matches(authorization.as_deref(), token) {
+        return Err((401, "Missing or wrong bearer token".to_string()));
+    }
+    if content_length > MAX_BODY_BYTES {
+        return Err((413, "Request body is too large".to_string()));
+    }
+
+    let mut body = buffer.split_off(header_end + 4);
+    while body.len() < content_length {
+        let read = read_before(stream, &mut chunk, deadline).await?;
+        if read == 0 {
+            return Err((400, "Incomplete request body".to_string()));
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+    Ok(HttpRequest { method, path, body })
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        413 => "Payload Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Service Unavailable",
+    }
+}
+
+async fn write_json(stream: &mut TcpStream, status: u16, value: &Value) -> std::io::Result<()> {
+    let body = value.to_string();
+    let head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status_text(status),
+        body.len()
+    );
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+async fn handle_connection(
+    mut stream: TcpStream,
+    token: String,
+    mut app: mpsc::Sender<Result<ControlRequest, String>>,
+) {
+    let request = match read_request(&mut stream, &token, REQUEST_TIMEOUT).await {
+        Ok(request) => request,
+        Err((status, message)) => {
+            let _ = write_json(&mut stream, status, &json!({ "error": message })).await;
+            return;
+        }
+    };
+    let command = match route(&request.method, &request.path, &request.body) {
+        Ok(command) => command,
+        Err((status, message)) => {
+            let _ = write_json(&mut stream, status, &json!({ "error": message })).await;
+            return;
+        }
+    };
+
+    let (request, reply) = ControlRequest::new(command);
+    let reply = match app.send(Ok(request)).await {
+        Ok(()) => reply.await.ok(),
+        Err(_) => None,
+    };
+    match reply {
+        Some(ControlReply::Json(status, value)) => {
+            let _ = write_json(&mut stream, status, &value).await;
+        }
+        Some(ControlReply::Stream(mut events)) => {
+            let head = "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\nConnection: close\r\n\r\n";
+            if stream.write_all(head.as_bytes()).await.is_err() {
+                return;
+            }
+            while let Some(event) = events.recv().await {
+                let line = format!("{event}\n");
+                if stream.write_all(line.as_bytes()).await.is_err() {
+                    return;
+                }
+            }
+            let _ = stream.shutdown().await;
+        }
+        None => {
+            let error = json!({ "error": "The app closed before answering" });
+            let _ = write_json(&mut stream, 503, &error).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::futures::StreamExt;
+
+    #[test]
+    fn requests_are_routed_and_need_the_token() {
+        assert_eq!(
+            route("GET", "/v1/chats", b""),
+            Ok(ControlCommand::ListChats)
+        );
+        assert_eq!(
+            route("POST", "/v1/chats/chat-1/open", b""),
+            Ok(ControlCommand::OpenChat("chat-1".into()))
+        );
+        assert_eq!(
+            route(
+                "POST",
+                "/v1/chats/chat-1/messages",
+                br#"{"prompt": "Hi", "images": [{"path": "/tmp/a.png"}]}"#
+            ),
+            Ok(ControlCommand::SendPrompt {
+                chat_id: Some("chat-1".into()),
+                prompt: "Hi".into(),
+                images: vec![ImageInput::Path {
+                    path: "/tmp/a.png".into()
+                }],
+            })
+        );
+        assert_eq!(
+            route("POST", "/v1/messages", b"not json").unwrap_err().0,
+            400
+        );
+        assert_eq!(route("DELETE", "/v1/chats", b"").unwrap_err().0, 404);
+
+        assert!(token_matches(Some("Bearer secret"), "secret"));
+        assert!(!token_matches(Some("Bearer secreT"), "secret"));
+        assert!(!token_matches(Some("secret"), "secret"));
+        assert!(!token_matches(Some("Bearer "), ""));
+        assert!(!token_matches(None, "secret"));
+    }
+
+    #[tokio::test]
+    async fn requests_are_refused_before_the_body_and_time_out() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // Announces a large body but sends none of it.
+        let mut client = TcpStream::connect(address).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        client
+            .write_all(b"POST /v1/messages HTTP/1.1\r\nContent-Length: 50000000\r\n\r\n")
+            .await
+            .unwrap();
+        let error = read_request(&mut server, "secret", Duration::from_secs(5))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.0, 401);
+
+        // Connects and then stays silent.
+        let _idle = TcpStream::connect(address).await.unwrap();
+        let (mut server, _) = listener.accept().await.unwrap();
+        let error = read_request(&mut server, "secret", Duration::from_millis(50))
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.0, 408);
+    }
+
+    #[tokio::test]
+    async fn streamed_answers_are_written_as_ndjson() {
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (app, mut requests) = mpsc::channel(4);
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                handle_connection(stream, "secret".into(), app.clone()).await;
+            }
+        });
+        let fake_app = tokio::spawn(async move {
+            let request = requests.next().await.unwrap().unwrap();
+            assert!(matches!(
+                request.command,
+                ControlCommand::SendPrompt { chat_id: None, .. }
+            ));
+            let (mut stream, receiver) = ResponseStream::new("chat-1".into());
+            request.reply(ControlReply::Stream(receiver));
+            assert!(stream.progress("<think>plan</think>Hel"));
+            assert!(stream.progress("<think>plan</think>Hello"));
+            stream.finish(Some(&Correspondence::bot("Hello".into(), None)), None);
+        });
+
+        let send = |authorization: &'static str| async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            let body = r#"{"prompt": "Hi"}"#;
+            let request = format!(
+                "POST /v1/messages HTTP/1.1\r\nHost: localhost\r\n{authorization}Content-Length: {}\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        };
+
+        assert!(send("").await.starts_with("HTTP/1.1 401"));
+        let response = send("Authorization: Bearer secret\r\n").await;
+        fake_app.await.unwrap();
+        server.await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.contains("application/x-ndjson"));
+        let events = body
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                json!({"delta": "Hel"}),
+                json!({"delta": "lo"}),
+                json!({"done": true, "response": "Hello", "model": null}),
+            ]
+        );
+    }
+}
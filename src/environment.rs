@@ -0,0 +1,176 @@
+//! Environment variables read at startup.
+//!
+//! `OLLAMA_HOST` is read in the Ollama CLI's `host[:port]` form, so a shell
+//! or container already set up for `ollama` points the app at the same
+//! server.
+
+use std::path::PathBuf;
+
+use crate::app::HostLocation;
+
+pub const OLLAMA_HOST: &str = "OLLAMA_HOST";
+pub const DATA_DIR: &str = "OLLAMA_GUI_DATA_DIR";
+/// The profile `OLLAMA_HOST` fills in, refreshed at every start.
+pub const PROFILE_NAME: &str = "OLLAMA_HOST";
+
+const DEFAULT_PORT: &str = "11434";
+
+/// Values taken from the environment, kept to show them in Settings.
+#[derive(Clone, Debug, Default)]
+pub struct EnvironmentOverrides {
+    pub ollama_host: Option<String>,
+    /// Why `ollama_host` was ignored, if it was.
+    pub ollama_host_error: Option<&'static str>,
+    pub data_dir: Option<PathBuf>,
+}
+
+impl EnvironmentOverrides {
+    pub fn from_env() -> Self {
+        Self {
+            ollama_host: non_empty_var(OLLAMA_HOST),
+            ollama_host_error: None,
+            data_dir: data_dir(),
+        }
+    }
+}
+
+fn non_empty_var(name: &str) -> Option<String> {
+    std::env::var(name)
+        .ok()
+        .map(|value| value.trim().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// The app data directory chosen through `OLLAMA_GUI_DATA_DIR`, if any.
+pub fn data_dir() -> Option<PathBuf> {
+    non_empty_var(DATA_DIR).map(PathBuf::from)
+}
+
+/// Parses `OLLAMA_HOST`: the scheme and port are optional, and an explicit
+/// `http` or `https` scheme defaults the port to 80 or 443. Server profiles
+/// hold no path, so a value with one, such as a reverse-proxy prefix the CLI
+/// would keep, is rejected rather than silently pointed at the root. Bind-all
+/// addresses such as `0.0.0.0` are only meaningful to the server, so the app
+/// connects to loopback instead. Errors are short reasons shown in Settings.
+pub fn parse_ollama_host(value: &str) -> Result<HostLocation, &'static str> {
+    let value = value.trim();
+    let (scheme, rest, default_port) = match value.split_once("://") {
+        None => ("http", value, DEFAULT_PORT),
+        Some(("http", rest)) => ("http", rest, "80"),
+        Some(("https", rest)) => ("https", rest, "443"),
+        Some(_) => return Err("unsupported scheme"),
+    };
+    let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    if !matches!(path, "" | "/") {
+        return Err("paths are not supported");
+    }
+
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        let (host, after) = bracketed.split_once(']').ok_or("invalid address")?;
+        let port = match after.strip_prefix(':') {
+            Some(port) => port,
+            None if after.is_empty() => default_port,
+            None => return Err("invalid address"),
+        };
+        (host, port)
+    } else {
+        match authority.rsplit_once(':') {
+            // More than one colon without brackets is a bare IPv6 address.
+            Some((host, port)) if !host.contains(':') => (host, port),
+            _ => (authority, default_port),
+        }
+    };
+    port.parse::<u16>().map_err(|_| "invalid port")?;
+
+    let host = match host {
+        "" | "0.0.0.0" => "127.0.0.1".to_string(),
+        "::" => "[::1]".to_string(),
+        host if host.contains(':') => format!("[{host}]"),
+        host => host.to_string(),
+    };
+    Ok(HostLocation {
+        name: PROFILE_NAME.to_string(),
+        scheme: scheme.to_string(),
+        ip: host,
+        port: port.to_string(),
+        ..HostLocation::default()
+    })
+}
+
+/// Puts the `OLLAMA_HOST` server into `profiles` and returns its index. An
+/// existing environment profile keeps its credentials and model list.
+pub fn apply_ollama_host(profiles: &mut Vec<HostLocation>, host: HostLocation) -> usize {
+    match profiles
+        .iter()
+        .position(|profile| profile.name == PROFILE_NAME)
+    {
+        Some(index) => {
+            let profile = &mut profiles[index];
+            profile.scheme = host.scheme;
+            profile.ip = host.ip;
+            profile.port = host.port;
+            index
+        }
+        None => {
+            profiles.push(host);
+            profiles.len() - 1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn base_url(value: &str) -> Option<String> {
+        parse_ollama_host(value).ok().map(|host| host.base_url())
+    }
+
+    #[test]
+    fn ollama_host_is_parsed_without_a_path() {
+        assert_eq!(base_url("gpu-box").as_deref(), Some("http://gpu-box:11434"));
+        assert_eq!(
+            base_url("0.0.0.0:8080").as_deref(),
+            Some("http://127.0.0.1:8080")
+        );
+        assert_eq!(
+            base_url("https://ollama.example.com/").as_deref(),
+            Some("https://ollama.example.com:443")
+        );
+        assert_eq!(
+            parse_ollama_host("https://example.com/ollama").err(),
+            Some("paths are not supported")
+        );
+        assert_eq!(
+            base_url("http://10.0.0.5").as_deref(),
+            Some("http://10.0.0.5:80")
+        );
+        assert_eq!(
+            base_url("[::1]:11500").as_deref(),
+            Some("http://[::1]:11500")
+        );
+        assert_eq!(
+            base_url("fe80::1").as_deref(),
+            Some("http://[fe80::1]:11434")
+        );
+        assert_eq!(
+            parse_ollama_host("ftp://host").err(),
+            Some("unsupported scheme")
+        );
+        assert_eq!(parse_ollama_host("host:port").err(), Some("invalid port"));
+    }
+
+    #[test]
+    fn environment_profile_is_reused_across_starts() {
+        let mut profiles = vec![HostLocation::default()];
+        let index = apply_ollama_host(&mut profiles, parse_ollama_host("gpu-box").unwrap());
+        assert_eq!(index, 1);
+        profiles[1].api_key = Some("token".to_string());
+
+        let index = apply_ollama_host(&mut profiles, parse_ollama_host("gpu-box:9000").unwrap());
+        assert_eq!(index, 1);
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[1].port, "9000");
+        assert_eq!(profiles[1].api_key.as_deref(), Some("token"));
+    }
+}
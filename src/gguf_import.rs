@@ -0,0 +1,268 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::{app::HostLocation, model_pull::PullOutcome, modelfile};
+
+/// What the import is doing with the file right now.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImportStage {
+    #[default]
+    Hashing,
+    Uploading,
+    Creating,
+}
+
+/// Progress of a GGUF import, shared with the view while it runs. `done`
+/// and `total` are bytes of the current stage.
+#[derive(Clone, Debug, Default)]
+pub struct ImportProgress {
+    pub stage: ImportStage,
+    pub done: u64,
+    pub total: u64,
+}
+
+impl ImportProgress {
+    pub fn fraction(&self) -> f32 {
+        match self.total {
+            0 => 0.0,
+            total => self.done as f32 / total as f32,
+        }
+    }
+}
+
+/// The model a GGUF file becomes once it is on the server.
+#[derive(Clone, Debug, Default)]
+pub struct GgufImport {
+    pub path: PathBuf,
+    pub model: String,
+    pub template: String,
+    pub system: String,
+}
+
+impl GgufImport {
+    /// The `/api/create` body for a blob already uploaded under `digest`.
+    /// Blank template and system prompt leave what the file itself carries.
+    pub fn create_request(&self, digest: &str) -> Value {
+        let file_name = self
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| "model.gguf".to_string());
+        let mut request = serde_json::json!({
+            "model": self.model,
+            "files": { file_name: digest },
+            "stream": true,
+        });
+        if !self.template.trim().is_empty() {
+            request["template"] = Value::String(self.template.clone());
+        }
+        if !self.system.trim().is_empty() {
+            request["system"] = Value::String(self.system.clone());
+        }
+        request
+    }
+}
+
+/// Suggests a model name from the file name, e.g. `Mistral-7B.Q4_K_M.gguf`
+/// becomes `mistral-7b.q4_k_m`.
+pub fn model_name_for(path: &Path) -> String {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy().to_lowercase().replace(' ', "-"))
+        .unwrap_or_default()
+}
+
+/// Counts bytes as they are read and fails once `cancel` is set, which
+/// aborts both hashing and an upload in flight.
+struct ProgressReader<R> {
+    inner: R,
+    progress: Arc<Mutex<ImportProgress>>,
+    cancel: Arc<AtomicBool>,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        if self.cancel.load(Ordering::Relaxed) {
+            // Not `Interrupted`, which readers are expected to retry.
+            return Err(io::Error::other("import cancelled"));
+        }
+        let read = self.inner.read(buffer)?;
+        self.progress.lock().unwrap().done += read as u64;
+        Ok(read)
+    }
+}
+
+fn open_stage(
+    path: &Path,
+    stage: ImportStage,
+    progress: &Arc<Mutex<ImportProgress>>,
+    cancel: &Arc<AtomicBool>,
+) -> io::Result<(ProgressReader<File>, u64)> {
+    let file = File::open(path)?;
+    let total = file.metadata()?.len();
+    *progress.lock().unwrap() = ImportProgress {
+        stage,
+        done: 0,
+        total,
+    };
+    Ok((
+        ProgressReader {
+            inner: file,
+            progress: progress.clone(),
+            cancel: cancel.clone(),
+        },
+        total,
+    ))
+}
+
+/// Returns `sha256:<hex>`, the digest Ollama names blobs by.
+fn file_digest(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0; 1024 * 1024];
+    loop {
+        match reader.read(&mut buffer)? {
+            0 => break,
+            read => hasher.update(&buffer[..read]),
+        }
+    }
+    let hex = hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>();
+    Ok(format!("sha256:{hex}"))
+}
+
+/// Hashes and uploads the file unless the server already holds the blob.
+fn upload_blob(
+    host: &HostLocation,
+    path: &Path,
+    progress: &Arc<Mutex<ImportProgress>>,
+    cancel: &Arc<AtomicBool>,
+) -> Result<String, String> {
+    let read_error = |error: io::Error| format!("Could not read {}: {error}", path.display());
+    let (mut reader, _) =
+        open_stage(path, ImportStage::Hashing, progress, cancel).map_err(read_error)?;
+    let digest = file_digest(&mut reader).map_err(read_error)?;
+
+    let client = host.blocking_client()?;
+    let blob_url = format!("{}/api/blobs/{digest}", host.base_url());
+    let existing = client
+        .head(&blob_url)
+        .send()
+        .map_err(|error| format!("Could not reach Ollama: {error}"))?;
+    if existing.status().is_success() {
+        return Ok(digest);
+    }
+
+    let (reader, total) =
+        open_stage(path, ImportStage::Uploading, progress, cancel).map_err(read_error)?;
+    let response = client
+        .post(&blob_url)
+        .body(reqwest::blocking::Body::sized(reader, total))
+        .send()
+        .map_err(|error| format!("Upload failed: {error}"))?;
+    if response.status().is_success() {
+        Ok(digest)
+    } else {
+        let status = response.status();
+        let detail = response.text().unwrap_or_default();
+        Err(format!(
+            "Ollama rejected the upload ({status}): {}",
+            detail.trim()
+        ))
+    }
+}
+
+/// Uploads `import.path` as a blob and creates `import.model` from it,
+/// appending Ollama's create statuses to `log`.
+pub async fn import_gguf(
+    host: HostLocation,
+    import: GgufImport,
+    progress: Arc<Mutex<ImportProgress>>,
+    log: Arc<Mutex<Vec<String>>>,
+    cancel: Arc<AtomicBool>,
+) -> Result<PullOutcome, String> {
+    let upload = {
+        let (host, path, progress, cancel) = (
+            host.clone(),
+            import.path.clone(),
+            progress.clone(),
+            cancel.clone(),
+        );
+        tokio::task::spawn_blocking(move || upload_blob(&host, &path, &progress, &cancel))
+            .await
+            .map_err(|error| error.to_string())?
+    };
+    if cancel.load(Ordering::Relaxed) {
+        return Ok(PullOutcome::Cancelled);
+    }
+    let digest = upload?;
+
+    *progress.lock().unwrap() = ImportProgress {
+        stage: ImportStage::Creating,
+        ..ImportProgress::default()
+    };
+    modelfile::create_model(host, import.create_request(&digest), log).await?;
+    Ok(PullOutcome::Completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reading_reports_progress_and_digest() {
+        let progress = Arc::new(Mutex::new(ImportProgress {
+            total: 3,
+            ..ImportProgress::default()
+        }));
+        let mut reader = ProgressReader {
+            inner: &b"abc"[..],
+            progress: progress.clone(),
+            cancel: Arc::new(AtomicBool::new(false)),
+        };
+        assert_eq!(
+            file_digest(&mut reader).unwrap(),
+            "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(progress.lock().unwrap().fraction(), 1.0);
+
+        let mut cancelled = ProgressReader {
+            inner: &b"abc"[..],
+            progress,
+            cancel: Arc::new(AtomicBool::new(true)),
+        };
+        assert!(file_digest(&mut cancelled).is_err());
+    }
+
+    #[test]
+    fn create_request_names_the_uploaded_file() {
+        let path = PathBuf::from("/models/My Tune.Q4_K_M.gguf");
+        let import = GgufImport {
+            model: model_name_for(&path),
+            path,
+            template: String::new(),
+            system: "You answer briefly.".to_string(),
+        };
+        assert_eq!(import.model, "my-tune.q4_k_m");
+        assert_eq!(
+            import.create_request("sha256:abc"),
+            serde_json::json!({
+                "model": "my-tune.q4_k_m",
+                "files": {"My Tune.Q4_K_M.gguf": "sha256:abc"},
+                "stream": true,
+                "system": "You answer briefly.",
+            })
+        );
+    }
+}
@@ -14,8 +14,20 @@ use std::{
 };
 
 use crate::{
-    ChatImage, Correspondence, GUIState, Language, MarkdownImageState, Message, Program,
-    ThinkingLevel, split_thinking_text,
+    ChatImage, CompareColumn, Correspondence, GUIState, GenerationStats, Language,
+    MarkdownImageState, Message, Program, ThinkingLevel,
+    app::HostLocation,
+    backend::BackendKind,
+    chat_export::ExportScope,
+    environment::{self, EnvironmentOverrides},
+    gguf_import::ImportStage,
+    model_manager::{self, ModelDetails, ModelText},
+    model_memory::{LoadedModel, format_bytes, parse_keep_alive},
+    model_pull,
+    sampling::{SamplingOption, SamplingSettings},
+    server_profiles::{self, CertificateKind},
+    split_thinking_text,
+    structured_output::{FormatCheck, ResponseFormat},
     web_search::{WebSearchState, WebSource},
 };
 
@@ -35,8 +47,8 @@ fn tr(language: Language, english: &'static str) -> &'static str {
             "Selecciona uno de tus modelos de Ollama instalados, escribe un mensaje y pulsa Intro para generar una respuesta."
         }
         "Manage models" => "Gestionar modelos",
-        "Use Advanced Settings to install models by name, change the Ollama address, or tune response rendering." => {
-            "Usa la configuración avanzada para instalar modelos por nombre, cambiar la dirección de Ollama o ajustar la presentación de respuestas."
+        "Use Advanced Settings to install, inspect, copy, and delete models, change the Ollama address, or tune response rendering." => {
+            "Usa la configuración avanzada para instalar, consultar, copiar y eliminar modelos, cambiar la dirección de Ollama o ajustar la presentación de respuestas."
         }
         "System prompts" => "Indicaciones del sistema",
         "System prompts let you switch the assistant's behaviour or personality without rewriting your prompt each time." => {
@@ -165,6 +177,16 @@ fn tr(language: Language, english: &'static str) -> &'static str {
         "You" => "Tú",
         "▾ Hide thinking" => "▾ Ocultar razonamiento",
         "▸ Show thinking" => "▸ Mostrar razonamiento",
+        "Regenerate" => "Regenerar",
+        "prompt tokens" => "tokens de entrada",
+        "output tokens" => "tokens de salida",
+        "load" => "de carga",
+        "Edit" => "Editar",
+        "Cancel" => "Cancelar",
+        "Messages above are sent to the model as a summary" => {
+            "Los mensajes anteriores se envían al modelo como resumen"
+        }
+        "Send edit" => "Enviar edición",
         "Describe an image, or ask a question about the attached image…" => {
             "Describe una imagen o pregunta sobre la imagen adjunta…"
         }
@@ -253,6 +275,22 @@ fn tr(language: Language, english: &'static str) -> &'static str {
             "Los chats guardados usan esta carpeta. Se muestra la ruta completa para que puedas encontrarlos."
         }
         "Choose folder" => "Elegir carpeta",
+        "Export" => "Exportar",
+        "Export and import" => "Exportar e importar",
+        "Save the open chat or every saved chat as Markdown, HTML or JSON; the format follows the file type chosen. Import reads ChatGPT, Open WebUI and Ollama GUI JSON exports." => {
+            "Guarda el chat abierto o todos los chats guardados como Markdown, HTML o JSON; el formato sigue el tipo de archivo elegido. Importar lee exportaciones JSON de ChatGPT, Open WebUI y Ollama GUI."
+        }
+        "Import chats" => "Importar chats",
+        "Search chats" => "Buscar chats",
+        "No matching chats" => "Ningún chat coincide",
+        "Indexing chats…" => "Indexando chats…",
+        "messages" => "mensajes",
+        "chats selected" => "chats seleccionados",
+        "Select all" => "Seleccionar todo",
+        "Select none" => "No seleccionar nada",
+        "Import selected" => "Importar selección",
+        "Export open chat" => "Exportar chat abierto",
+        "Export all chats" => "Exportar todos los chats",
         "Model conversation context" => "Contexto de conversación del modelo",
         "Include earlier messages from this chat in the next model request. Saved chats are managed in the left menu." => {
             "Incluye mensajes anteriores de este chat en la próxima solicitud. Los chats guardados se gestionan en el menú izquierdo."
@@ -275,10 +313,123 @@ fn tr(language: Language, english: &'static str) -> &'static str {
         "Back to settings" => "Volver a configuración",
         "Change the active prompt profile." => "Cambia el perfil de indicaciones activo.",
         "Install model" => "Instalar modelo",
-        "Enter an Ollama model name and press Enter." => {
-            "Escribe el nombre de un modelo de Ollama y pulsa Intro."
+        "Enter an Ollama model name and press Enter. Further models wait in a queue." => {
+            "Escribe el nombre de un modelo de Ollama y pulsa Intro. Los siguientes modelos esperan en cola."
         }
         "Batch tokens" => "Lote de tokens",
+        "Sampling" => "Muestreo",
+        "Fine-tune how tokens are chosen. Leave a field empty to use the model's own default." => {
+            "Ajusta cómo se eligen los tokens. Deja un campo vacío para usar el valor predeterminado del modelo."
+        }
+        "Reset to model defaults" => "Restablecer valores del modelo",
+        "Models" => "Modelos",
+        "Inspect, copy, rename and delete installed models." => {
+            "Consulta, copia, renombra y elimina los modelos instalados."
+        }
+        "Back to advanced settings" => "Volver a ajustes avanzados",
+        "New from this model" => "Nuevo a partir de este modelo",
+        "New Modelfile" => "Nuevo Modelfile",
+        "Modelfile editor" => "Editor de Modelfile",
+        "Derive a model with its own SYSTEM, PARAMETER and TEMPLATE." => {
+            "Deriva un modelo con sus propios SYSTEM, PARAMETER y TEMPLATE."
+        }
+        "Back to models" => "Volver a los modelos",
+        "New model name, e.g. reviewer:latest" => "Nombre del nuevo modelo, p. ej. reviewer:latest",
+        "Create model" => "Crear modelo",
+        "Creating..." => "Creando...",
+        "Validation" => "Validación",
+        "No problems found." => "No se encontraron problemas.",
+        "Line" => "Línea",
+        "Progress" => "Progreso",
+        "Waiting for Ollama..." => "Esperando a Ollama...",
+        "Nothing submitted yet." => "Todavía no se ha enviado nada.",
+        "Import GGUF" => "Importar GGUF",
+        "Import GGUF file" => "Importar archivo GGUF",
+        "Close" => "Cerrar",
+        "Computing SHA-256..." => "Calculando SHA-256...",
+        "Uploading to the server..." => "Subiendo al servidor...",
+        "Creating the model..." => "Creando el modelo...",
+        "No file chosen" => "Ningún archivo elegido",
+        "The file is uploaded to the connected server, so this works with remote hosts too." => {
+            "El archivo se sube al servidor conectado, así que también funciona con hosts remotos."
+        }
+        "Choose file" => "Elegir archivo",
+        "Model name" => "Nombre del modelo",
+        "System prompt (optional)" => "Prompt del sistema (opcional)",
+        "You are a helpful assistant." => "Eres un asistente útil.",
+        "Template (optional)" => "Plantilla (opcional)",
+        "Import" => "Importar",
+        "Compare" => "Comparar",
+        "Compare models" => "Comparar modelos",
+        "Send one prompt to two to four models and read the answers side by side." => {
+            "Envía un prompt a entre dos y cuatro modelos y lee las respuestas lado a lado."
+        }
+        "Ask every selected model the same question" => {
+            "Haz la misma pregunta a todos los modelos seleccionados"
+        }
+        "Answers appear here side by side, each with its own statistics." => {
+            "Las respuestas aparecen aquí lado a lado, cada una con sus estadísticas."
+        }
+        "Continue with this one" => "Continuar con esta",
+        "Could not list installed models:" => "No se pudieron listar los modelos instalados:",
+        "Select a model to see its details." => "Selecciona un modelo para ver sus detalles.",
+        "Loading model details..." => "Cargando detalles del modelo...",
+        "Could not read model details:" => "No se pudieron leer los detalles del modelo:",
+        "Confirm delete" => "Confirmar eliminación",
+        "Size" => "Tamaño",
+        "Modified" => "Modificado",
+        "Family" => "Familia",
+        "Parameters" => "Parámetros",
+        "Quantisation" => "Cuantización",
+        "Context length" => "Longitud de contexto",
+        "License" => "Licencia",
+        "Template" => "Plantilla",
+        "Copy or rename" => "Copiar o renombrar",
+        "New name, e.g. my-model:latest" => "Nuevo nombre, p. ej. mi-modelo:latest",
+        "Rename" => "Renombrar",
+        "left" => "restante",
+        "Queued:" => "En cola:",
+        "Copy" => "Copiar",
+        "Delete" => "Eliminar",
+        "Loaded models" => "Modelos cargados",
+        "Models Ollama currently holds in memory, and how long it keeps them after a request." => {
+            "Modelos que Ollama mantiene en memoria y cuánto tiempo los conserva tras una solicitud."
+        }
+        "Refresh" => "Actualizar",
+        "Preload selected model" => "Precargar modelo seleccionado",
+        "Could not list loaded models:" => "No se pudieron listar los modelos cargados:",
+        "No models are loaded." => "No hay modelos cargados.",
+        "Unload" => "Descargar",
+        "Keep alive" => "Mantener cargado",
+        "Ollama default, e.g. 10m or -1" => "Valor de Ollama, p. ej. 10m o -1",
+        "Keep alive for this chat" => "Mantener cargado en este chat",
+        "Use the global setting" => "Usar el ajuste global",
+        "{ } Structured" => "{ } Estructurada",
+        "{ } Structured on" => "{ } Estructurada activa",
+        "Structured output" => "Salida estructurada",
+        "Paste a JSON Schema, or json for any JSON value. Answers in this chat are checked against it." => {
+            "Pega un JSON Schema, o json para cualquier valor JSON. Las respuestas de este chat se validan con él."
+        }
+        "Load from file" => "Cargar desde archivo",
+        "Any JSON" => "Cualquier JSON",
+        "Any JSON value" => "Cualquier valor JSON",
+        "Schema ready" => "Esquema listo",
+        "Done" => "Listo",
+        "Clear" => "Borrar",
+        "Off" => "Desactivada",
+        "Copy JSON" => "Copiar JSON",
+        "Response does not match the requested format" => {
+            "La respuesta no coincide con el formato solicitado"
+        }
+        "more problems" => "problemas más",
+        "Model default" => "Predeterminado del modelo",
+        "Comma-separated, e.g. </s>, User:" => "Separadas por comas, p. ej. </s>, User:",
+        "Repeat penalty" => "Penalización por repetición",
+        "Repeat window" => "Ventana de repetición",
+        "Presence penalty" => "Penalización por presencia",
+        "Frequency penalty" => "Penalización por frecuencia",
+        "Seed" => "Semilla",
+        "Stop sequences" => "Secuencias de parada",
         "Tokens per visual update when fast streaming is off. Higher values reduce rendering work." => {
             "Tokens por actualización visual cuando la transmisión rápida está desactivada. Los valores altos reducen el trabajo de presentación."
         }
@@ -291,9 +442,44 @@ fn tr(language: Language, english: &'static str) -> &'static str {
             "Censura palabras ofensivas, malsonantes, sexuales y gravemente inapropiadas con caracteres #."
         }
         "Ollama address" => "Dirección de Ollama",
-        "Change the IP address and port used to connect to Ollama." => {
-            "Cambia la dirección IP y el puerto usados para conectar con Ollama."
+        "Save the Ollama servers you use and pick which one the app talks to." => {
+            "Guarda los servidores de Ollama que usas y elige con cuál habla la aplicación."
         }
+        "Add server" => "Añadir servidor",
+        "Delete server" => "Eliminar servidor",
+        "Profile name" => "Nombre del perfil",
+        "Server type" => "Tipo de servidor",
+        "Chat only. Models are managed on the server itself." => {
+            "Solo chat. Los modelos se gestionan en el propio servidor."
+        }
+        "Use HTTPS" => "Usar HTTPS",
+        "API key (optional)" => "Clave de API (opcional)",
+        "Server" => "Servidor",
+        "CA certificate" => "Certificado de CA",
+        "Client certificate" => "Certificado de cliente",
+        "Header name" => "Nombre del encabezado",
+        "Value" => "Valor",
+        "Add header" => "Añadir encabezado",
+        "None" => "Ninguno",
+        "Environment" => "Entorno",
+        "Control API" => "API de control",
+        "Lets editors and scripts open chats, send prompts and read answers over HTTP on 127.0.0.1. Every request needs the token below." => {
+            "Permite que editores y scripts abran chats, envíen mensajes y lean respuestas por HTTP en 127.0.0.1. Cada petición necesita el token de abajo."
+        }
+        "Copy token" => "Copiar token",
+        "New token" => "Nuevo token",
+        "Port" => "Puerto",
+        "Values read from environment variables when the app started. They take precedence over saved settings." => {
+            "Valores leídos de variables de entorno al iniciar la aplicación. Tienen prioridad sobre los ajustes guardados."
+        }
+        "Not set" => "Sin definir",
+        "used for the OLLAMA_HOST server profile" => "usado para el perfil de servidor OLLAMA_HOST",
+        "used for settings, history and chats" => "usado para ajustes, historial y chats",
+        "ignored" => "ignorado",
+        "unsupported scheme" => "esquema no admitido",
+        "paths are not supported" => "no se admiten rutas",
+        "invalid address" => "dirección no válida",
+        "invalid port" => "puerto no válido",
         "Fastest · no extra reasoning" => "Más rápido · sin razonamiento adicional",
         "Use this model's standard reasoning mode" => {
             "Usa el modo de razonamiento estándar de este modelo"
@@ -356,6 +542,9 @@ fn rgb(r: u8, g: u8, b: u8) -> Color {
     Color::from_rgb(r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0)
 }
 
+/// The scrollable holding the open conversation, for jumping to a message.
+pub const CONVERSATION_SCROLL_ID: &str = "conversation";
+
 static DARK_MODE: AtomicBool = AtomicBool::new(true);
 
 pub(crate) fn set_dark_mode(enabled: bool) {
@@ -654,6 +843,23 @@ fn flat_card_style(_theme: &Theme) -> Style {
     }
 }
 
+/// Outline around the message a search result jumped to.
+fn search_highlight_style(_theme: &Theme) -> Style {
+    Style {
+        snap: true,
+        background: Some(Background::Color(Color {
+            a: 0.08,
+            ..accent_2()
+        })),
+        border: Border {
+            color: accent_2(),
+            width: 1.5,
+            radius: Radius::from(14.0),
+        },
+        ..Style::default()
+    }
+}
+
 fn chat_entry_style(active: bool) -> impl Fn(&Theme) -> Style {
     move |_theme| Style {
         snap: true,
@@ -1343,8 +1549,13 @@ fn composer_image_previews<'a>(images: &[ChatImage]) -> Element<'a, Message> {
         .into()
 }
 
-fn copy_code_button<'a>(code: String, copied: bool, language: Language) -> Element<'a, Message> {
-    let label = tr(language, if copied { "Copied ✓" } else { "Copy code" });
+fn copy_button<'a>(
+    text: String,
+    label: &'static str,
+    copied: bool,
+    language: Language,
+) -> Element<'a, Message> {
+    let label = tr(language, if copied { "Copied ✓" } else { label });
 
     widget::button(widget::text(label).size(12).align_x(Horizontal::Center))
         .padding(8)
@@ -1355,7 +1566,7 @@ fn copy_code_button<'a>(code: String, copied: bool, language: Language) -> Eleme
                 button_visual(panel_soft(), border_soft(), text_muted(), status)
             }
         })
-        .on_press(Message::CopyPressed(code))
+        .on_press(Message::CopyPressed(text))
         .into()
 }
 
@@ -1444,144 +1655,1309 @@ fn settings_group_title<'a>(title: &'a str) -> Element<'a, Message> {
     .into()
 }
 
-fn help_card<'a>(title: &'a str, body: &'a str, color: Color) -> Element<'a, Message> {
-    container(widget::column![
-        container(widget::text(title).size(16).color(text_main()))
-            .padding(8)
-            .style(chip_style(color)),
-        Space::new().height(Length::Fixed(10.0)),
-        widget::text(body).size(14).color(text_muted()),
-    ])
-    .padding(16)
-    .width(Length::Fill)
-    .style(flat_card_style)
+/// Marks where the model starts seeing messages verbatim; everything above
+/// is sent as a summary.
+fn summary_divider<'a>(language: Language) -> Element<'a, Message> {
+    let rule = || {
+        widget::rule::horizontal(1).style(|_theme| widget::rule::Style {
+            color: border_soft(),
+            radius: Radius::from(1.0),
+            fill_mode: widget::rule::FillMode::Full,
+            snap: true,
+        })
+    };
+    widget::row![
+        rule(),
+        Space::new().width(Length::Fixed(10.0)),
+        widget::text(tr(
+            language,
+            "Messages above are sent to the model as a summary"
+        ))
+        .size(11)
+        .color(text_muted()),
+        Space::new().width(Length::Fixed(10.0)),
+        rule(),
+    ]
+    .align_y(iced::Alignment::Center)
     .into()
 }
 
-fn website_host(url: &str) -> String {
-    url::Url::parse(url)
-        .ok()
-        .and_then(|url| url.host_str().map(str::to_string))
-        .map(|host| host.strip_prefix("www.").unwrap_or(&host).to_string())
-        .unwrap_or_else(|| url.to_string())
+/// One line of speed and token counts, e.g. for comparing quantisations.
+fn generation_stats_summary(stats: &GenerationStats, language: Language) -> String {
+    let mut parts = Vec::new();
+    if let Some(rate) = stats.tokens_per_second() {
+        parts.push(format!("{rate:.1} tok/s"));
+    }
+    parts.push(format!(
+        "{} {}",
+        stats.prompt_eval_count,
+        tr(language, "prompt tokens")
+    ));
+    parts.push(format!(
+        "{} {}",
+        stats.eval_count,
+        tr(language, "output tokens")
+    ));
+    parts.push(format!(
+        "{:.2} s {}",
+        stats.load_seconds(),
+        tr(language, "load")
+    ));
+    parts.join(" · ")
 }
 
-fn ellipsize_chat_title(title: &str, max_chars: usize) -> String {
-    if max_chars == 0 {
-        return String::new();
+/// Renders a validated structured response as a foldable tree. `path` is the
+/// JSON path of `value` and keys whether the node is folded.
+fn json_tree<'a>(
+    index: usize,
+    label: Option<String>,
+    value: &serde_json::Value,
+    path: String,
+    collapsed: &std::collections::HashSet<(usize, String)>,
+) -> Element<'a, Message> {
+    use serde_json::Value;
+
+    let label = label.map(|label| format!("{label}: ")).unwrap_or_default();
+    let children: Vec<(String, String, &Value)> = match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(key, child)| (key.clone(), format!("{path}.{key}"), child))
+            .collect(),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .map(|(item, child)| (item.to_string(), format!("{path}[{item}]"), child))
+            .collect(),
+        scalar => {
+            let color = match scalar {
+                Value::String(_) => success(),
+                Value::Number(_) => accent_2(),
+                _ => warning(),
+            };
+            return widget::row![
+                widget::text(label)
+                    .font(iced::Font::MONOSPACE)
+                    .size(13)
+                    .color(text_muted()),
+                widget::text(scalar.to_string())
+                    .font(iced::Font::MONOSPACE)
+                    .size(13)
+                    .color(color),
+            ]
+            .padding([2, 4])
+            .into();
+        }
+    };
+
+    let (open, close) = if value.is_object() {
+        ("{", "}")
+    } else {
+        ("[", "]")
+    };
+    let folded = collapsed.contains(&(index, path.clone()));
+    let header = if folded {
+        format!("▸ {label}{open} {} {close}", children.len())
+    } else {
+        format!("▾ {label}{open}")
+    };
+    let toggle = widget::button(
+        widget::text(header)
+            .font(iced::Font::MONOSPACE)
+            .size(13)
+            .color(text_main()),
+    )
+    .padding([2, 4])
+    .style(chat_title_button_style)
+    .on_press(Message::ToggleJsonNode(index, path));
+    if folded {
+        return toggle.into();
     }
 
-    let single_line = title.split_whitespace().collect::<Vec<_>>().join(" ");
-    let mut chars = single_line.chars();
-    let mut shortened = chars.by_ref().take(max_chars).collect::<String>();
+    let nested = children
+        .into_iter()
+        .map(|(key, child_path, child)| json_tree(index, Some(key), child, child_path, collapsed))
+        .collect::<Vec<_>>();
+    widget::column![
+        toggle,
+        widget::row![
+            Space::new().width(Length::Fixed(16.0)),
+            widget::Column::with_children(nested),
+        ],
+        widget::text(close)
+            .font(iced::Font::MONOSPACE)
+            .size(13)
+            .color(text_main()),
+    ]
+    .into()
+}
 
-    if chars.next().is_some() {
-        shortened.pop();
-        shortened.push('…');
+/// Lists why a structured response failed its check, keeping long lists short.
+fn format_check_notice<'a>(errors: &[String], language: Language) -> Element<'a, Message> {
+    const SHOWN_ERRORS: usize = 5;
+    let mut details = errors
+        .iter()
+        .take(SHOWN_ERRORS)
+        .map(|error| format!("• {error}"))
+        .collect::<Vec<_>>();
+    if errors.len() > SHOWN_ERRORS {
+        details.push(format!(
+            "• {} {}",
+            errors.len() - SHOWN_ERRORS,
+            tr(language, "more problems")
+        ));
     }
+    widget::column![
+        container(widget::column![
+            widget::text(tr(language, "Response does not match the requested format"))
+                .size(12)
+                .color(danger()),
+            Space::new().height(Length::Fixed(4.0)),
+            widget::text(details.join("\n"))
+                .size(11)
+                .color(text_muted()),
+        ])
+        .padding([8, 10])
+        .width(Length::Fill)
+        .style(chip_style(danger())),
+        Space::new().height(Length::Fixed(8.0)),
+    ]
+    .into()
+}
 
-    shortened
+/// A labelled keep-alive field, outlined while its text is not a duration.
+fn keep_alive_field<'a>(
+    label: &'a str,
+    placeholder: &'a str,
+    value: &'a str,
+    on_input: fn(String) -> Message,
+) -> Element<'a, Message> {
+    let invalid = parse_keep_alive(value).is_err();
+    widget::column![
+        widget::text(label).size(12).color(text_muted()),
+        Space::new().height(Length::Fixed(5.0)),
+        iced::widget::TextInput::<Message>::new(placeholder, value)
+            .on_input(on_input)
+            .padding(9)
+            .width(Length::Fill)
+            .style(move |theme, status| {
+                let mut style = text_input_style(theme, status);
+                if invalid {
+                    style.border.color = danger();
+                }
+                style
+            }),
+    ]
+    .width(Length::Fill)
+    .into()
 }
 
-fn website_result_row<'a>(
-    index: usize,
-    source: &WebSource,
-    active: bool,
+/// One row per model Ollama has in memory, each with an unload button.
+fn running_model_rows<'a>(
+    models: &'a [LoadedModel],
+    error: Option<&'a String>,
     language: Language,
 ) -> Element<'a, Message> {
-    let host = website_host(&source.url);
-    let title = ellipsize_chat_title(&source.title, 64);
-    let host = ellipsize_chat_title(&host, 64);
-    let trailing = if active {
-        tr(language, "READING").to_string()
-    } else {
-        "↗".to_string()
-    };
-    let trailing_color = if active { accent_2() } else { text_faint() };
+    if let Some(error) = error {
+        return widget::text(format!(
+            "{} {error}",
+            tr(language, "Could not list loaded models:")
+        ))
+        .size(12)
+        .color(danger())
+        .into();
+    }
+    if models.is_empty() {
+        return widget::text(tr(language, "No models are loaded."))
+            .size(12)
+            .color(text_faint())
+            .into();
+    }
+    let now = chrono::Utc::now();
+    let rows = models.iter().map(|model| {
+        container(
+            widget::row![
+                widget::column![
+                    widget::text(&model.name).size(14).color(text_main()),
+                    Space::new().height(Length::Fixed(3.0)),
+                    widget::text(format!(
+                        "{} · {}",
+                        model.memory_summary(),
+                        model.expiry_summary(now)
+                    ))
+                    .size(11)
+                    .color(text_muted()),
+                ]
+                .width(Length::Fill),
+                mini_button(
+                    tr(language, "Unload"),
+                    Message::UnloadModel(model.name.clone())
+                ),
+            ]
+            .align_y(iced::Alignment::Center),
+        )
+        .padding([8, 10])
+        .width(Length::Fill)
+        .style(chip_style(accent_2()))
+        .into()
+    });
+    widget::Column::with_children(rows).spacing(6).into()
+}
 
-    container(
-        widget::button(widget::row![
-            container(
-                widget::text((index + 1).to_string())
+/// The download in progress with per-layer detail, followed by queued pulls.
+fn model_downloads<'a>(program: &'a Program, language: Language) -> Element<'a, Message> {
+    let mut rows: Vec<Element<'a, Message>> = Vec::new();
+    if let Some(pull) = &program.active_pull {
+        let progress = pull.progress.lock().unwrap().clone();
+        let mut summary = vec![format!(
+            "{} / {}",
+            format_bytes(progress.completed_bytes()),
+            format_bytes(progress.total_bytes())
+        )];
+        if progress.bytes_per_second > 0.0 {
+            summary.push(format!(
+                "{}/s",
+                format_bytes(progress.bytes_per_second as u64)
+            ));
+        }
+        if let Some(eta) = progress.eta() {
+            summary.push(format!(
+                "{} {}",
+                model_pull::format_eta(eta),
+                tr(language, "left")
+            ));
+        }
+        let layers = progress
+            .layers
+            .iter()
+            .filter(|layer| layer.total > 0)
+            .map(|layer| {
+                let digest = layer.digest.trim_start_matches("sha256:");
+                widget::row![
+                    widget::text(digest.chars().take(12).collect::<String>())
+                        .font(iced::Font::MONOSPACE)
+                        .size(11)
+                        .color(text_muted())
+                        .width(Length::Fixed(110.0)),
+                    widget::progress_bar(0.0..=1.0, layer.completed as f32 / layer.total as f32)
+                        .girth(Length::Fixed(4.0)),
+                    Space::new().width(Length::Fixed(8.0)),
+                    widget::text(format_bytes(layer.total))
+                        .size(11)
+                        .color(text_faint())
+                        .width(Length::Fixed(64.0)),
+                ]
+                .align_y(iced::Alignment::Center)
+                .into()
+            })
+            .collect::<Vec<Element<'a, Message>>>();
+        rows.push(
+            container(widget::column![
+                widget::row![
+                    widget::column![
+                        widget::text(&pull.model).size(14).color(text_main()),
+                        Space::new().height(Length::Fixed(3.0)),
+                        widget::text(progress.status.clone())
+                            .size(11)
+                            .color(text_muted()),
+                    ]
+                    .width(Length::Fill),
+                    mini_button(tr(language, "Cancel"), Message::CancelPull),
+                ]
+                .align_y(iced::Alignment::Center),
+                Space::new().height(Length::Fixed(8.0)),
+                widget::progress_bar(0.0..=1.0, progress.fraction()).girth(Length::Fixed(6.0)),
+                Space::new().height(Length::Fixed(5.0)),
+                widget::text(summary.join(" · "))
                     .size(11)
-                    .color(if active { accent_2() } else { text_muted() })
-                    .align_x(Horizontal::Center)
-            )
-            .padding([4, 7])
-            .style(chip_style(if active {
-                accent_2()
-            } else {
-                border_bright()
-            })),
-            Space::new().width(Length::Fixed(9.0)),
-            widget::column![
-                widget::text(title)
+                    .color(text_muted()),
+                Space::new().height(Length::Fixed(6.0)),
+                widget::Column::with_children(layers).spacing(4),
+            ])
+            .padding([10, 12])
+            .width(Length::Fill)
+            .style(chip_style(accent()))
+            .into(),
+        );
+    }
+    for (index, model) in program.pull_queue.iter().enumerate() {
+        rows.push(
+            widget::row![
+                widget::text(format!("{} {model}", tr(language, "Queued:")))
                     .size(12)
-                    .color(text_main())
-                    .wrapping(Wrapping::None),
-                Space::new().height(Length::Fixed(2.0)),
-                widget::text(host)
-                    .size(11)
                     .color(text_muted())
-                    .wrapping(Wrapping::None),
+                    .width(Length::Fill),
+                mini_button(tr(language, "Remove"), Message::RemoveQueuedPull(index)),
             ]
-            .width(Length::Fill),
-            widget::text(trailing).size(10).color(trailing_color),
-        ])
-        .on_press(Message::OpenSource(source.url.clone()))
-        .padding(0)
-        .style(chat_title_button_style)
-        .clip(true)
-        .width(Length::Fill),
-    )
-    .padding(9)
-    .width(Length::Fill)
-    .style(website_row_style(active))
+            .align_y(iced::Alignment::Center)
+            .into(),
+        );
+    }
+    if rows.is_empty() {
+        return widget::column![].into();
+    }
+    widget::column![
+        Space::new().height(Length::Fixed(10.0)),
+        widget::Column::with_children(rows).spacing(6),
+    ]
     .into()
 }
 
-fn web_search_activity<'a>(state: WebSearchState, language: Language) -> Element<'a, Message> {
-    let (status, detail, count, status_color) = match state {
-        WebSearchState::Searching { query } => {
-            (tr(language, "Searching the web…"), query, None, accent_2())
-        }
-        WebSearchState::Results { query, websites } => (
-            tr(language, "Reviewing results"),
-            query,
-            Some(websites.len()),
-            warning(),
+/// Each supported environment variable with the value the app started with.
+fn environment_rows<'a>(
+    overrides: &EnvironmentOverrides,
+    language: Language,
+) -> Element<'a, Message> {
+    let row = |name: &'static str,
+               value: Option<String>,
+               effect: &'static str,
+               error: Option<&'static str>| {
+        let (value, color) = match (value, error) {
+            (Some(value), Some(error)) => (
+                format!(
+                    "{value} — {}: {}",
+                    tr(language, "ignored"),
+                    tr(language, error)
+                ),
+                danger(),
+            ),
+            (Some(value), None) => (format!("{value} — {}", tr(language, effect)), text_main()),
+            (None, _) => (tr(language, "Not set").to_string(), text_faint()),
+        };
+        widget::row![
+            widget::text(name)
+                .size(12)
+                .font(iced::Font::MONOSPACE)
+                .color(accent_2())
+                .width(Length::Fixed(190.0)),
+            widget::text(value)
+                .size(12)
+                .color(color)
+                .wrapping(Wrapping::WordOrGlyph)
+                .width(Length::Fill),
+        ]
+    };
+    widget::column![
+        row(
+            environment::OLLAMA_HOST,
+            overrides.ollama_host.clone(),
+            "used for the OLLAMA_HOST server profile",
+            overrides.ollama_host_error,
         ),
-        WebSearchState::Fetching {
-            url,
-            query,
-            websites,
-        } => (
-            tr(language, "Reading website"),
-            if query.trim().is_empty() {
-                website_host(&url)
-            } else {
-                query
-            },
-            Some(websites.len()),
-            success(),
+        row(
+            environment::DATA_DIR,
+            overrides
+                .data_dir
+                .as_ref()
+                .map(|path| path.display().to_string()),
+            "used for settings, history and chats",
+            None,
         ),
-        WebSearchState::Failed { message } => (tr(language, "Web search"), message, None, danger()),
-        WebSearchState::Idle | WebSearchState::Completed => return widget::column![].into(),
-    };
-    let detail = ellipsize_chat_title(&detail, 72);
-    let result_count: Element<'a, Message> = count.map_or_else(
-        || widget::column![].into(),
-        |count| {
-            container(
-                widget::text(format!("{count} {}", tr(language, "found")))
-                    .size(10)
-                    .color(status_color),
-            )
-            .padding([4, 7])
-            .style(chip_style(status_color))
-            .into()
-        },
-    );
+    ]
+    .spacing(6)
+    .into()
+}
+
+/// Picks the active Ollama server among the saved profiles.
+fn server_switcher<'a>(program: &Program, language: Language) -> Element<'a, Message> {
+    let choices = server_profiles::profile_choices(&program.server_profiles);
+    let selected = choices.get(program.active_server_profile).cloned();
+    widget::pick_list(choices, selected, Message::SwitchServerProfile)
+        .placeholder(tr(language, "Server"))
+        .padding([10, 12])
+        .text_size(13)
+        .style(pick_list_style)
+        .menu_style(pick_list_menu_style)
+        .into()
+}
+
+/// The opt-in loopback API: switch, port and bearer token.
+fn control_api_settings<'a>(program: &'a Program, language: Language) -> Element<'a, Message> {
+    let settings = &program.control_api;
+    let mut column = widget::column![widget::row![
+        setting_label(
+            tr(language, "Control API"),
+            tr(
+                language,
+                "Lets editors and scripts open chats, send prompts and read answers over HTTP on 127.0.0.1. Every request needs the token below."
+            )
+        ),
+        widget::checkbox(settings.enabled)
+            .label(tr(language, "Enabled"))
+            .on_toggle(|_| Message::ToggleControlApi),
+    ]];
+    if settings.enabled {
+        column = column.push(Space::new().height(Length::Fixed(12.0))).push(
+            widget::row![
+                widget::text(tr(language, "Port"))
+                    .size(13)
+                    .color(text_muted()),
+                iced::widget::TextInput::<Message>::new("11500", &program.control_api_port_input)
+                    .padding(10)
+                    .size(14)
+                    .width(Length::Fixed(96.0))
+                    .on_input(Message::EditControlApiPort)
+                    .style(text_input_style),
+                widget::text(&settings.token)
+                    .size(12)
+                    .font(iced::Font::MONOSPACE)
+                    .color(text_muted())
+                    .wrapping(Wrapping::WordOrGlyph)
+                    .width(Length::Fill),
+                secondary_button(
+                    tr(language, "Copy token"),
+                    Message::CopyPressed(settings.token.clone())
+                ),
+                secondary_button(tr(language, "New token"), Message::NewControlApiToken),
+            ]
+            .spacing(10)
+            .align_y(iced::Alignment::Center),
+        );
+    }
+    column.into()
+}
+
+/// Ranked search hits for the sidebar, each with a snippet of the message
+/// that matched.
+fn chat_search_results<'a>(program: &'a Program, language: Language) -> Vec<Element<'a, Message>> {
+    if program.chat_search_indexing {
+        return vec![
+            widget::text(tr(language, "Indexing chats…"))
+                .size(13)
+                .color(text_muted())
+                .into(),
+        ];
+    }
+    if program.chat_search_results.is_empty() {
+        return vec![
+            widget::text(tr(language, "No matching chats"))
+                .size(13)
+                .color(text_muted())
+                .into(),
+        ];
+    }
+    program
+        .chat_search_results
+        .iter()
+        .map(|hit| {
+            container(
+                widget::button(
+                    widget::column![
+                        widget::text(ellipsize_chat_title(&hit.title, 24))
+                            .size(13)
+                            .wrapping(Wrapping::None),
+                        widget::text(ellipsize_chat_title(&hit.snippet, 96))
+                            .size(11)
+                            .color(text_muted())
+                            .wrapping(Wrapping::WordOrGlyph),
+                    ]
+                    .spacing(2),
+                )
+                .on_press(Message::OpenSearchResult(hit.chat_id.clone(), hit.message))
+                .padding([6, 8])
+                .style(chat_title_button_style)
+                .width(Length::Fill),
+            )
+            .padding(4)
+            .width(Length::Fill)
+            .style(chat_entry_style(hit.chat_id == program.current_chat_id))
+            .into()
+        })
+        .collect()
+}
+
+/// Export and import buttons, plus the chat picker once an import file has
+/// been read.
+fn chat_transfer_settings<'a>(program: &'a Program, language: Language) -> Element<'a, Message> {
+    let mut column = widget::column![widget::row![
+        widget::column![setting_label(
+            tr(language, "Export and import"),
+            tr(
+                language,
+                "Save the open chat or every saved chat as Markdown, HTML or JSON; the format follows the file type chosen. Import reads ChatGPT, Open WebUI and Ollama GUI JSON exports."
+            )
+        )]
+        .width(Length::Fill),
+        secondary_button(
+            tr(language, "Export open chat"),
+            Message::ExportChats(ExportScope::OpenChat)
+        ),
+        Space::new().width(Length::Fixed(6.0)),
+        secondary_button(
+            tr(language, "Export all chats"),
+            Message::ExportChats(ExportScope::AllChats)
+        ),
+        Space::new().width(Length::Fixed(6.0)),
+        secondary_button(tr(language, "Import chats"), Message::ImportChats),
+    ]
+    .align_y(iced::Alignment::Center)];
+
+    let Some(preview) = &program.chat_import else {
+        return column.into();
+    };
+    let selected = preview.chats.iter().filter(|chat| chat.selected).count();
+    let file_name = preview
+        .path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut list = widget::column![].spacing(6);
+    for (index, chat) in preview.chats.iter().enumerate() {
+        let models = chat.models().join(", ");
+        let details = format!(
+            "{} {} · {}{}",
+            chat.messages.len(),
+            tr(language, "messages"),
+            chat.updated_at.get(..10).unwrap_or(&chat.updated_at),
+            if models.is_empty() {
+                String::new()
+            } else {
+                format!(" · {models}")
+            }
+        );
+        list = list.push(
+            widget::row![
+                widget::checkbox(chat.selected)
+                    .on_toggle(move |_| Message::ToggleImportedChat(index)),
+                widget::column![
+                    widget::text(ellipsize_chat_title(&chat.title, 70))
+                        .size(13)
+                        .color(text_main()),
+                    widget::text(details).size(11).color(text_muted()),
+                ]
+                .width(Length::Fill),
+            ]
+            .spacing(8)
+            .align_y(iced::Alignment::Center),
+        );
+    }
+    column = column
+        .push(Space::new().height(Length::Fixed(12.0)))
+        .push(
+            widget::row![
+                widget::text(format!(
+                    "{file_name} · {} · {selected}/{} {}",
+                    preview.source,
+                    preview.chats.len(),
+                    tr(language, "chats selected")
+                ))
+                .size(12)
+                .color(text_muted())
+                .width(Length::Fill),
+                mini_button(
+                    tr(language, "Select all"),
+                    Message::SelectAllImportedChats(true)
+                ),
+                Space::new().width(Length::Fixed(5.0)),
+                mini_button(
+                    tr(language, "Select none"),
+                    Message::SelectAllImportedChats(false)
+                ),
+            ]
+            .align_y(iced::Alignment::Center),
+        )
+        .push(Space::new().height(Length::Fixed(8.0)))
+        .push(widget::scrollable(list).height(Length::Fixed(240.0)))
+        .push(Space::new().height(Length::Fixed(10.0)))
+        .push(widget::row![
+            Space::new().width(Length::Fill),
+            secondary_button(tr(language, "Cancel"), Message::CancelChatImport),
+            Space::new().width(Length::Fixed(6.0)),
+            primary_button(tr(language, "Import selected"), Message::ConfirmChatImport),
+        ]);
+    column.into()
+}
+
+/// Certificates and extra headers of the active server, for Ollama behind
+/// a TLS reverse proxy.
+fn server_connection_fields<'a>(host: &HostLocation, language: Language) -> Element<'a, Message> {
+    let certificate_row = |label: &'static str, path: &Option<String>, kind: CertificateKind| {
+        widget::row![
+            widget::text(tr(language, label))
+                .size(13)
+                .color(text_muted())
+                .width(Length::Fixed(160.0)),
+            widget::text(
+                path.clone()
+                    .unwrap_or_else(|| tr(language, "None").to_string())
+            )
+            .size(13)
+            .color(text_main())
+            .width(Length::Fill),
+            mini_button(
+                tr(language, "Choose file"),
+                Message::PickServerCertificate(kind)
+            ),
+            Space::new().width(Length::Fixed(5.0)),
+            mini_button(tr(language, "Clear"), Message::ClearServerCertificate(kind)),
+        ]
+        .align_y(iced::Alignment::Center)
+    };
+    let headers = host
+        .headers
+        .iter()
+        .enumerate()
+        .map(|(index, (name, value))| {
+            widget::row![
+                iced::widget::TextInput::<Message>::new(tr(language, "Header name"), name)
+                    .padding(10)
+                    .size(13)
+                    .width(Length::FillPortion(2))
+                    .on_input(move |name| Message::EditServerHeaderName(index, name))
+                    .style(text_input_style),
+                Space::new().width(Length::Fixed(6.0)),
+                iced::widget::TextInput::<Message>::new(tr(language, "Value"), value)
+                    .secure(true)
+                    .padding(10)
+                    .size(13)
+                    .width(Length::FillPortion(3))
+                    .on_input(move |value| Message::EditServerHeaderValue(index, value))
+                    .style(text_input_style),
+                Space::new().width(Length::Fixed(6.0)),
+                mini_button(tr(language, "Remove"), Message::RemoveServerHeader(index)),
+            ]
+            .align_y(iced::Alignment::Center)
+            .into()
+        });
+    widget::column![
+        certificate_row(
+            "CA certificate",
+            &host.ca_certificate,
+            CertificateKind::Authority
+        ),
+        Space::new().height(Length::Fixed(6.0)),
+        certificate_row(
+            "Client certificate",
+            &host.client_certificate,
+            CertificateKind::Client
+        ),
+        Space::new().height(Length::Fixed(10.0)),
+        widget::Column::with_children(headers).spacing(6),
+        Space::new().height(Length::Fixed(6.0)),
+        mini_button(tr(language, "Add header"), Message::AddServerHeader),
+    ]
+    .into()
+}
+
+/// The last version check of every saved server.
+fn server_health_rows<'a>(program: &Program) -> Element<'a, Message> {
+    let health = program.server_health.clone();
+    let rows = program
+        .server_profiles
+        .iter()
+        .enumerate()
+        .map(|(index, profile)| {
+            let status = health
+                .get(index)
+                .cloned()
+                .unwrap_or_else(|| "Offline".to_string());
+            let color = if status == "Offline" {
+                danger()
+            } else {
+                success()
+            };
+            widget::row![
+                widget::text(profile.name.clone())
+                    .size(13)
+                    .color(text_main())
+                    .width(Length::Fill),
+                widget::text(profile.base_url())
+                    .size(12)
+                    .color(text_muted()),
+                Space::new().width(Length::Fixed(10.0)),
+                container(widget::text(status).size(12).color(color))
+                    .padding([4, 8])
+                    .style(chip_style(color)),
+            ]
+            .align_y(iced::Alignment::Center)
+            .into()
+        });
+    widget::Column::with_children(rows).spacing(6).into()
+}
+
+/// One model's answer in the compare workspace: streaming while its prompt
+/// runs, then with its statistics and a way to keep chatting with it.
+fn compare_column<'a>(
+    program: &'a Program,
+    column: &'a CompareColumn,
+    language: Language,
+) -> Element<'a, Message> {
+    let text_size = program.user_information.text_size;
+    let copied_text = program.last_copied_text.as_ref();
+    let running = program.active_prompts.get(&column.chat_id);
+    let (status, body, footer): (
+        Element<'a, Message>,
+        Element<'a, Message>,
+        Element<'a, Message>,
+    ) = if let Some(job) = running {
+        (
+            widget::progress_bar(0.0..=1.0, program.prompt_progress())
+                .length(Length::Fixed(64.0))
+                .girth(Length::Fixed(4.0))
+                .into(),
+            markdown_with_code_copy(
+                &job.parsed_markdown,
+                text_size,
+                copied_text,
+                language,
+                program.code_checking_enabled,
+                &program.markdown_images,
+                program.ui_motion,
+            ),
+            widget::column![].into(),
+        )
+    } else {
+        let stats = program
+            .temporary_chats
+            .get(&column.chat_id)
+            .and_then(|session| {
+                session
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .messages
+                    .iter()
+                    .rev()
+                    .find_map(|message| match message {
+                        Correspondence::Bot { stats, .. } => Some(*stats),
+                        Correspondence::User { .. } => None,
+                    })
+            })
+            .flatten();
+        (
+            widget::text(tr(language, "Done"))
+                .size(11)
+                .color(success())
+                .into(),
+            markdown_with_code_copy(
+                &column.markdown,
+                text_size,
+                copied_text,
+                language,
+                program.code_checking_enabled,
+                &program.markdown_images,
+                program.ui_motion,
+            ),
+            widget::column![
+                widget::text(
+                    stats
+                        .map(|stats| generation_stats_summary(&stats, language))
+                        .unwrap_or_default()
+                )
+                .size(11)
+                .color(text_muted()),
+                Space::new().height(Length::Fixed(8.0)),
+                secondary_button(
+                    tr(language, "Continue with this one"),
+                    Message::ContinueWithComparison(column.chat_id.clone())
+                ),
+            ]
+            .into(),
+        )
+    };
+    let notice: Element<'a, Message> = match program.chat_notices.get(&column.chat_id) {
+        Some((notice, _)) if notice.is_error => widget::text(&notice.message)
+            .size(11)
+            .color(danger())
+            .into(),
+        _ => widget::column![].into(),
+    };
+
+    container(widget::column![
+        widget::row![
+            widget::text(&column.model)
+                .size(15)
+                .color(text_main())
+                .wrapping(Wrapping::None)
+                .width(Length::Fill),
+            status,
+        ]
+        .align_y(iced::Alignment::Center),
+        Space::new().height(Length::Fixed(4.0)),
+        notice,
+        Space::new().height(Length::Fixed(8.0)),
+        widget::scrollable(body).height(Length::Fill),
+        Space::new().height(Length::Fixed(10.0)),
+        footer,
+    ])
+    .padding(14)
+    .width(Length::FillPortion(1))
+    .height(Length::Fill)
+    .style(bot_bubble_style(1.0))
+    .into()
+}
+
+/// Form for turning a local GGUF file into a model, or the import under way.
+fn gguf_import_panel<'a>(program: &'a Program, language: Language) -> Element<'a, Message> {
+    let title = widget::row![
+        widget::text(tr(language, "Import GGUF file"))
+            .size(22)
+            .color(text_main()),
+        Space::new().width(Length::Fill),
+        secondary_button(tr(language, "Close"), Message::ToggleGgufImport),
+    ]
+    .align_y(iced::Alignment::Center);
+
+    if let Some(import) = &program.active_import {
+        let progress = import.progress.lock().unwrap().clone();
+        let stage = match progress.stage {
+            ImportStage::Hashing => tr(language, "Computing SHA-256..."),
+            ImportStage::Uploading => tr(language, "Uploading to the server..."),
+            ImportStage::Creating => tr(language, "Creating the model..."),
+        };
+        let detail = if progress.stage == ImportStage::Creating {
+            import
+                .log
+                .lock()
+                .unwrap()
+                .last()
+                .cloned()
+                .unwrap_or_default()
+        } else {
+            format!(
+                "{} / {}",
+                format_bytes(progress.done),
+                format_bytes(progress.total)
+            )
+        };
+        return widget::column![
+            title,
+            Space::new().height(Length::Fixed(14.0)),
+            container(widget::column![
+                widget::row![
+                    widget::column![
+                        widget::text(&import.model).size(14).color(text_main()),
+                        Space::new().height(Length::Fixed(3.0)),
+                        widget::text(stage).size(11).color(text_muted()),
+                    ]
+                    .width(Length::Fill),
+                    mini_button(tr(language, "Cancel"), Message::CancelGgufImport),
+                ]
+                .align_y(iced::Alignment::Center),
+                Space::new().height(Length::Fixed(8.0)),
+                widget::progress_bar(0.0..=1.0, progress.fraction()).girth(Length::Fixed(6.0)),
+                Space::new().height(Length::Fixed(5.0)),
+                widget::text(detail).size(11).color(text_muted()),
+            ])
+            .padding([10, 12])
+            .width(Length::Fill)
+            .style(chip_style(accent())),
+        ]
+        .into();
+    }
+
+    let file = match &program.gguf_path {
+        Some(path) => path.display().to_string(),
+        None => tr(language, "No file chosen").to_string(),
+    };
+    let label = |text: &'static str| {
+        widget::text(tr(language, text))
+            .size(12)
+            .color(text_muted())
+    };
+    widget::column![
+        title,
+        Space::new().height(Length::Fixed(4.0)),
+        widget::text(tr(
+            language,
+            "The file is uploaded to the connected server, so this works with remote hosts too."
+        ))
+        .size(12)
+        .color(text_faint()),
+        Space::new().height(Length::Fixed(14.0)),
+        widget::row![
+            widget::text(file)
+                .size(13)
+                .color(text_main())
+                .width(Length::Fill),
+            secondary_button(tr(language, "Choose file"), Message::PickGgufFile),
+        ]
+        .align_y(iced::Alignment::Center),
+        Space::new().height(Length::Fixed(12.0)),
+        label("Model name"),
+        Space::new().height(Length::Fixed(5.0)),
+        iced::widget::TextInput::<Message>::new(
+            tr(language, "New model name, e.g. reviewer:latest"),
+            &program.gguf_model_name,
+        )
+        .on_input(Message::EditGgufModelName)
+        .padding(10)
+        .width(Length::Fill)
+        .style(text_input_style),
+        Space::new().height(Length::Fixed(12.0)),
+        label("System prompt (optional)"),
+        Space::new().height(Length::Fixed(5.0)),
+        iced::widget::TextInput::<Message>::new(
+            tr(language, "You are a helpful assistant."),
+            &program.gguf_system,
+        )
+        .on_input(Message::EditGgufSystem)
+        .padding(10)
+        .width(Length::Fill)
+        .style(text_input_style),
+        Space::new().height(Length::Fixed(12.0)),
+        label("Template (optional)"),
+        Space::new().height(Length::Fixed(5.0)),
+        widget::text_editor(&program.gguf_template)
+            .placeholder("{{ .System }} {{ .Prompt }}")
+            .on_action(Message::EditGgufTemplate)
+            .font(iced::Font::MONOSPACE)
+            .size(13)
+            .padding(10)
+            .height(Length::Fixed(140.0))
+            .style(text_editor_style),
+        Space::new().height(Length::Fixed(14.0)),
+        primary_button(tr(language, "Import"), Message::ImportGguf),
+    ]
+    .into()
+}
+
+fn model_fact<'a>(label: &'a str, value: String) -> Element<'a, Message> {
+    widget::column![
+        widget::text(label).size(11).color(text_faint()),
+        Space::new().height(Length::Fixed(3.0)),
+        widget::text(if value.is_empty() {
+            "—".to_string()
+        } else {
+            value
+        })
+        .size(14)
+        .color(text_main()),
+    ]
+    .width(Length::Fill)
+    .into()
+}
+
+/// Size, architecture and stored texts of the model picked in the manager.
+fn installed_model_details<'a>(
+    program: &'a Program,
+    model: &'a ollama_rs::models::LocalModel,
+    language: Language,
+) -> Element<'a, Message> {
+    let details: Element<'a, Message> = match program.model_details.get(&model.name) {
+        None => widget::text(tr(language, "Loading model details..."))
+            .size(12)
+            .color(text_faint())
+            .into(),
+        Some(Err(error)) => widget::text(format!(
+            "{} {error}",
+            tr(language, "Could not read model details:")
+        ))
+        .size(12)
+        .color(danger())
+        .into(),
+        Some(Ok(details)) => model_details_view(program.model_text, details, language),
+    };
+    let delete_label = if program.pending_model_delete.as_ref() == Some(&model.name) {
+        tr(language, "Confirm delete")
+    } else {
+        tr(language, "Delete")
+    };
+
+    widget::column![
+        widget::row![
+            widget::text(&model.name).size(22).color(text_main()),
+            Space::new().width(Length::Fill),
+            secondary_button(
+                tr(language, "New from this model"),
+                Message::OpenModelfileEditor(Some(model.name.clone()))
+            ),
+            Space::new().width(Length::Fixed(6.0)),
+            danger_button(
+                delete_label,
+                Message::DeleteInstalledModel(model.name.clone())
+            ),
+        ]
+        .align_y(iced::Alignment::Center),
+        Space::new().height(Length::Fixed(12.0)),
+        widget::row![
+            model_fact(tr(language, "Size"), format_bytes(model.size)),
+            model_fact(
+                tr(language, "Modified"),
+                model_manager::format_modified_at(&model.modified_at)
+            ),
+        ]
+        .spacing(10),
+        Space::new().height(Length::Fixed(12.0)),
+        details,
+        Space::new().height(Length::Fixed(14.0)),
+        widget::text(tr(language, "Copy or rename"))
+            .size(12)
+            .color(text_muted()),
+        Space::new().height(Length::Fixed(6.0)),
+        widget::row![
+            iced::widget::TextInput::<Message>::new(
+                tr(language, "New name, e.g. my-model:latest"),
+                &program.model_copy_name,
+            )
+            .on_input(Message::EditModelCopyName)
+            .on_submit(Message::CopyInstalledModel)
+            .padding(10)
+            .width(Length::Fill)
+            .style(text_input_style),
+            Space::new().width(Length::Fixed(8.0)),
+            secondary_button(tr(language, "Copy"), Message::CopyInstalledModel),
+            Space::new().width(Length::Fixed(6.0)),
+            secondary_button(tr(language, "Rename"), Message::RenameInstalledModel),
+        ]
+        .align_y(iced::Alignment::Center),
+    ]
+    .into()
+}
+
+fn model_details_view<'a>(
+    shown: ModelText,
+    details: &'a ModelDetails,
+    language: Language,
+) -> Element<'a, Message> {
+    let text = match shown {
+        ModelText::Modelfile => &details.modelfile,
+        ModelText::Template => &details.template,
+        ModelText::License => &details.license,
+    };
+    let tab = |label: &'static str, target: ModelText| -> Element<'a, Message> {
+        let active = shown == target;
+        widget::button(widget::text(tr(language, label)).size(12))
+            .padding([7, 10])
+            .style(move |_theme, status| {
+                if active {
+                    button_visual(panel_soft(), accent(), text_main(), status)
+                } else {
+                    button_visual(panel_soft(), border_soft(), text_muted(), status)
+                }
+            })
+            .on_press(Message::ShowModelText(target))
+            .into()
+    };
+    widget::column![
+        widget::row![
+            model_fact(tr(language, "Family"), details.family.clone()),
+            model_fact(tr(language, "Parameters"), details.parameter_size.clone()),
+            model_fact(tr(language, "Quantisation"), details.quantization.clone()),
+            model_fact(
+                tr(language, "Context length"),
+                details
+                    .context_length
+                    .map(|tokens| tokens.to_string())
+                    .unwrap_or_default()
+            ),
+        ]
+        .spacing(10),
+        Space::new().height(Length::Fixed(12.0)),
+        model_fact(tr(language, "License"), details.license_name().to_string()),
+        Space::new().height(Length::Fixed(14.0)),
+        widget::row![
+            tab("Modelfile", ModelText::Modelfile),
+            Space::new().width(Length::Fixed(6.0)),
+            tab("Template", ModelText::Template),
+            Space::new().width(Length::Fixed(6.0)),
+            tab("License", ModelText::License),
+        ],
+        Space::new().height(Length::Fixed(8.0)),
+        container(
+            widget::scrollable(
+                widget::text(text.as_str())
+                    .font(iced::Font::MONOSPACE)
+                    .size(12)
+                    .color(text_main())
+            )
+            .height(Length::Fixed(260.0))
+        )
+        .padding(12)
+        .width(Length::Fill)
+        .style(flat_card_style),
+    ]
+    .into()
+}
+
+/// Text fields for the optional sampling options, two per row. Fields whose
+/// text does not parse keep it but are outlined until corrected.
+fn sampling_fields<'a>(
+    settings: &SamplingSettings,
+    inputs: &'a std::collections::HashMap<SamplingOption, String>,
+    language: Language,
+) -> Element<'a, Message> {
+    let field = |option: SamplingOption| -> Element<'a, Message> {
+        let input = inputs.get(&option).map(String::as_str).unwrap_or_default();
+        let invalid = settings.clone().set_text(option, input).is_err();
+        let placeholder = if option == SamplingOption::Stop {
+            tr(language, "Comma-separated, e.g. </s>, User:")
+        } else {
+            tr(language, "Model default")
+        };
+        widget::column![
+            widget::text(tr(language, option.label()))
+                .size(12)
+                .color(text_muted()),
+            Space::new().height(Length::Fixed(5.0)),
+            iced::widget::TextInput::<Message>::new(placeholder, input)
+                .on_input(move |value| Message::EditSamplingOption(option, value))
+                .padding(9)
+                .width(Length::Fill)
+                .style(move |theme, status| {
+                    let mut style = text_input_style(theme, status);
+                    if invalid {
+                        style.border.color = danger();
+                    }
+                    style
+                }),
+        ]
+        .width(Length::Fill)
+        .into()
+    };
+    let options = SamplingOption::ALL
+        .into_iter()
+        .filter(|option| *option != SamplingOption::Stop)
+        .collect::<Vec<_>>();
+    let mut rows = options
+        .chunks(2)
+        .map(|pair| {
+            widget::Row::with_children(pair.iter().map(|option| field(*option)))
+                .spacing(10)
+                .into()
+        })
+        .collect::<Vec<Element<'a, Message>>>();
+    rows.push(field(SamplingOption::Stop));
+    widget::Column::with_children(rows).spacing(10).into()
+}
+
+fn help_card<'a>(title: &'a str, body: &'a str, color: Color) -> Element<'a, Message> {
+    container(widget::column![
+        container(widget::text(title).size(16).color(text_main()))
+            .padding(8)
+            .style(chip_style(color)),
+        Space::new().height(Length::Fixed(10.0)),
+        widget::text(body).size(14).color(text_muted()),
+    ])
+    .padding(16)
+    .width(Length::Fill)
+    .style(flat_card_style)
+    .into()
+}
+
+fn website_host(url: &str) -> String {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .map(|host| host.strip_prefix("www.").unwrap_or(&host).to_string())
+        .unwrap_or_else(|| url.to_string())
+}
+
+fn ellipsize_chat_title(title: &str, max_chars: usize) -> String {
+    if max_chars == 0 {
+        return String::new();
+    }
+
+    let single_line = title.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut chars = single_line.chars();
+    let mut shortened = chars.by_ref().take(max_chars).collect::<String>();
+
+    if chars.next().is_some() {
+        shortened.pop();
+        shortened.push('…');
+    }
+
+    shortened
+}
+
+fn website_result_row<'a>(
+    index: usize,
+    source: &WebSource,
+    active: bool,
+    language: Language,
+) -> Element<'a, Message> {
+    let host = website_host(&source.url);
+    let title = ellipsize_chat_title(&source.title, 64);
+    let host = ellipsize_chat_title(&host, 64);
+    let trailing = if active {
+        tr(language, "READING").to_string()
+    } else {
+        "↗".to_string()
+    };
+    let trailing_color = if active { accent_2() } else { text_faint() };
+
+    container(
+        widget::button(widget::row![
+            container(
+                widget::text((index + 1).to_string())
+                    .size(11)
+                    .color(if active { accent_2() } else { text_muted() })
+                    .align_x(Horizontal::Center)
+            )
+            .padding([4, 7])
+            .style(chip_style(if active {
+                accent_2()
+            } else {
+                border_bright()
+            })),
+            Space::new().width(Length::Fixed(9.0)),
+            widget::column![
+                widget::text(title)
+                    .size(12)
+                    .color(text_main())
+                    .wrapping(Wrapping::None),
+                Space::new().height(Length::Fixed(2.0)),
+                widget::text(host)
+                    .size(11)
+                    .color(text_muted())
+                    .wrapping(Wrapping::None),
+            ]
+            .width(Length::Fill),
+            widget::text(trailing).size(10).color(trailing_color),
+        ])
+        .on_press(Message::OpenSource(source.url.clone()))
+        .padding(0)
+        .style(chat_title_button_style)
+        .clip(true)
+        .width(Length::Fill),
+    )
+    .padding(9)
+    .width(Length::Fill)
+    .style(website_row_style(active))
+    .into()
+}
+
+fn web_search_activity<'a>(state: WebSearchState, language: Language) -> Element<'a, Message> {
+    let (status, detail, count, status_color) = match state {
+        WebSearchState::Searching { query } => {
+            (tr(language, "Searching the web…"), query, None, accent_2())
+        }
+        WebSearchState::Results { query, websites } => (
+            tr(language, "Reviewing results"),
+            query,
+            Some(websites.len()),
+            warning(),
+        ),
+        WebSearchState::Fetching {
+            url,
+            query,
+            websites,
+        } => (
+            tr(language, "Reading website"),
+            if query.trim().is_empty() {
+                website_host(&url)
+            } else {
+                query
+            },
+            Some(websites.len()),
+            success(),
+        ),
+        WebSearchState::Failed { message } => (tr(language, "Web search"), message, None, danger()),
+        WebSearchState::Idle | WebSearchState::Completed => return widget::column![].into(),
+    };
+    let detail = ellipsize_chat_title(&detail, 72);
+    let result_count: Element<'a, Message> = count.map_or_else(
+        || widget::column![].into(),
+        |count| {
+            container(
+                widget::text(format!("{count} {}", tr(language, "found")))
+                    .size(10)
+                    .color(status_color),
+            )
+            .padding([4, 7])
+            .style(chip_style(status_color))
+            .into()
+        },
+    );
 
     container(widget::row![
         widget::text("●").size(10).color(status_color),
@@ -1709,7 +3085,7 @@ fn markdown_with_code_copy<'a>(
                     Space::new().width(Length::Fill),
                     check_button,
                     Space::new().width(Length::Fixed(6.0)),
-                    copy_code_button(code.clone(), copied, language),
+                    copy_button(code.clone(), "Copy code", copied, language),
                 ]
                 .into(),
             );
@@ -1721,6 +3097,43 @@ fn markdown_with_code_copy<'a>(
         .into()
 }
 
+/// A "‹ 2/3 ›" control for stepping through response variants or branches.
+fn position_switcher<'a>(
+    active: usize,
+    total: usize,
+    enabled: bool,
+    select: impl Fn(usize) -> Message,
+) -> Element<'a, Message> {
+    let previous = active.checked_sub(1).filter(|_| enabled).map(&select);
+    let next = (enabled && active + 1 < total).then(|| select(active + 1));
+    widget::row![
+        widget::button(widget::text("‹").size(13))
+            .padding([4, 9])
+            .style(|_theme, status| button_visual(
+                panel_soft(),
+                border_soft(),
+                text_muted(),
+                status
+            ))
+            .on_press_maybe(previous),
+        widget::text(format!("{}/{}", active + 1, total))
+            .size(12)
+            .color(text_muted()),
+        widget::button(widget::text("›").size(13))
+            .padding([4, 9])
+            .style(|_theme, status| button_visual(
+                panel_soft(),
+                border_soft(),
+                text_muted(),
+                status
+            ))
+            .on_press_maybe(next),
+    ]
+    .spacing(6)
+    .align_y(iced::alignment::Vertical::Center)
+    .into()
+}
+
 #[allow(clippy::too_many_arguments)]
 fn message_bubble<'a>(
     index: usize,
@@ -1734,43 +3147,128 @@ fn message_bubble<'a>(
     language: Language,
     code_checking_enabled: bool,
     markdown_images: &'a std::collections::HashMap<String, MarkdownImageState>,
+    collapsed_json: &std::collections::HashSet<(usize, String)>,
+    editing: Option<&'a widget::text_editor::Content>,
+    can_edit: bool,
+    can_regenerate: bool,
     reveal: f32,
     motion: f32,
 ) -> Element<'a, Message> {
     match message {
-        Correspondence::User { text, images } => widget::row![
-            Space::new().width(Length::Fill),
-            container(widget::column![
-                widget::text(tr(language, "You"))
-                    .size(12)
-                    .color(if is_dark_mode() {
-                        rgb(205, 221, 255)
-                    } else {
-                        rgb(55, 72, 150)
-                    })
-                    .align_x(Horizontal::Right),
-                Space::new().height(Length::Fixed(6.0)),
-                image_previews(images, false, language),
+        Correspondence::User { text, images, .. } => {
+            let body: Element<'a, Message> = if let Some(editor) = editing {
+                widget::column![
+                    widget::text_editor(editor)
+                        .on_action(Message::EditTurnAction)
+                        .size(text_size)
+                        .padding(10)
+                        .width(520.0)
+                        .style(text_editor_style),
+                    Space::new().height(Length::Fixed(8.0)),
+                    widget::row![
+                        Space::new().width(Length::Fill),
+                        secondary_button(tr(language, "Cancel"), Message::CancelEditTurn),
+                        Space::new().width(Length::Fixed(8.0)),
+                        primary_button(tr(language, "Send edit"), Message::SubmitEditedTurn),
+                    ],
+                ]
+                .width(Length::Fixed(520.0))
+                .into()
+            } else {
                 widget::text(text)
                     .size(text_size)
-                    .align_x(Horizontal::Right),
-            ])
-            .padding([13, 16])
-            .width(Length::Shrink)
-            .max_width(760)
-            .style(user_bubble_style(reveal)),
-            Space::new().width(Length::Fixed(4.0 + (1.0 - reveal) * 8.0)),
-        ]
-        .into(),
+                    .align_x(Horizontal::Right)
+                    .into()
+            };
+            let (active_branch, branch_count) = message.branch_position().unwrap_or((0, 1));
+            let branch_actions: Element<'a, Message> =
+                if editing.is_none() && (branch_count > 1 || can_edit) {
+                    widget::column![
+                        Space::new().height(Length::Fixed(8.0)),
+                        widget::row![
+                            Space::new().width(Length::Fill),
+                            if branch_count > 1 {
+                                position_switcher(
+                                    active_branch,
+                                    branch_count,
+                                    can_edit,
+                                    move |branch| Message::SelectBranch(index, branch),
+                                )
+                            } else {
+                                widget::row![].into()
+                            },
+                            Space::new().width(Length::Fixed(6.0)),
+                            if can_edit {
+                                mini_button(tr(language, "Edit"), Message::StartEditTurn(index))
+                            } else {
+                                widget::row![].into()
+                            },
+                        ]
+                        .align_y(iced::alignment::Vertical::Center),
+                    ]
+                    .into()
+                } else {
+                    widget::column![].into()
+                };
+
+            widget::row![
+                Space::new().width(Length::Fill),
+                container(widget::column![
+                    widget::text(tr(language, "You"))
+                        .size(12)
+                        .color(if is_dark_mode() {
+                            rgb(205, 221, 255)
+                        } else {
+                            rgb(55, 72, 150)
+                        })
+                        .align_x(Horizontal::Right),
+                    Space::new().height(Length::Fixed(6.0)),
+                    image_previews(images, false, language),
+                    body,
+                    branch_actions,
+                ])
+                .padding([13, 16])
+                .width(Length::Shrink)
+                .max_width(760)
+                .style(user_bubble_style(reveal)),
+                Space::new().width(Length::Fixed(4.0 + (1.0 - reveal) * 8.0)),
+            ]
+            .into()
+        }
 
         Correspondence::Bot {
             text,
             thinking_seconds,
             sources,
             web_search_used,
+            stats,
+            format_check,
             ..
         } => {
-            let body: Element<'a, Message> = if let Some(parsed) = parsed_markdown {
+            let structured = match format_check {
+                Some(FormatCheck::Valid) => {
+                    serde_json::from_str::<serde_json::Value>(split_thinking_text(text).1.trim())
+                        .ok()
+                }
+                _ => None,
+            };
+            let format_notice: Element<'a, Message> = match format_check {
+                Some(FormatCheck::Invalid(errors)) => format_check_notice(errors, language),
+                _ => widget::column![].into(),
+            };
+            let body: Element<'a, Message> = if let Some(value) = &structured {
+                let pretty = serde_json::to_string_pretty(value).unwrap_or_default();
+                let copied = copied_text == Some(&pretty);
+                widget::column![
+                    json_tree(index, None, value, "$".to_string(), collapsed_json),
+                    Space::new().height(Length::Fixed(8.0)),
+                    widget::row![
+                        Space::new().width(Length::Fill),
+                        copy_button(pretty, "Copy JSON", copied, language),
+                    ],
+                ]
+                .into()
+            } else if let Some(parsed) = parsed_markdown {
                 markdown_with_code_copy(
                     parsed,
                     text_size,
@@ -1852,6 +3350,47 @@ fn message_bubble<'a>(
                 .into()
             };
 
+            let stats_line: Element<'a, Message> = match stats {
+                Some(stats) => widget::column![
+                    Space::new().height(Length::Fixed(10.0)),
+                    widget::text(generation_stats_summary(stats, language))
+                        .size(11)
+                        .color(text_faint()),
+                ]
+                .into(),
+                None => widget::column![].into(),
+            };
+
+            let (active_variant, variant_count) = message.variant_position().unwrap_or((0, 1));
+            let variant_switcher: Element<'a, Message> = if variant_count > 1 {
+                position_switcher(active_variant, variant_count, true, move |variant| {
+                    Message::SelectResponseVariant(index, variant)
+                })
+            } else {
+                widget::row![].into()
+            };
+            let response_actions: Element<'a, Message> = if variant_count > 1 || can_regenerate {
+                widget::column![
+                    Space::new().height(Length::Fixed(10.0)),
+                    widget::row![
+                        variant_switcher,
+                        Space::new().width(Length::Fill),
+                        if can_regenerate {
+                            mini_button(
+                                tr(language, "Regenerate"),
+                                Message::RegenerateResponse(index),
+                            )
+                        } else {
+                            widget::row![].into()
+                        },
+                    ]
+                    .align_y(iced::alignment::Vertical::Center),
+                ]
+                .into()
+            } else {
+                widget::column![].into()
+            };
+
             widget::row![
                 Space::new().width(Length::Fixed((1.0 - reveal) * 8.0)),
                 container(widget::text("✦").size(17).color(Color::WHITE))
@@ -1872,8 +3411,11 @@ fn message_bubble<'a>(
                     ],
                     Space::new().height(Length::Fixed(7.0)),
                     reasoning,
+                    format_notice,
                     body,
                     source_list,
+                    stats_line,
+                    response_actions,
                 ])
                 .padding(14)
                 .width(Length::Fill)
@@ -1938,7 +3480,7 @@ impl Program {
                                     Space::new().width(Length::Fixed(12.0)),
                                     help_card(
                                         tr(language, "Manage models"),
-                                        tr(language, "Use Advanced Settings to install models by name, change the Ollama address, or tune response rendering."),
+                                        tr(language, "Use Advanced Settings to install, inspect, copy, and delete models, change the Ollama address, or tune response rendering."),
                                         accent_2(),
                                     ),
                                 ],
@@ -2042,6 +3584,26 @@ impl Program {
                         .unwrap_or_else(|| tr(language, "New conversation").to_string())
                 };
 
+                // Settings this chat restored that differ from what new chats use.
+                let overrides = self.current_chat_settings().overrides(&self.chat_defaults);
+                let overrides_gap = if overrides.is_empty() { 0.0 } else { 6.0 };
+                let chat_overrides =
+                    widget::Row::with_children(overrides.into_iter().map(|(label, value)| {
+                        container(
+                            widget::text(format!(
+                                "{}: {}",
+                                tr(language, label),
+                                ellipsize_chat_title(&value, 28)
+                            ))
+                            .size(11)
+                            .color(text_muted()),
+                        )
+                        .padding([4, 8])
+                        .style(chip_style(accent_2()))
+                        .into()
+                    }))
+                    .spacing(6);
+
                 let prompt_to_send = self.prompt.prompt.clone();
                 let prompt = widget::text_editor(&self.prompt.editor)
                     .placeholder(tr(language, "Ask something..."))
@@ -2108,22 +3670,58 @@ impl Program {
                         let reveal = eased(self.page_reveal * 1.3 - (index.min(10) as f32 * 0.025));
                         let motion = 1.0 - (self.ui_motion * 2.0 - 1.0).abs();
 
+                        let summary_marker: Element<Message> =
+                            if self.chat_summary_boundary == Some(index) {
+                                widget::column![
+                                    summary_divider(language),
+                                    Space::new().height(Length::Fixed(10.0)),
+                                ]
+                                .into()
+                            } else {
+                                widget::column![].into()
+                            };
+
+                        let bubble = message_bubble(
+                            index,
+                            message,
+                            parsed_markdown,
+                            cached_thinking,
+                            user_information.text_size,
+                            message_model_name,
+                            copied_text.as_ref(),
+                            self.expanded_thinking.contains(&index),
+                            language,
+                            self.code_checking_enabled,
+                            &self.markdown_images,
+                            &self.collapsed_json_nodes,
+                            self.editing_message
+                                .as_ref()
+                                .filter(|(editing, _)| *editing == index)
+                                .map(|(_, editor)| editor),
+                            !is_processing,
+                            !is_processing && index + 1 == chat_messages.len(),
+                            reveal,
+                            motion,
+                        );
+                        let highlighted =
+                            self.search_highlight
+                                .as_ref()
+                                .is_some_and(|(chat_id, message)| {
+                                    *chat_id == self.current_chat_id && *message == index
+                                });
+                        let bubble = if highlighted {
+                            container(bubble)
+                                .padding(4)
+                                .width(Length::Fill)
+                                .style(search_highlight_style)
+                                .into()
+                        } else {
+                            bubble
+                        };
+
                         vec![
-                            message_bubble(
-                                index,
-                                message,
-                                parsed_markdown,
-                                cached_thinking,
-                                user_information.text_size,
-                                message_model_name,
-                                copied_text.as_ref(),
-                                self.expanded_thinking.contains(&index),
-                                language,
-                                self.code_checking_enabled,
-                                &self.markdown_images,
-                                reveal,
-                                motion,
-                            ),
+                            summary_marker,
+                            bubble,
                             Space::new().height(Length::Fixed(10.0)).into(),
                         ]
                     })
@@ -2425,131 +4023,156 @@ impl Program {
                             },
                             Message::ToggleTemporaryChat,
                         ),
-                        Space::new().height(Length::Fixed(14.0)).into(),
+                        Space::new().height(Length::Fixed(10.0)).into(),
+                        iced::widget::TextInput::<Message>::new(
+                            tr(language, "Search chats"),
+                            &self.chat_search_query,
+                        )
+                        .on_input(Message::ChatSearchChanged)
+                        .padding([8, 10])
+                        .size(13)
+                        .style(text_input_style)
+                        .into(),
+                        Space::new().height(Length::Fixed(12.0)).into(),
                     ];
-                    let has_temporary_chats = self.temporary_chat
-                        || !self.temporary_chats.is_empty()
-                        || self.active_prompts.values().any(|job| job.temporary);
-                    if has_temporary_chats {
-                        entries.push(
-                            widget::text(tr(language, "Temporary chats"))
-                                .size(13)
-                                .color(text_muted())
-                                .into(),
-                        );
-                    }
-                    let mut temporary_jobs = self
-                        .active_prompts
-                        .iter()
-                        .filter(|(_, job)| job.temporary)
-                        .collect::<Vec<_>>();
-                    temporary_jobs.sort_by_key(|(_, job)| job.started_at);
-                    for (chat_id, job) in temporary_jobs {
-                        let title = job
-                            .chat_history
-                            .lock()
-                            .ok()
-                            .and_then(|chat| {
-                                chat.messages.iter().find_map(|message| match message {
-                                    Correspondence::User { text, .. } => Some(text.clone()),
-                                    Correspondence::Bot { .. } => None,
-                                })
-                            })
-                            .unwrap_or_else(|| tr(language, "Temporary chat").to_string());
-                        let title = format!("T · {}", ellipsize_chat_title(&title, 16));
-                        entries.push(
-                            container(widget::row![
-                                widget::button(
-                                    widget::text(title).size(13).wrapping(Wrapping::None)
-                                )
-                                .on_press(Message::OpenChat(chat_id.clone()))
-                                .style(chat_title_button_style)
-                                .clip(true)
-                                .width(Length::Fill),
-                                widget::progress_bar(0.0..=1.0, self.prompt_progress())
-                                    .length(Length::Fixed(42.0))
-                                    .girth(Length::Fixed(4.0)),
-                            ])
-                            .padding(4)
-                            .width(Length::Fill)
-                            .style(chat_entry_style(chat_id == &self.current_chat_id))
-                            .into(),
-                        );
-                    }
-                    let mut temporary_sessions = self.temporary_chats.iter().collect::<Vec<_>>();
-                    temporary_sessions.sort_by_key(|(chat_id, _)| *chat_id);
-                    for (chat_id, session) in temporary_sessions {
-                        let title = session
-                            .chat_history
-                            .lock()
-                            .ok()
-                            .and_then(|chat| {
-                                chat.messages.iter().find_map(|message| match message {
-                                    Correspondence::User { text, .. } => Some(text.clone()),
-                                    Correspondence::Bot { .. } => None,
-                                })
+                    if self.chat_search_query.trim().is_empty() {
+                        let has_temporary_chats = self.temporary_chat
+                            || self
+                                .temporary_chats
+                                .keys()
+                                .any(|chat_id| !self.is_compare_chat(chat_id))
+                            || self.active_prompts.iter().any(|(chat_id, job)| {
+                                job.temporary && !self.is_compare_chat(chat_id)
+                            });
+                        if has_temporary_chats {
+                            entries.push(
+                                widget::text(tr(language, "Temporary chats"))
+                                    .size(13)
+                                    .color(text_muted())
+                                    .into(),
+                            );
+                        }
+                        let mut temporary_jobs = self
+                            .active_prompts
+                            .iter()
+                            .filter(|(chat_id, job)| {
+                                job.temporary && !self.is_compare_chat(chat_id)
                             })
-                            .unwrap_or_else(|| tr(language, "Temporary chat").to_string());
-                        let title = format!("T · {}", ellipsize_chat_title(&title, 16));
-                        entries.push(
-                            container(widget::row![
-                                widget::button(
-                                    widget::text(title).size(13).wrapping(Wrapping::None)
-                                )
-                                .on_press(Message::OpenChat(chat_id.clone()))
-                                .style(chat_title_button_style)
-                                .clip(true)
-                                .width(Length::Fill),
-                                mini_button("×", Message::DeleteTemporaryChat(chat_id.clone())),
-                            ])
-                            .padding(4)
-                            .width(Length::Fill)
-                            .style(chat_entry_style(chat_id == &self.current_chat_id))
-                            .into(),
-                        );
-                    }
-                    if has_temporary_chats {
-                        entries.push(Space::new().height(Length::Fixed(8.0)).into());
-                    }
-                    entries.push(
-                        widget::text(tr(language, "Saved chats"))
-                            .size(13)
-                            .color(text_muted())
-                            .into(),
-                    );
-                    for saved in &self.saved_chats {
-                        let selected = saved.id == self.current_chat_id;
-                        let title = ellipsize_chat_title(&saved.title, 20);
-                        let working = self.active_prompts.contains_key(&saved.id);
-                        let working_progress: Element<Message> = if working {
-                            widget::progress_bar(0.0..=1.0, self.prompt_progress())
-                                .length(Length::Fixed(42.0))
-                                .girth(Length::Fixed(4.0))
-                                .into()
-                        } else {
-                            widget::column![].into()
-                        };
+                            .collect::<Vec<_>>();
+                        temporary_jobs.sort_by_key(|(_, job)| job.started_at);
+                        for (chat_id, job) in temporary_jobs {
+                            let title = job
+                                .chat_history
+                                .lock()
+                                .ok()
+                                .and_then(|chat| {
+                                    chat.messages.iter().find_map(|message| match message {
+                                        Correspondence::User { text, .. } => Some(text.clone()),
+                                        Correspondence::Bot { .. } => None,
+                                    })
+                                })
+                                .unwrap_or_else(|| tr(language, "Temporary chat").to_string());
+                            let title = format!("T · {}", ellipsize_chat_title(&title, 16));
+                            entries.push(
+                                container(widget::row![
+                                    widget::button(
+                                        widget::text(title).size(13).wrapping(Wrapping::None)
+                                    )
+                                    .on_press(Message::OpenChat(chat_id.clone()))
+                                    .style(chat_title_button_style)
+                                    .clip(true)
+                                    .width(Length::Fill),
+                                    widget::progress_bar(0.0..=1.0, self.prompt_progress())
+                                        .length(Length::Fixed(42.0))
+                                        .girth(Length::Fixed(4.0)),
+                                ])
+                                .padding(4)
+                                .width(Length::Fill)
+                                .style(chat_entry_style(chat_id == &self.current_chat_id))
+                                .into(),
+                            );
+                        }
+                        let mut temporary_sessions = self
+                            .temporary_chats
+                            .iter()
+                            .filter(|(chat_id, _)| !self.is_compare_chat(chat_id))
+                            .collect::<Vec<_>>();
+                        temporary_sessions.sort_by_key(|(chat_id, _)| *chat_id);
+                        for (chat_id, session) in temporary_sessions {
+                            let title = session
+                                .chat_history
+                                .lock()
+                                .ok()
+                                .and_then(|chat| {
+                                    chat.messages.iter().find_map(|message| match message {
+                                        Correspondence::User { text, .. } => Some(text.clone()),
+                                        Correspondence::Bot { .. } => None,
+                                    })
+                                })
+                                .unwrap_or_else(|| tr(language, "Temporary chat").to_string());
+                            let title = format!("T · {}", ellipsize_chat_title(&title, 16));
+                            entries.push(
+                                container(widget::row![
+                                    widget::button(
+                                        widget::text(title).size(13).wrapping(Wrapping::None)
+                                    )
+                                    .on_press(Message::OpenChat(chat_id.clone()))
+                                    .style(chat_title_button_style)
+                                    .clip(true)
+                                    .width(Length::Fill),
+                                    mini_button("×", Message::DeleteTemporaryChat(chat_id.clone())),
+                                ])
+                                .padding(4)
+                                .width(Length::Fill)
+                                .style(chat_entry_style(chat_id == &self.current_chat_id))
+                                .into(),
+                            );
+                        }
+                        if has_temporary_chats {
+                            entries.push(Space::new().height(Length::Fixed(8.0)).into());
+                        }
                         entries.push(
-                            container(widget::row![
-                                widget::button(
-                                    widget::text(title).size(13).wrapping(Wrapping::None)
-                                )
-                                .on_press(Message::OpenChat(saved.id.clone()))
-                                .style(chat_title_button_style)
-                                .clip(true)
-                                .width(Length::Fill),
-                                working_progress,
-                                mini_button(
-                                    tr(language, if saved.pinned { "Unpin" } else { "Pin" }),
-                                    Message::ToggleChatPin(saved.id.clone()),
-                                ),
-                                mini_button("×", Message::DeleteChat(saved.id.clone())),
-                            ])
-                            .padding(4)
-                            .width(Length::Fill)
-                            .style(chat_entry_style(selected))
-                            .into(),
+                            widget::text(tr(language, "Saved chats"))
+                                .size(13)
+                                .color(text_muted())
+                                .into(),
                         );
+                        for saved in &self.saved_chats {
+                            let selected = saved.id == self.current_chat_id;
+                            let title = ellipsize_chat_title(&saved.title, 20);
+                            let working = self.active_prompts.contains_key(&saved.id);
+                            let working_progress: Element<Message> = if working {
+                                widget::progress_bar(0.0..=1.0, self.prompt_progress())
+                                    .length(Length::Fixed(42.0))
+                                    .girth(Length::Fixed(4.0))
+                                    .into()
+                            } else {
+                                widget::column![].into()
+                            };
+                            entries.push(
+                                container(widget::row![
+                                    widget::button(
+                                        widget::text(title).size(13).wrapping(Wrapping::None)
+                                    )
+                                    .on_press(Message::OpenChat(saved.id.clone()))
+                                    .style(chat_title_button_style)
+                                    .clip(true)
+                                    .width(Length::Fill),
+                                    working_progress,
+                                    mini_button(
+                                        tr(language, if saved.pinned { "Unpin" } else { "Pin" }),
+                                        Message::ToggleChatPin(saved.id.clone()),
+                                    ),
+                                    mini_button("×", Message::DeleteChat(saved.id.clone())),
+                                ])
+                                .padding(4)
+                                .width(Length::Fill)
+                                .style(chat_entry_style(selected))
+                                .into(),
+                            );
+                        }
+                    } else {
+                        entries.extend(chat_search_results(self, language));
                     }
                     container(widget::column![
                         widget::row![
@@ -2595,7 +4218,7 @@ impl Program {
                     let mut temporary_jobs = self
                         .active_prompts
                         .iter()
-                        .filter(|(_, job)| job.temporary)
+                        .filter(|(chat_id, job)| job.temporary && !self.is_compare_chat(chat_id))
                         .collect::<Vec<_>>();
                     temporary_jobs.sort_by_key(|(_, job)| job.started_at);
                     for (chat_id, _) in temporary_jobs {
@@ -2619,7 +4242,11 @@ impl Program {
                             .into(),
                         );
                     }
-                    let mut temporary_sessions = self.temporary_chats.keys().collect::<Vec<_>>();
+                    let mut temporary_sessions = self
+                        .temporary_chats
+                        .keys()
+                        .filter(|chat_id| !self.is_compare_chat(chat_id))
+                        .collect::<Vec<_>>();
                     temporary_sessions.sort();
                     for chat_id in temporary_sessions {
                         compact_entries.push(
@@ -2704,6 +4331,79 @@ impl Program {
                     .into()
                 };
 
+                let structured_output_active = self.user_information.response_format.is_some();
+                let structured_toggle = mini_button(
+                    if structured_output_active {
+                        tr(language, "{ } Structured on")
+                    } else {
+                        tr(language, "{ } Structured")
+                    },
+                    Message::ToggleStructuredOutput,
+                );
+                let structured_output_panel: Element<Message> = if self.structured_output_open {
+                    let (status, status_color) = match ResponseFormat::parse(
+                        self.user_information
+                            .response_format
+                            .as_deref()
+                            .unwrap_or_default(),
+                    ) {
+                        Ok(None) => (tr(language, "Off").to_string(), text_faint()),
+                        Ok(Some(ResponseFormat::Json)) => {
+                            (tr(language, "Any JSON value").to_string(), success())
+                        }
+                        Ok(Some(ResponseFormat::Schema(_))) => {
+                            (tr(language, "Schema ready").to_string(), success())
+                        }
+                        Err(error) => (error, danger()),
+                    };
+                    widget::column![
+                        Space::new().height(Length::Fixed(10.0)),
+                        container(widget::column![
+                            widget::row![
+                                widget::text(tr(language, "Structured output"))
+                                    .size(14)
+                                    .color(text_main()),
+                                Space::new().width(Length::Fill),
+                                mini_button(tr(language, "Done"), Message::ToggleStructuredOutput),
+                            ]
+                            .align_y(iced::alignment::Vertical::Center),
+                            Space::new().height(Length::Fixed(4.0)),
+                            widget::text(tr(
+                                language,
+                                "Paste a JSON Schema, or json for any JSON value. Answers in this chat are checked against it."
+                            ))
+                            .size(11)
+                            .color(text_muted()),
+                            Space::new().height(Length::Fixed(8.0)),
+                            widget::text_editor(&self.response_format_editor)
+                                .placeholder("{ \"type\": \"object\", \"properties\": { ... } }")
+                                .on_action(Message::EditResponseFormat)
+                                .font(iced::Font::MONOSPACE)
+                                .size(13)
+                                .padding(10)
+                                .height(Length::Fixed(150.0))
+                                .style(text_editor_style),
+                            Space::new().height(Length::Fixed(8.0)),
+                            widget::row![
+                                mini_button(tr(language, "Load from file"), Message::LoadResponseFormat),
+                                Space::new().width(Length::Fixed(5.0)),
+                                mini_button(tr(language, "Any JSON"), Message::UseJsonFormat),
+                                Space::new().width(Length::Fixed(5.0)),
+                                mini_button(tr(language, "Clear"), Message::ClearResponseFormat),
+                                Space::new().width(Length::Fixed(10.0)),
+                                widget::text(status).size(12).color(status_color),
+                            ]
+                            .align_y(iced::alignment::Vertical::Center),
+                        ])
+                        .padding(14)
+                        .width(Length::Fill)
+                        .style(flat_card_style),
+                    ]
+                    .into()
+                } else {
+                    widget::column![].into()
+                };
+
                 let content = widget::column![
                     Space::new().height(Length::Fixed((1.0 - eased(self.page_reveal)) * 4.0)),
                     container(widget::column![
@@ -2714,10 +4414,22 @@ impl Program {
                                     .color(accent_2()),
                                 Space::new().height(Length::Fixed(3.0)),
                                 widget::text(current_chat_title).size(20).color(text_main()),
+                                Space::new().height(Length::Fixed(overrides_gap)),
+                                chat_overrides,
                             ],
                             Space::new().width(Length::Fill),
+                            server_switcher(self, language),
+                            Space::new().width(Length::Fixed(6.0)),
+                            toolbar_button("⇆", tr(language, "Compare"), Message::ToggleCompare),
+                            Space::new().width(Length::Fixed(6.0)),
                             toolbar_button("▣", tr(language, "Images"), Message::ToggleImages),
                             Space::new().width(Length::Fixed(6.0)),
+                            toolbar_button(
+                                "⇩",
+                                tr(language, "Export"),
+                                Message::ExportChats(ExportScope::OpenChat)
+                            ),
+                            Space::new().width(Length::Fixed(6.0)),
                             toolbar_button("⚙", tr(language, "Settings"), Message::ToggleSettings),
                         ],
                         Space::new().height(Length::Fixed(16.0)),
@@ -2778,6 +4490,7 @@ impl Program {
                             ]
                             .spacing(iced::Pixels(6.0))
                         )
+                        .id(CONVERSATION_SCROLL_ID)
                         .height(Length::Fill)
                         .anchor_bottom()
                     )
@@ -2785,6 +4498,7 @@ impl Program {
                     .width(Length::Fill)
                     .height(Length::Fill)
                     .style(conversation_style),
+                    structured_output_panel,
                     composer_resize_handle(),
                     container(
                         widget::column![
@@ -2796,6 +4510,8 @@ impl Program {
                                 mini_button(tr(language, "Paste"), Message::PasteImage),
                                 Space::new().width(Length::Fixed(5.0)),
                                 web_toggle,
+                                Space::new().width(Length::Fixed(5.0)),
+                                structured_toggle,
                                 Space::new().width(Length::Fill),
                                 widget::text(tr(language, "Enter to send"))
                                     .size(11)
@@ -3623,70 +5339,452 @@ impl Program {
                             .width(Length::Fill)
                             .style(flat_card_style),
 
-                            Space::new().height(Length::Fixed(10.0)),
+                            Space::new().height(Length::Fixed(10.0)),
+
+                            container(chat_transfer_settings(self, language))
+                                .padding(16)
+                                .width(Length::Fill)
+                                .style(flat_card_style),
+
+                            Space::new().height(Length::Fixed(10.0)),
+
+                            container(widget::column![
+                                setting_label(
+                                    tr(language, "Environment"),
+                                    tr(language, "Values read from environment variables when the app started. They take precedence over saved settings.")
+                                ),
+                                Space::new().height(Length::Fixed(8.0)),
+                                environment_rows(&self.environment, language),
+                            ])
+                            .padding(16)
+                            .width(Length::Fill)
+                            .style(flat_card_style),
+
+                            Space::new().height(Length::Fixed(10.0)),
+
+                            container(control_api_settings(self, language))
+                                .padding(16)
+                                .width(Length::Fill)
+                                .style(flat_card_style),
+
+                            Space::new().height(Length::Fixed(10.0)),
+
+                            container(
+                                widget::row![
+                                    setting_label(
+                                        tr(language, "Model conversation context"),
+                                        tr(language, "Include earlier messages from this chat in the next model request. Saved chats are managed in the left menu.")
+                                    ),
+                                    widget::checkbox(
+                                        user_information.current_chat_history_enabled
+                                    )
+                                    .label(tr(language, "Enabled"))
+                                    .on_toggle(|_| Message::ToggleChatHistory),
+                                ]
+                            )
+                            .padding(16)
+                            .width(Length::Fill)
+                            .style(flat_card_style),
+
+                            Space::new().height(Length::Fixed(14.0)),
+
+                            container(
+                                widget::row![
+                                    widget::column![
+                                        widget::text(tr(language, "Maintenance"))
+                                            .size(16)
+                                            .color(text_main()),
+                                        Space::new().height(Length::Fixed(4.0)),
+                                        widget::text(tr(language, "Clear local conversation data or open deeper configuration options."))
+                                            .size(12)
+                                            .color(text_muted()),
+                                    ]
+                                    .width(Length::Fill),
+
+                                    danger_button(
+                                        tr(language, "Clear current context"),
+                                        Message::WipeChatHistory
+                                    ),
+
+                                    Space::new().width(Length::Fixed(10.0)),
+
+                                    secondary_button(
+                                        tr(language, "Advanced settings"),
+                                        Message::ToggleAdvancedSettings
+                                    ),
+                                ]
+                            )
+                            .padding(16)
+                            .width(Length::Fill)
+                            .style(danger_zone_style),
+
+                            Space::new().height(Length::Fixed(12.0)),
+
+                            widget::text(self.debug_message.clone().message)
+                                .size(13)
+                                .color(debug_color),
+                        ]
+                    )
+                    .padding(18)
+                    .width(Length::Fill)
+                    .style(conversation_style),
+                ];
+
+                container(widget::scrollable(content).height(Length::Fill))
+                    .padding(18)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(app_background_style)
+            }
+
+            GUIState::Models => {
+                let visible_debug = self.current_debug_message().clone();
+                let model_list: Element<Message> = if let Some(error) = &self.installed_models_error
+                {
+                    widget::text(format!(
+                        "{} {error}",
+                        tr(language, "Could not list installed models:")
+                    ))
+                    .size(12)
+                    .color(danger())
+                    .into()
+                } else if self.installed_models.is_empty() {
+                    widget::text(tr(language, "No models installed"))
+                        .size(12)
+                        .color(text_faint())
+                        .into()
+                } else {
+                    widget::Column::with_children(self.installed_models.iter().map(|model| {
+                        let selected = self.selected_installed_model.as_ref() == Some(&model.name);
+                        container(
+                            widget::button(widget::column![
+                                widget::text(&model.name).size(14),
+                                Space::new().height(Length::Fixed(2.0)),
+                                widget::text(format_bytes(model.size))
+                                    .size(11)
+                                    .color(text_muted()),
+                            ])
+                            .on_press(Message::SelectInstalledModel(model.name.clone()))
+                            .padding([8, 10])
+                            .width(Length::Fill)
+                            .style(chat_title_button_style),
+                        )
+                        .padding(2)
+                        .width(Length::Fill)
+                        .style(chat_entry_style(selected))
+                        .into()
+                    }))
+                    .spacing(5)
+                    .into()
+                };
+                let selected_model = self.selected_installed_model.as_ref().and_then(|name| {
+                    self.installed_models
+                        .iter()
+                        .find(|model| &model.name == name)
+                });
+                let details: Element<Message> = match selected_model {
+                    _ if self.gguf_import_open || self.active_import.is_some() => {
+                        gguf_import_panel(self, language)
+                    }
+                    Some(model) => installed_model_details(self, model, language),
+                    None => widget::text(tr(language, "Select a model to see its details."))
+                        .size(13)
+                        .color(text_faint())
+                        .into(),
+                };
+
+                let content = widget::column![
+                    Space::new().height(Length::Fixed((1.0 - eased(self.page_reveal)) * 4.0)),
+                    container(widget::row![
+                        section_title(
+                            tr(language, "Models"),
+                            tr(
+                                language,
+                                "Inspect, copy, rename and delete installed models."
+                            )
+                        ),
+                        Space::new().width(Length::Fill),
+                        secondary_button(tr(language, "Import GGUF"), Message::ToggleGgufImport),
+                        Space::new().width(Length::Fixed(8.0)),
+                        secondary_button(
+                            tr(language, "New Modelfile"),
+                            Message::OpenModelfileEditor(None)
+                        ),
+                        Space::new().width(Length::Fixed(8.0)),
+                        secondary_button(
+                            tr(language, "Back to advanced settings"),
+                            Message::ToggleModelManager
+                        ),
+                    ])
+                    .padding(18)
+                    .width(Length::Fill)
+                    .style(top_bar_style),
+                    Space::new().height(Length::Fixed(14.0)),
+                    widget::row![
+                        container(widget::scrollable(model_list).height(Length::Fill))
+                            .padding(10)
+                            .width(Length::Fixed(260.0))
+                            .height(Length::Fill)
+                            .style(sidebar_style),
+                        Space::new().width(Length::Fixed(12.0)),
+                        container(widget::scrollable(widget::column![
+                            details,
+                            Space::new().height(Length::Fixed(12.0)),
+                            widget::text(visible_debug.message).size(13).color(
+                                if visible_debug.is_error {
+                                    danger()
+                                } else {
+                                    success()
+                                }
+                            ),
+                        ]))
+                        .padding(18)
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .style(conversation_style),
+                    ]
+                    .height(Length::Fill),
+                ];
+
+                container(content)
+                    .padding(18)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(app_background_style)
+            }
 
-                            container(
-                                widget::row![
-                                    setting_label(
-                                        tr(language, "Model conversation context"),
-                                        tr(language, "Include earlier messages from this chat in the next model request. Saved chats are managed in the left menu.")
-                                    ),
-                                    widget::checkbox(
-                                        user_information.current_chat_history_enabled
-                                    )
-                                    .label(tr(language, "Enabled"))
-                                    .on_toggle(|_| Message::ToggleChatHistory),
-                                ]
+            GUIState::Compare => {
+                let visible_debug = self.current_debug_message().clone();
+                let bots_list = self.app_state.bots_list.lock().unwrap().clone();
+                let model_chips = widget::Row::with_children(bots_list.into_iter().map(|model| {
+                    let selected = self.compare_models.contains(&model);
+                    widget::button(widget::text(model.clone()).size(12))
+                        .padding([7, 10])
+                        .style(move |_theme, status| {
+                            if selected {
+                                button_visual(panel_soft(), accent(), text_main(), status)
+                            } else {
+                                button_visual(panel_soft(), border_soft(), text_muted(), status)
+                            }
+                        })
+                        .on_press(Message::ToggleCompareModel(model))
+                        .into()
+                }))
+                .spacing(6)
+                .wrap()
+                .vertical_spacing(6);
+                let running = self
+                    .compare_columns
+                    .iter()
+                    .any(|column| self.active_prompts.contains_key(&column.chat_id));
+                let columns: Element<Message> = if self.compare_columns.is_empty() {
+                    container(
+                        widget::text(tr(
+                            language,
+                            "Answers appear here side by side, each with its own statistics.",
+                        ))
+                        .size(13)
+                        .color(text_faint()),
+                    )
+                    .center(Length::Fill)
+                    .into()
+                } else {
+                    widget::Row::with_children(
+                        self.compare_columns
+                            .iter()
+                            .map(|column| compare_column(self, column, language)),
+                    )
+                    .spacing(10)
+                    .height(Length::Fill)
+                    .into()
+                };
+
+                let content = widget::column![
+                    Space::new().height(Length::Fixed((1.0 - eased(self.page_reveal)) * 4.0)),
+                    container(widget::column![
+                        widget::row![
+                            section_title(
+                                tr(language, "Compare models"),
+                                tr(
+                                    language,
+                                    "Send one prompt to two to four models and read the answers side by side."
+                                )
+                            ),
+                            Space::new().width(Length::Fill),
+                            secondary_button(tr(language, "Back to chat"), Message::ToggleCompare),
+                        ],
+                        Space::new().height(Length::Fixed(12.0)),
+                        model_chips,
+                        Space::new().height(Length::Fixed(12.0)),
+                        widget::row![
+                            iced::widget::TextInput::<Message>::new(
+                                tr(language, "Ask every selected model the same question"),
+                                &self.compare_prompt,
                             )
-                            .padding(16)
+                            .on_input(Message::EditComparePrompt)
+                            .on_submit(Message::StartComparison)
+                            .padding(12)
                             .width(Length::Fill)
-                            .style(flat_card_style),
-
-                            Space::new().height(Length::Fixed(14.0)),
-
-                            container(
-                                widget::row![
-                                    widget::column![
-                                        widget::text(tr(language, "Maintenance"))
-                                            .size(16)
-                                            .color(text_main()),
-                                        Space::new().height(Length::Fixed(4.0)),
-                                        widget::text(tr(language, "Clear local conversation data or open deeper configuration options."))
-                                            .size(12)
-                                            .color(text_muted()),
-                                    ]
-                                    .width(Length::Fill),
+                            .style(text_input_style),
+                            Space::new().width(Length::Fixed(8.0)),
+                            if running {
+                                danger_button(tr(language, "Stop"), Message::StopComparison)
+                            } else {
+                                primary_button(tr(language, "Compare"), Message::StartComparison)
+                            },
+                        ]
+                        .align_y(iced::Alignment::Center),
+                        Space::new().height(Length::Fixed(6.0)),
+                        widget::text(visible_debug.message).size(12).color(
+                            if visible_debug.is_error {
+                                danger()
+                            } else {
+                                success()
+                            }
+                        ),
+                    ])
+                    .padding(18)
+                    .width(Length::Fill)
+                    .style(top_bar_style),
+                    Space::new().height(Length::Fixed(14.0)),
+                    columns,
+                ];
 
-                                    danger_button(
-                                        tr(language, "Clear current context"),
-                                        Message::WipeChatHistory
-                                    ),
+                container(content)
+                    .padding(18)
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .style(app_background_style)
+            }
 
-                                    Space::new().width(Length::Fixed(10.0)),
+            GUIState::ModelfileEditor => {
+                let visible_debug = self.current_debug_message().clone();
+                let validation: Element<Message> = if self.modelfile_issues.is_empty() {
+                    widget::text(tr(language, "No problems found."))
+                        .size(12)
+                        .color(success())
+                        .into()
+                } else {
+                    widget::Column::with_children(self.modelfile_issues.iter().map(|issue| {
+                        widget::text(format!(
+                            "{} {}: {}",
+                            tr(language, "Line"),
+                            issue.line,
+                            issue.message
+                        ))
+                        .size(12)
+                        .color(danger())
+                        .into()
+                    }))
+                    .spacing(4)
+                    .into()
+                };
+                let log = self.modelfile_log.lock().unwrap().clone();
+                let progress: Element<Message> = if log.is_empty() {
+                    widget::text(match &self.creating_model {
+                        Some(_) => tr(language, "Waiting for Ollama..."),
+                        None => tr(language, "Nothing submitted yet."),
+                    })
+                    .size(12)
+                    .color(text_faint())
+                    .into()
+                } else {
+                    widget::Column::with_children(log.into_iter().map(|status| {
+                        widget::text(status)
+                            .font(iced::Font::MONOSPACE)
+                            .size(12)
+                            .color(text_muted())
+                            .into()
+                    }))
+                    .spacing(3)
+                    .into()
+                };
+                let create_label = match &self.creating_model {
+                    Some(_) => tr(language, "Creating..."),
+                    None => tr(language, "Create model"),
+                };
 
-                                    secondary_button(
-                                        tr(language, "Advanced settings"),
-                                        Message::ToggleAdvancedSettings
-                                    ),
-                                ]
+                let content = widget::column![
+                    Space::new().height(Length::Fixed((1.0 - eased(self.page_reveal)) * 4.0)),
+                    container(widget::row![
+                        section_title(
+                            tr(language, "Modelfile editor"),
+                            tr(
+                                language,
+                                "Derive a model with its own SYSTEM, PARAMETER and TEMPLATE."
                             )
-                            .padding(16)
-                            .width(Length::Fill)
-                            .style(danger_zone_style),
-
-                            Space::new().height(Length::Fixed(12.0)),
-
-                            widget::text(self.debug_message.clone().message)
-                                .size(13)
-                                .color(debug_color),
-                        ]
-                    )
+                        ),
+                        Space::new().width(Length::Fill),
+                        secondary_button(
+                            tr(language, "Back to models"),
+                            Message::CloseModelfileEditor
+                        ),
+                    ])
                     .padding(18)
                     .width(Length::Fill)
-                    .style(conversation_style),
+                    .style(top_bar_style),
+                    Space::new().height(Length::Fixed(14.0)),
+                    widget::row![
+                        container(widget::column![
+                            widget::text_editor(&self.modelfile_editor)
+                                .placeholder("FROM llama3.2\nSYSTEM You are a careful reviewer.")
+                                .on_action(Message::EditModelfile)
+                                .font(iced::Font::MONOSPACE)
+                                .size(13)
+                                .padding(12)
+                                .height(Length::Fill)
+                                .style(text_editor_style),
+                            Space::new().height(Length::Fixed(10.0)),
+                            widget::row![
+                                iced::widget::TextInput::<Message>::new(
+                                    tr(language, "New model name, e.g. reviewer:latest"),
+                                    &self.new_model_name,
+                                )
+                                .on_input(Message::EditNewModelName)
+                                .on_submit(Message::CreateModelFromModelfile)
+                                .padding(10)
+                                .width(Length::Fill)
+                                .style(text_input_style),
+                                Space::new().width(Length::Fixed(8.0)),
+                                primary_button(create_label, Message::CreateModelFromModelfile),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                        ])
+                        .padding(18)
+                        .width(Length::Fill)
+                        .height(Length::Fill)
+                        .style(conversation_style),
+                        Space::new().width(Length::Fixed(12.0)),
+                        container(widget::scrollable(widget::column![
+                            widget::text(tr(language, "Validation"))
+                                .size(14)
+                                .color(text_main()),
+                            Space::new().height(Length::Fixed(8.0)),
+                            validation,
+                            Space::new().height(Length::Fixed(18.0)),
+                            widget::text(tr(language, "Progress"))
+                                .size(14)
+                                .color(text_main()),
+                            Space::new().height(Length::Fixed(8.0)),
+                            progress,
+                            Space::new().height(Length::Fixed(12.0)),
+                            widget::text(visible_debug.message).size(13).color(
+                                if visible_debug.is_error {
+                                    danger()
+                                } else {
+                                    success()
+                                }
+                            ),
+                        ]))
+                        .padding(14)
+                        .width(Length::Fixed(320.0))
+                        .height(Length::Fill)
+                        .style(sidebar_style),
+                    ]
+                    .height(Length::Fill),
                 ];
 
-                container(widget::scrollable(content).height(Length::Fill))
+                container(content)
                     .padding(18)
                     .width(Length::Fill)
                     .height(Length::Fill)
@@ -3694,7 +5792,6 @@ impl Program {
             }
 
             GUIState::AdvancedSettings => {
-                let user_information = self.user_information.clone();
                 let ip = self.user_information.ip_address.clone();
 
                 let prompts_list = self
@@ -3766,6 +5863,44 @@ impl Program {
                         .width(Length::Fill)
                         .style(flat_card_style),
                         Space::new().height(Length::Fixed(10.0)),
+                        container(widget::column![
+                            widget::row![
+                                setting_label(
+                                    tr(language, "Loaded models"),
+                                    tr(language, "Models Ollama currently holds in memory, and how long it keeps them after a request.")
+                                ),
+                                secondary_button(tr(language, "Refresh"), Message::RefreshRunningModels),
+                                Space::new().width(Length::Fixed(8.0)),
+                                secondary_button(tr(language, "Preload selected model"), Message::PreloadModel),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(12.0)),
+                            running_model_rows(
+                                &self.running_models,
+                                self.running_models_error.as_ref(),
+                                language,
+                            ),
+                            Space::new().height(Length::Fixed(12.0)),
+                            widget::row![
+                                keep_alive_field(
+                                    tr(language, "Keep alive"),
+                                    tr(language, "Ollama default, e.g. 10m or -1"),
+                                    &self.keep_alive,
+                                    Message::EditKeepAlive,
+                                ),
+                                keep_alive_field(
+                                    tr(language, "Keep alive for this chat"),
+                                    tr(language, "Use the global setting"),
+                                    self.user_information.keep_alive.as_deref().unwrap_or_default(),
+                                    Message::EditChatKeepAlive,
+                                ),
+                            ]
+                            .spacing(10),
+                        ])
+                        .padding(16)
+                        .width(Length::Fill)
+                        .style(flat_card_style),
+                        Space::new().height(Length::Fixed(10.0)),
                         container(widget::column![
                             widget::row![
                                 setting_label(
@@ -3786,11 +5921,36 @@ impl Program {
                         .style(flat_card_style),
                         Space::new().height(Length::Fixed(10.0)),
                         container(widget::column![
-                            setting_label(
-                                tr(language, "Install model"),
-                                tr(language, "Enter an Ollama model name and press Enter.")
-                            ),
+                            widget::row![
+                                setting_label(
+                                    tr(language, "Sampling"),
+                                    tr(language, "Fine-tune how tokens are chosen. Leave a field empty to use the model's own default.")
+                                ),
+                                secondary_button(
+                                    tr(language, "Reset to model defaults"),
+                                    Message::ResetSamplingToModelDefaults
+                                ),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(12.0)),
+                            sampling_fields(&self.sampling_settings, &self.sampling_inputs, language),
+                        ])
+                        .padding(16)
+                        .width(Length::Fill)
+                        .style(flat_card_style),
+                        Space::new().height(Length::Fixed(10.0)),
+                        container(widget::column![
+                            widget::row![
+                                setting_label(
+                                    tr(language, "Install model"),
+                                    tr(language, "Enter an Ollama model name and press Enter. Further models wait in a queue.")
+                                ),
+                                secondary_button(tr(language, "Manage models"), Message::ToggleModelManager),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(10.0)),
                             model_install,
+                            model_downloads(self, language),
                         ])
                         .padding(16)
                         .width(Length::Fill)
@@ -3847,11 +6007,58 @@ impl Program {
                         .style(flat_card_style),
                         Space::new().height(Length::Fixed(10.0)),
                         container(widget::column![
-                            setting_label(
-                                tr(language, "Ollama address"),
-                                tr(language, "Change the IP address and port used to connect to Ollama.")
-                            ),
+                            widget::row![
+                                setting_label(
+                                    tr(language, "Ollama address"),
+                                    tr(language, "Save the Ollama servers you use and pick which one the app talks to.")
+                                ),
+                                secondary_button(tr(language, "Add server"), Message::AddServerProfile),
+                                Space::new().width(Length::Fixed(8.0)),
+                                danger_button(tr(language, "Delete server"), Message::DeleteServerProfile),
+                            ]
+                            .align_y(iced::Alignment::Center),
                             Space::new().height(Length::Fixed(12.0)),
+                            widget::row![
+                                server_switcher(self, language),
+                                Space::new().width(Length::Fixed(8.0)),
+                                iced::widget::TextInput::<Message>::new(tr(language, "Profile name"), &ip.name)
+                                    .padding(12)
+                                    .size(15)
+                                    .width(Length::Fill)
+                                    .on_input(Message::ChangeServerName)
+                                    .style(text_input_style),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(8.0)),
+                            widget::row![
+                                widget::text(tr(language, "Server type"))
+                                    .size(13)
+                                    .color(text_muted())
+                                    .width(Length::Fixed(160.0)),
+                                widget::pick_list(
+                                    BackendKind::ALL,
+                                    Some(ip.backend),
+                                    Message::ChangeServerBackend,
+                                )
+                                .padding([10, 12])
+                                .text_size(13)
+                                .style(pick_list_style)
+                                .menu_style(pick_list_menu_style),
+                                Space::new().width(Length::Fixed(12.0)),
+                                widget::text(tr(
+                                    language,
+                                    match ip.backend {
+                                        BackendKind::Ollama => "",
+                                        BackendKind::OpenAiCompatible => {
+                                            "Chat only. Models are managed on the server itself."
+                                        }
+                                    },
+                                ))
+                                .size(12)
+                                .color(text_muted()),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(8.0)),
                             widget::row![
                                 change_ip,
                                 Space::new().width(Length::Fixed(8.0)),
@@ -3859,26 +6066,40 @@ impl Program {
                                 Space::new().width(Length::Fixed(8.0)),
                                 change_port,
                             ],
+                            Space::new().height(Length::Fixed(8.0)),
+                            widget::row![
+                                widget::checkbox(ip.scheme == "https")
+                                    .label(tr(language, "Use HTTPS"))
+                                    .on_toggle(|_| Message::ToggleServerHttps),
+                                Space::new().width(Length::Fixed(12.0)),
+                                iced::widget::TextInput::<Message>::new(
+                                    tr(language, "API key (optional)"),
+                                    ip.api_key.as_deref().unwrap_or_default(),
+                                )
+                                .secure(true)
+                                .padding(12)
+                                .size(15)
+                                .width(Length::Fill)
+                                .on_input(Message::ChangeServerApiKey)
+                                .style(text_input_style),
+                            ]
+                            .align_y(iced::Alignment::Center),
+                            Space::new().height(Length::Fixed(12.0)),
+                            server_connection_fields(&ip, language),
                             Space::new().height(Length::Fixed(12.0)),
                             container(
                                 widget::text(if language == Language::Spanish {
-                                    format!(
-                                        "Dirección actual: {}:{}",
-                                        user_information.ip_address.ip,
-                                        user_information.ip_address.port
-                                    )
+                                    format!("Dirección actual: {}", ip.base_url())
                                 } else {
-                                    format!(
-                                        "Current address: {}:{}",
-                                        user_information.ip_address.ip,
-                                        user_information.ip_address.port
-                                    )
+                                    format!("Current address: {}", ip.base_url())
                                 })
                                 .size(13)
                                 .color(text_main())
                             )
                             .padding(10)
                             .style(chip_style(accent_2())),
+                            Space::new().height(Length::Fixed(12.0)),
+                            server_health_rows(self),
                         ])
                         .padding(16)
                         .width(Length::Fill)
@@ -3901,7 +6122,8 @@ impl Program {
 
 #[cfg(test)]
 mod tests {
-    use super::ellipsize_chat_title;
+    use super::{ellipsize_chat_title, generation_stats_summary};
+    use crate::{GenerationStats, Language};
 
     #[test]
     fn chat_title_ellipsis_is_unicode_safe() {
@@ -3922,4 +6144,19 @@ mod tests {
         );
         assert_eq!(ellipsize_chat_title("anything", 0), "");
     }
+
+    #[test]
+    fn generation_stats_summary_reports_speed_and_token_counts() {
+        let stats = GenerationStats {
+            prompt_eval_count: 26,
+            eval_count: 120,
+            eval_duration: 2_000_000_000,
+            load_duration: 250_000_000,
+            ..GenerationStats::default()
+        };
+        assert_eq!(
+            generation_stats_summary(&stats, Language::English),
+            "60.0 tok/s · 26 prompt tokens · 120 output tokens · 0.25 s load"
+        );
+    }
 }
@@ -2,8 +2,10 @@
 
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::process::Command;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
@@ -13,23 +15,53 @@ use base64::{Engine as _, engine::general_purpose::STANDARD as BASE64};
 use chrono::Local;
 use iced::{Element, Point, Size, Subscription, Task, Theme, clipboard, keyboard, mouse, time};
 use iced_widget::markdown;
-use ollama_rs::Ollama;
 use ollama_rs::generation::completion::GenerationResponse;
-use ollama_rs::generation::completion::request::GenerationRequest;
-use ollama_rs::models::ModelOptions;
+use ollama_rs::models::{LocalModel, ModelOptions};
 use rustrict::{Censor, Type};
 mod app;
+mod backend;
+mod chat_export;
+mod chat_import;
+mod chat_search;
+mod cli;
+mod context_budget;
+mod control_api;
+mod environment;
+mod gguf_import;
 mod gui;
+mod model_manager;
+mod model_memory;
+mod model_pull;
+mod modelfile;
+mod sampling;
+mod server_profiles;
+mod structured_output;
 mod web_search;
 
 use crate::app::{
-    AppState, Channels, ChatImage, Correspondence, CurrentChat, DebugMessage,
-    DynamicPromptSettings, History, HostLocation, Language, Log, Prompt, SavedChat, SystemPrompt,
-    ThinkingLevel, UserInformation,
+    AppState, Channels, ChatImage, ChatSettings, Correspondence, CurrentChat, DebugMessage,
+    DynamicPromptSettings, GenerationStats, History, HostLocation, Language, Log, Prompt,
+    SavedChat, SystemPrompt, ThinkingLevel, UserInformation,
 };
+use crate::backend::BackendKind;
+use crate::chat_export::{ChatDocument, ExportFormat, ExportScope};
+use crate::chat_import::ImportPreview;
+use crate::chat_search::{SearchHit, SearchIndex};
+use crate::context_budget::ContextSummary;
+use crate::control_api::{
+    ControlApiSettings, ControlCommand, ControlReply, ControlRequest, ResponseStream,
+};
+use crate::environment::EnvironmentOverrides;
+use crate::gguf_import::{GgufImport, ImportProgress};
+use crate::model_manager::{ModelDetails, ModelText};
+use crate::model_memory::LoadedModel;
+use crate::model_pull::{PullOutcome, PullProgress};
+use crate::modelfile::{Modelfile, ModelfileIssue};
+use crate::sampling::{SamplingOption, SamplingSettings};
+use crate::structured_output::ResponseFormat;
 use crate::web_search::{
-    BraveSearchProvider, ToolLoopRequest, WebSearchProviderKind, WebSearchSettings, WebSearchState,
-    run_tool_loop,
+    BraveSearchProvider, StreamToken, ToolLoopRequest, WebSearchProviderKind, WebSearchSettings,
+    WebSearchState, run_tool_loop, user_message,
 };
 
 /// Tick points:
@@ -37,6 +69,8 @@ use crate::web_search::{
 const VERSION_TICK: i32 = 2;
 const MAX_TICK: i32 = 59;
 const BOT_LIST_TICK: i32 = 3;
+/// Loaded models are only polled while Advanced Settings shows them.
+const RUNNING_MODELS_TICK: i32 = 30;
 const TICK_MS: u64 = 200;
 /// Live output and indeterminate progress need a frame-oriented cadence. Keeping
 /// this separate from housekeeping avoids making disk/network polling run at
@@ -45,6 +79,9 @@ const UI_FRAME_MS: u64 = 33;
 const MAX_MARKDOWN_IMAGE_BYTES: usize = 12 * 1024 * 1024;
 const MAX_MARKDOWN_IMAGE_PIXELS: u64 = 32_000_000;
 const SETTINGS_SAVE_DEBOUNCE_MS: u64 = 450;
+/// Attachments are stored beside `chats.json` in this subfolder.
+const CHAT_IMAGE_DIR: &str = "images";
+const OLLAMA_ONLY: &str = "Model management is only available for Ollama servers.";
 const DEFAULT_MAX_RESPONSE_TOKENS: u32 = 32_768;
 const DEFAULT_CONTEXT_TOKENS: u32 = 131_072;
 const MIN_RESPONSE_TOKENS: u32 = 512;
@@ -57,6 +94,7 @@ const MAX_SIDEBAR_WIDTH: f32 = 460.0;
 const DEFAULT_COMPOSER_HEIGHT: f32 = 142.0;
 const MIN_COMPOSER_HEIGHT: f32 = 118.0;
 const MAX_COMPOSER_HEIGHT: f32 = 320.0;
+const MAX_COMPARE_MODELS: usize = 4;
 
 const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
 
@@ -66,6 +104,9 @@ pub enum GUIState {
     Main,
     Settings,
     AdvancedSettings,
+    Models,
+    ModelfileEditor,
+    Compare,
     Images,
 }
 
@@ -131,6 +172,17 @@ enum Message {
     ToggleTemporaryChat,
     ChooseChatFolder,
     ChatFolderSelected(Option<PathBuf>),
+    ExportChats(ExportScope),
+    ChatsExported(Result<Option<PathBuf>, String>),
+    ImportChats,
+    ChatImportLoaded(Result<Option<ImportPreview>, String>),
+    ToggleImportedChat(usize),
+    SelectAllImportedChats(bool),
+    ConfirmChatImport,
+    CancelChatImport,
+    ChatSearchChanged(String),
+    ChatSearchIndexed(SearchIndex),
+    OpenSearchResult(String, Option<usize>),
     AsyncResult(()),
     PromptFinished(String),
     ListPrompt,
@@ -150,6 +202,58 @@ enum Message {
     ImageGenerated(Result<String, String>),
     CopyImage(String),
     ModelCapabilitiesKnown(String, Option<ModelCapabilities>),
+    EditSamplingOption(SamplingOption, String),
+    ResetSamplingToModelDefaults,
+    ModelParametersLoaded(Result<String, String>),
+    ToggleStructuredOutput,
+    EditResponseFormat(iced::widget::text_editor::Action),
+    UseJsonFormat,
+    ClearResponseFormat,
+    LoadResponseFormat,
+    ResponseFormatLoaded(Option<Result<String, String>>),
+    ToggleJsonNode(usize, String),
+    RefreshRunningModels,
+    RunningModelsLoaded(Result<Vec<LoadedModel>, String>),
+    UnloadModel(String),
+    PreloadModel,
+    ModelResidencyChanged(Result<String, String>),
+    EditKeepAlive(String),
+    EditChatKeepAlive(String),
+    ToggleModelManager,
+    InstalledModelsLoaded(Result<Vec<LocalModel>, String>),
+    SelectInstalledModel(String),
+    ModelDetailsLoaded(String, Result<ModelDetails, String>),
+    ShowModelText(ModelText),
+    EditModelCopyName(String),
+    CopyInstalledModel,
+    RenameInstalledModel,
+    DeleteInstalledModel(String),
+    InstalledModelChanged(Result<String, String>),
+    OpenModelfileEditor(Option<String>),
+    ModelfileSourceLoaded(String, Result<ModelDetails, String>),
+    CloseModelfileEditor,
+    EditModelfile(iced::widget::text_editor::Action),
+    EditNewModelName(String),
+    CreateModelFromModelfile,
+    ModelCreated(String, Result<(), String>),
+    ToggleGgufImport,
+    ToggleCompare,
+    ToggleCompareModel(String),
+    EditComparePrompt(String),
+    StartComparison,
+    StopComparison,
+    ContinueWithComparison(String),
+    PickGgufFile,
+    GgufFilePicked(Option<PathBuf>),
+    EditGgufModelName(String),
+    EditGgufTemplate(iced::widget::text_editor::Action),
+    EditGgufSystem(String),
+    ImportGguf,
+    CancelGgufImport,
+    GgufImportFinished(String, Result<PullOutcome, String>),
+    CancelPull,
+    RemoveQueuedPull(usize),
+    PullFinished(String, Result<PullOutcome, String>),
     ToggleSettings,
     SystemPromptChange(String),
     Prompt(String),
@@ -168,6 +272,13 @@ enum Message {
     CopyPressed(String),
     CopyLatestResponse,
     ToggleThinking(usize),
+    RegenerateResponse(usize),
+    SelectResponseVariant(usize, usize),
+    StartEditTurn(usize),
+    EditTurnAction(iced::widget::text_editor::Action),
+    SubmitEditedTurn,
+    CancelEditTurn,
+    SelectBranch(usize, usize),
     UpdateTextSize(f32),
     InstallationPrompt,
     ModelChange(String),
@@ -197,6 +308,27 @@ enum Message {
     ToggleAdvancedSettings,
     ChangeIp(String),
     ChangePort(String),
+    ChangeServerName(String),
+    ToggleServerHttps,
+    ChangeServerBackend(BackendKind),
+    ControlApi(Result<ControlRequest, String>),
+    ToggleControlApi,
+    EditControlApiPort(String),
+    NewControlApiToken,
+    ChangeServerApiKey(String),
+    SwitchServerProfile(server_profiles::ProfileChoice),
+    AddServerProfile,
+    DeleteServerProfile,
+    PickServerCertificate(server_profiles::CertificateKind),
+    ServerCertificatePicked(server_profiles::CertificateKind, Option<PathBuf>),
+    ClearServerCertificate(server_profiles::CertificateKind),
+    AddServerHeader,
+    EditServerHeaderName(usize, String),
+    EditServerHeaderValue(usize, String),
+    RemoveServerHeader(usize),
+    /// Health of every server profile, tagged with the profile generation
+    /// the check started in.
+    ServerHealthChecked(u64, Vec<String>),
 }
 
 struct ActivePrompt {
@@ -214,6 +346,19 @@ struct ActivePrompt {
     had_image: bool,
     web_search_enabled: bool,
     temporary: bool,
+    /// The answer being regenerated; its variants are carried onto the new one.
+    replaced_response: Option<Correspondence>,
+    /// Settings the prompt was sent with, saved with the chat when it finishes.
+    settings: ChatSettings,
+}
+
+#[derive(Clone, Copy)]
+enum PromptOrigin {
+    Composer,
+    /// Re-answer the user turn at this index.
+    Regenerate(usize),
+    /// Fork a branch that replaces the user turn at this index.
+    Edit(usize),
 }
 
 struct LiveRender {
@@ -222,9 +367,34 @@ struct LiveRender {
     markdown: Vec<markdown::Item>,
 }
 
+/// The model download in progress. The view reads `progress` every frame.
+struct ActivePull {
+    model: String,
+    progress: Arc<Mutex<PullProgress>>,
+    cancel: Arc<AtomicBool>,
+}
+
+struct ActiveImport {
+    model: String,
+    progress: Arc<Mutex<ImportProgress>>,
+    /// Statuses streamed back by `/api/create` once the upload is done.
+    log: Arc<Mutex<Vec<String>>>,
+    cancel: Arc<AtomicBool>,
+}
+
+/// One model's answer in the compare workspace. It runs as a temporary chat
+/// that stays out of the sidebar until it is continued.
+struct CompareColumn {
+    model: String,
+    chat_id: String,
+    /// The finished answer, parsed once it arrives.
+    markdown: Vec<markdown::Item>,
+}
+
 struct TemporaryChatSession {
     chat_history: Arc<Mutex<CurrentChat>>,
     web_search_enabled: bool,
+    settings: ChatSettings,
 }
 
 struct VisionResponse {
@@ -246,6 +416,9 @@ struct Program {
     chat_notice_receiver: crossbeam_channel::Receiver<(String, DebugMessage)>,
     current_tick: i32,
     installing_model: String,
+    active_pull: Option<ActivePull>,
+    /// Models waiting to be pulled after the active one, in order.
+    pull_queue: VecDeque<String>,
 
     debug_message: DebugMessage,
     debug_message_set_at: Option<Instant>,
@@ -260,6 +433,8 @@ struct Program {
     chat_messages_cache: Vec<Correspondence>,
     chat_thinking_cache: Vec<String>,
     chat_visible_text_cache: Vec<String>,
+    /// First message still sent verbatim when older ones are summarised.
+    chat_summary_boundary: Option<usize>,
 
     /// One model label per chat message.
     /// User messages use None. Bot messages store the model that generated them.
@@ -277,6 +452,10 @@ struct Program {
 
     /// Message indexes whose reasoning disclosure is open. Reasoning is hidden by default.
     expanded_thinking: HashSet<usize>,
+    /// JSON-tree nodes folded in structured responses, by message index and path.
+    collapsed_json_nodes: HashSet<(usize, String)>,
+    structured_output_open: bool,
+    response_format_editor: iced::widget::text_editor::Content,
 
     system_prompt: SystemPrompt,
     app_state: AppState,
@@ -299,14 +478,74 @@ struct Program {
     temporary_chat: bool,
     web_search_settings: WebSearchSettings,
     web_search_for_chat: bool,
+    /// Model and sampling settings picked last, used for new chats.
+    chat_defaults: ChatSettings,
     current_chat_id: String,
     open_chat_dirty: bool,
+    /// The user turn being rewritten in place, with its draft text.
+    editing_message: Option<(usize, iced::widget::text_editor::Content)>,
     saved_chats: Vec<SavedChat>,
     chat_storage_dir: PathBuf,
     code_checking_enabled: bool,
     dynamic_prompt_settings: DynamicPromptSettings,
+    sampling_settings: SamplingSettings,
+    /// Text typed into each sampling field, kept while it does not parse.
+    sampling_inputs: HashMap<SamplingOption, String>,
     max_response_tokens_input: String,
     context_tokens_input: String,
+    /// Global keep-alive text sent with every request unless the chat overrides it.
+    keep_alive: String,
+    running_models: Vec<LoadedModel>,
+    running_models_error: Option<String>,
+    installed_models: Vec<LocalModel>,
+    installed_models_error: Option<String>,
+    selected_installed_model: Option<String>,
+    /// `/api/show` results by model name, cleared whenever a model changes.
+    model_details: HashMap<String, Result<ModelDetails, String>>,
+    model_text: ModelText,
+    model_copy_name: String,
+    /// A model whose Delete button was pressed once and awaits confirmation.
+    pending_model_delete: Option<String>,
+    modelfile_editor: iced::widget::text_editor::Content,
+    new_model_name: String,
+    modelfile_issues: Vec<ModelfileIssue>,
+    /// Statuses streamed back by `/api/create` for the model being created.
+    modelfile_log: Arc<Mutex<Vec<String>>>,
+    creating_model: Option<String>,
+    gguf_import_open: bool,
+    compare_models: Vec<String>,
+    compare_prompt: String,
+    compare_columns: Vec<CompareColumn>,
+    gguf_path: Option<PathBuf>,
+    gguf_model_name: String,
+    gguf_template: iced::widget::text_editor::Content,
+    gguf_system: String,
+    active_import: Option<ActiveImport>,
+    /// Saved Ollama servers. The active one is edited through
+    /// `user_information.ip_address` and written back on every change.
+    server_profiles: Vec<HostLocation>,
+    active_server_profile: usize,
+    /// Last `/api/version` result for each profile, by position.
+    server_health: Vec<String>,
+    /// Bumped whenever the active profile or the profile list changes, so a
+    /// health check that started before is dropped instead of misapplied.
+    server_profiles_generation: u64,
+    /// Startup values that came from environment variables.
+    environment: EnvironmentOverrides,
+    control_api: ControlApiSettings,
+    control_api_port_input: String,
+    /// API clients waiting for the answer in a chat.
+    control_streams: Vec<ResponseStream>,
+    /// An export that was read and waits for the user to choose chats.
+    chat_import: Option<ImportPreview>,
+    chat_search_query: String,
+    chat_search_index: SearchIndex,
+    /// Set while the first index is built in the background.
+    chat_search_indexing: bool,
+    chat_search_results: Vec<SearchHit>,
+    /// The message a search result pointed at, highlighted while its chat
+    /// stays open.
+    search_highlight: Option<(String, usize)>,
     pending_settings: serde_json::Map<String, serde_json::Value>,
     settings_dirty_at: Option<Instant>,
 }
@@ -322,6 +561,9 @@ fn app_data_dir() -> PathBuf {
 
 #[cfg(not(test))]
 fn app_data_dir() -> PathBuf {
+    if let Some(directory) = environment::data_dir() {
+        return directory;
+    }
     #[cfg(target_os = "windows")]
     if let Some(base) = std::env::var_os("LOCALAPPDATA") {
         return PathBuf::from(base).join("Ollama GUI");
@@ -518,18 +760,24 @@ fn generated_image_payload(body: &serde_json::Value) -> Option<&str> {
 }
 
 async fn generate_image_via_ollama(
-    host: String,
+    host: HostLocation,
     model: String,
     prompt: String,
+    keep_alive: Option<serde_json::Value>,
 ) -> Result<String, String> {
-    let response = reqwest::Client::new()
-        .post(format!("{host}/v1/images/generations"))
-        .json(&serde_json::json!({
-            "model": model,
-            "prompt": prompt,
-            "size": "1024x1024",
-            "response_format": "b64_json"
-        }))
+    let mut body = serde_json::json!({
+        "model": model,
+        "prompt": prompt,
+        "size": "1024x1024",
+        "response_format": "b64_json"
+    });
+    if let Some(keep_alive) = keep_alive {
+        body["keep_alive"] = keep_alive;
+    }
+    let response = host
+        .client()?
+        .post(format!("{}/v1/images/generations", host.base_url()))
+        .json(&body)
         .send()
         .await
         .map_err(|error| format!("Could not reach Ollama: {error}"))?;
@@ -839,16 +1087,136 @@ fn parse_markdown_items(input: &str) -> Vec<markdown::Item> {
     markdown::parse(&normalized).collect()
 }
 
-fn decode_generation_line(
-    input: &str,
-) -> Result<(GenerationResponse, Option<String>), serde_json::Error> {
-    let value = serde_json::from_str::<serde_json::Value>(input)?;
+/// The streaming half of a prompt, run by an iced task or by headless mode.
+type PromptGeneration = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+type DecodedLine = (GenerationResponse, Option<String>, Option<GenerationStats>);
+
+/// Decodes one streamed chat line into its token, `done_reason` and, on the
+/// final line, the generation statistics.
+fn decode_generation_value(mut value: serde_json::Value) -> Result<DecodedLine, serde_json::Error> {
     let done_reason = value
         .get("done_reason")
         .and_then(serde_json::Value::as_str)
         .map(str::to_string);
+    let stats = GenerationStats::from_response(&value);
+    // `/api/chat` nests streamed text under `message`. Lift it into the
+    // `/api/generate` shape so both endpoints decode to the same token.
+    if let Some(message) = value.get("message").cloned() {
+        value["response"] = message
+            .get("content")
+            .cloned()
+            .unwrap_or_else(|| serde_json::Value::String(String::new()));
+        if let Some(thinking) = message.get("thinking") {
+            value["thinking"] = thinking.clone();
+        }
+    }
     let response = serde_json::from_value(value)?;
-    Ok((response, done_reason))
+    Ok((response, done_reason, stats))
+}
+
+fn sampling_inputs(settings: &SamplingSettings) -> HashMap<SamplingOption, String> {
+    SamplingOption::ALL
+        .into_iter()
+        .map(|option| (option, settings.text(option)))
+        .collect()
+}
+
+/// Rebuilds the role-tagged `/api/chat` history from the visible transcript.
+/// Reasoning is omitted from assistant turns; chat templates expect only the
+/// answer, and replaying thinking wastes context.
+fn chat_history_messages(messages: &[Correspondence]) -> Vec<serde_json::Value> {
+    messages
+        .iter()
+        .map(|message| match message {
+            Correspondence::User { text, images, .. } => user_message(
+                text.clone(),
+                images
+                    .iter()
+                    .map(|image| BASE64.encode(&image.bytes))
+                    .collect(),
+            ),
+            Correspondence::Bot { text, .. } => serde_json::json!({
+                "role": "assistant",
+                "content": split_thinking_text(text).1,
+            }),
+        })
+        .collect()
+}
+
+/// Trims `history` to the context window, replacing the oldest turns with a
+/// summary. The summary is cached on the chat so later prompts only fold in
+/// turns that have newly fallen out of the window.
+async fn fit_history_to_context(
+    user_info: &UserInformation,
+    system_prompt: &str,
+    mut history: Vec<serde_json::Value>,
+    new_turn: &serde_json::Value,
+    notify: impl Fn(DebugMessage),
+) -> Vec<serde_json::Value> {
+    let plan = context_budget::plan_history(
+        system_prompt,
+        &history,
+        new_turn,
+        user_info.context_tokens,
+        user_info.max_response_tokens,
+    );
+    if plan.dropped == 0 {
+        return history;
+    }
+
+    let cached = user_info
+        .chat_history
+        .lock()
+        .unwrap()
+        .context_summary
+        .clone()
+        .filter(|summary| summary.covers <= plan.dropped);
+    let summary = match cached {
+        Some(summary) if summary.covers == plan.dropped => Some(summary.text),
+        cached => {
+            let start = cached.as_ref().map_or(0, |summary| summary.covers);
+            let result = context_budget::summarise_history(
+                backend::for_host(&user_info.ip_address).as_ref(),
+                user_info.model.as_deref().unwrap_or_default(),
+                cached.as_ref().map(|summary| summary.text.as_str()),
+                &history[start..plan.dropped],
+                plan.summary_tokens,
+                user_info.context_tokens,
+            )
+            .await;
+            match result {
+                Ok(text) => {
+                    user_info.chat_history.lock().unwrap().context_summary = Some(ContextSummary {
+                        covers: plan.dropped,
+                        text: text.clone(),
+                    });
+                    notify(DebugMessage {
+                        message: "Older messages were summarised to fit the context window."
+                            .to_string(),
+                        is_error: false,
+                    });
+                    Some(text)
+                }
+                Err(error) => {
+                    notify(DebugMessage {
+                        message: format!(
+                            "Could not summarise older messages, so they were left out: {error}"
+                        ),
+                        is_error: true,
+                    });
+                    cached.map(|summary| summary.text)
+                }
+            }
+        }
+    };
+
+    let kept = history.split_off(plan.dropped);
+    summary
+        .map(|summary| context_budget::summary_message(&summary))
+        .into_iter()
+        .chain(kept)
+        .collect()
 }
 
 fn disabled_web_tool_message(input: &str) -> Option<&'static str> {
@@ -975,23 +1343,35 @@ async fn load_markdown_image(url: String) -> Result<iced::widget::image::Handle,
     decoded_image_handle(&bytes)
 }
 
-fn convert_port_to_u16(port: String) -> u16 {
-    match port.parse::<u16>() {
-        Ok(p) => p,
-        Err(_) => {
-            eprintln!("Invalid port number: {}", port);
-            11434
-        }
-    }
-}
-
 fn load_chat_image(path: &Path) -> Result<ChatImage, String> {
     let bytes = fs::read(path).map_err(|error| format!("Could not read image: {error}"))?;
+    let name = path
+        .file_name()
+        .and_then(|value| value.to_str())
+        .unwrap_or("image")
+        .to_string();
+    chat_image_from_bytes(name, bytes)
+}
+
+/// Builds an attachment, taking its type from the file name's extension.
+/// The sidebar title of a chat: the start of its first user message.
+fn chat_title(messages: &[Correspondence]) -> String {
+    messages
+        .iter()
+        .find_map(|message| match message {
+            Correspondence::User { text, .. } => Some(text.trim().chars().take(42).collect()),
+            _ => None,
+        })
+        .filter(|title: &String| !title.is_empty())
+        .unwrap_or_else(|| "New chat".into())
+}
+
+fn chat_image_from_bytes(name: String, bytes: Vec<u8>) -> Result<ChatImage, String> {
     if bytes.len() > 20 * 1024 * 1024 {
         return Err("Images must be smaller than 20 MB.".to_string());
     }
     let preview_handle = decoded_image_handle(&bytes)?;
-    let mime_type = match path
+    let mime_type = match Path::new(&name)
         .extension()
         .and_then(|value| value.to_str())
         .unwrap_or("")
@@ -1003,16 +1383,53 @@ fn load_chat_image(path: &Path) -> Result<ChatImage, String> {
         "gif" => "image/gif",
         _ => "image/png",
     };
-    Ok(ChatImage {
-        name: path
-            .file_name()
-            .and_then(|value| value.to_str())
-            .unwrap_or("image")
-            .to_string(),
-        mime_type: mime_type.to_string(),
+    Ok(ChatImage::new(
+        name,
+        mime_type.to_string(),
         bytes,
         preview_handle,
-    })
+    ))
+}
+
+/// Writes attachments from every branch of a chat into the content-addressed
+/// image folder. Files that already exist are left untouched.
+fn store_chat_images(image_dir: &Path, messages: &[Correspondence]) -> std::io::Result<()> {
+    for message in messages {
+        let Correspondence::User {
+            images, branches, ..
+        } = message
+        else {
+            continue;
+        };
+        for image in images {
+            let path = image_dir.join(&image.digest);
+            if !path.exists() {
+                fs::create_dir_all(image_dir)?;
+                fs::write(path, &image.bytes)?;
+            }
+        }
+        for branch in branches {
+            store_chat_images(image_dir, branch)?;
+        }
+    }
+    Ok(())
+}
+
+/// Deletes stored attachments that no saved chat refers to any more.
+fn remove_unreferenced_images(image_dir: &Path, saved_chats: &[SavedChat]) {
+    let mut referenced = HashSet::new();
+    for chat in saved_chats {
+        chat.thread.collect_image_digests(&mut referenced);
+    }
+    let Ok(entries) = fs::read_dir(image_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        if !referenced.contains(name.to_string_lossy().as_ref()) {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
 }
 
 fn paste_chat_image() -> Result<ChatImage, String> {
@@ -1035,12 +1452,12 @@ fn paste_chat_image() -> Result<ChatImage, String> {
         )
         .map_err(|error| format!("Could not prepare clipboard image: {error}"))?;
     let preview_handle = decoded_image_handle(&bytes)?;
-    Ok(ChatImage {
-        name: "Pasted image.png".to_string(),
-        mime_type: "image/png".to_string(),
+    Ok(ChatImage::new(
+        "Pasted image.png".to_string(),
+        "image/png".to_string(),
         bytes,
         preview_handle,
-    })
+    ))
 }
 
 fn copy_image_file(path: &str) -> Result<(), String> {
@@ -1088,13 +1505,7 @@ fn append_failed_response(
     chat_history
         .lock()
         .unwrap()
-        .push_message(Correspondence::Bot {
-            text: message,
-            model,
-            thinking_seconds: None,
-            sources: Vec::new(),
-            web_search_used: false,
-        });
+        .push_message(Correspondence::bot(message, model));
 }
 
 async fn wait_until_cancelled(cancel: &AtomicBool) {
@@ -1209,6 +1620,7 @@ impl Program {
             chats: vec![],
             messages: vec![],
             bot_responding: false,
+            context_summary: None,
         }));
         self.chat_messages_cache.clear();
         self.chat_thinking_cache.clear();
@@ -1218,6 +1630,9 @@ impl Program {
         self.last_copied_text = None;
         self.last_copied_at = None;
         self.expanded_thinking.clear();
+        self.collapsed_json_nodes.clear();
+        self.editing_message = None;
+        self.search_highlight = None;
         self.open_chat_dirty = false;
     }
 
@@ -1230,52 +1645,315 @@ impl Program {
             self.current_chat_id.clone(),
             &chat,
             self.web_search_for_chat,
+            self.current_chat_settings(),
         );
         self.open_chat_dirty = false;
     }
 
-    fn save_chat_snapshot(&mut self, id: String, chat: &CurrentChat, web_search_enabled: bool) {
+    fn save_chat_snapshot(
+        &mut self,
+        id: String,
+        chat: &CurrentChat,
+        web_search_enabled: bool,
+        settings: ChatSettings,
+    ) {
         if chat.messages.is_empty() {
             return;
         }
-        let title = chat
+        let title = chat_title(&chat.messages);
+        if let Err(error) = store_chat_images(&self.chat_image_dir(), &chat.messages) {
+            self.set_debug_message(DebugMessage {
+                message: format!("Could not save chat images: {error}"),
+                is_error: true,
+            });
+        }
+        let mut saved = SavedChat::from_current(id, title, chat, web_search_enabled, settings);
+        let dropped_images =
+            if let Some(existing) = self.saved_chats.iter_mut().find(|item| item.id == saved.id) {
+                saved.pinned = existing.pinned;
+                let mut old_images = HashSet::new();
+                existing.thread.collect_image_digests(&mut old_images);
+                let mut new_images = HashSet::new();
+                saved.thread.collect_image_digests(&mut new_images);
+                *existing = saved;
+                !old_images.is_subset(&new_images)
+            } else {
+                // New chats appear after the pinned section. Updating or opening an
+                // existing chat deliberately leaves it at its current position.
+                let insert_at = self
+                    .saved_chats
+                    .iter()
+                    .position(|chat| !chat.pinned)
+                    .unwrap_or(self.saved_chats.len());
+                self.saved_chats.insert(insert_at, saved);
+                false
+            };
+        // An edit can leave attachments that only the old version used.
+        if self.persist_saved_chats() && dropped_images {
+            remove_unreferenced_images(&self.chat_image_dir(), &self.saved_chats);
+        }
+    }
+
+    /// The open chat as it is on screen, including unsaved and temporary
+    /// chats.
+    fn open_chat_document(&self) -> ChatDocument {
+        let messages = self
+            .user_information
+            .chat_history
+            .lock()
+            .unwrap()
             .messages
+            .clone();
+        let saved = self
+            .saved_chats
             .iter()
-            .find_map(|message| match message {
-                Correspondence::User { text, .. } => Some(text.trim().chars().take(42).collect()),
-                _ => None,
+            .find(|chat| chat.id == self.current_chat_id);
+        ChatDocument {
+            id: self.current_chat_id.clone(),
+            title: saved.map_or_else(|| chat_title(&messages), |chat| chat.title.clone()),
+            updated_at: saved
+                .map_or_else(|| Local::now().to_rfc3339(), |chat| chat.updated_at.clone()),
+            pinned: saved.is_some_and(|chat| chat.pinned),
+            messages,
+        }
+    }
+
+    /// Adds the ticked chats from an import as new saved chats. Every chat gets
+    /// a fresh id, so importing the same file twice never overwrites a chat.
+    fn merge_imported_chats(&mut self, preview: ImportPreview) {
+        let image_dir = self.chat_image_dir();
+        let id_prefix = Self::new_chat_id();
+        let mut imported = Vec::new();
+        for (index, chat) in preview
+            .chats
+            .into_iter()
+            .filter(|chat| chat.selected)
+            .enumerate()
+        {
+            if let Err(error) = store_chat_images(&image_dir, &chat.messages) {
+                self.set_debug_message(DebugMessage {
+                    message: format!("Could not save chat images: {error}"),
+                    is_error: true,
+                });
+                // Drop what the chats stored so far wrote before giving up.
+                remove_unreferenced_images(&image_dir, &self.saved_chats);
+                return;
+            }
+            let current = CurrentChat {
+                chats: vec![],
+                messages: chat.messages,
+                bot_responding: false,
+                context_summary: None,
+            };
+            let mut saved = SavedChat::from_current(
+                format!("{id_prefix}-{}", index + 1),
+                chat.title,
+                &current,
+                false,
+                ChatSettings::default(),
+            );
+            saved.updated_at = chat.updated_at;
+            saved.pinned = chat.pinned;
+            saved.web_search_enabled = None;
+            imported.push(saved);
+        }
+        if imported.is_empty() {
+            return;
+        }
+        let count = imported.len();
+        // Newest first, pinned imports at the end of the pinned section and
+        // the rest ahead of the existing unpinned chats.
+        imported.sort_by_cached_key(|chat| {
+            std::cmp::Reverse(chrono::DateTime::parse_from_rfc3339(&chat.updated_at).ok())
+        });
+        let (pinned, unpinned): (Vec<_>, Vec<_>) =
+            imported.into_iter().partition(|chat| chat.pinned);
+        let insert_at = self
+            .saved_chats
+            .iter()
+            .position(|chat| !chat.pinned)
+            .unwrap_or(self.saved_chats.len());
+        self.saved_chats
+            .splice(insert_at..insert_at, pinned.into_iter().chain(unpinned));
+        if self.persist_saved_chats() {
+            remove_unreferenced_images(&image_dir, &self.saved_chats);
+        }
+        self.set_debug_message(DebugMessage {
+            message: format!(
+                "Imported {count} chat{} from {}.",
+                if count == 1 { "" } else { "s" },
+                preview.source
+            ),
+            is_error: false,
+        });
+    }
+
+    /// Re-runs the sidebar search. The index only re-reads chats that changed
+    /// since the last call, so this is cheap enough for every keystroke.
+    fn refresh_chat_search(&mut self) {
+        if self.chat_search_query.trim().is_empty() {
+            self.chat_search_results.clear();
+            return;
+        }
+        if self.chat_search_indexing {
+            return;
+        }
+        self.chat_search_index.sync(&self.saved_chats);
+        self.chat_search_results =
+            self.chat_search_index
+                .search(&self.saved_chats, &self.chat_search_query, 100);
+    }
+
+    /// Scrolls the conversation so the message at `index` comes into view.
+    /// Heights are estimated from text length; the conversation is anchored
+    /// to the bottom, so offsets count from the end.
+    fn scroll_to_message(&self, index: usize) -> Task<Message> {
+        let heights = self
+            .user_information
+            .chat_history
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .map(|message| match message {
+                Correspondence::User { text, .. } => 2.0 + text.len() as f32 / 90.0,
+                Correspondence::Bot { text, .. } => {
+                    2.0 + split_thinking_text(text).1.len() as f32 / 90.0
+                }
             })
-            .filter(|title: &String| !title.is_empty())
-            .unwrap_or_else(|| "New chat".into());
-        let mut saved = SavedChat::from_current(id, title, chat, web_search_enabled);
-        if let Some(existing) = self.saved_chats.iter_mut().find(|item| item.id == saved.id) {
-            saved.pinned = existing.pinned;
-            *existing = saved;
-        } else {
-            // New chats appear after the pinned section. Updating or opening an
-            // existing chat deliberately leaves it at its current position.
-            let insert_at = self
-                .saved_chats
-                .iter()
-                .position(|chat| !chat.pinned)
-                .unwrap_or(self.saved_chats.len());
-            self.saved_chats.insert(insert_at, saved);
+            .collect::<Vec<_>>();
+        let total = heights.iter().sum::<f32>();
+        if total <= 0.0 || index >= heights.len() {
+            return Task::none();
         }
-        self.persist_saved_chats();
+        let above = heights[..index].iter().sum::<f32>();
+        let below = total - above;
+        iced::widget::operation::snap_to(
+            gui::CONVERSATION_SCROLL_ID,
+            iced::widget::operation::RelativeOffset {
+                x: 0.0,
+                y: (below / total).clamp(0.0, 1.0),
+            },
+        )
+    }
+
+    fn chat_image_dir(&self) -> PathBuf {
+        self.chat_storage_dir.join(CHAT_IMAGE_DIR)
     }
 
-    fn persist_saved_chats(&mut self) {
-        if let Err(error) = fs::create_dir_all(&self.chat_storage_dir).and_then(|_| {
+    /// Writes `chats.json` and returns whether that worked. Stored images are
+    /// only collected after a successful write, since the file on disk may
+    /// still refer to them otherwise.
+    fn persist_saved_chats(&mut self) -> bool {
+        let result = fs::create_dir_all(&self.chat_storage_dir).and_then(|_| {
             fs::write(
                 self.chat_storage_dir.join("chats.json"),
                 serde_json::to_string_pretty(&self.saved_chats).unwrap_or_else(|_| "[]".into()),
             )
-        }) {
+        });
+        if let Err(error) = &result {
             self.set_debug_message(DebugMessage {
                 message: format!("Could not update saved chats: {error}"),
                 is_error: true,
             });
         }
+        self.refresh_chat_search();
+        result.is_ok()
+    }
+
+    /// Switches the active model and asks Ollama what it can do.
+    fn select_model(&mut self, model: String) -> Task<Message> {
+        self.user_information.model = Some(model.clone());
+
+        self.user_information.thinking_supported = None;
+        self.user_information.vision_supported = None;
+        self.user_information.image_generation_supported = None;
+        self.user_information.thinking_levels = vec![ThinkingLevel::Off];
+        // Reasoning support and accepted effort values vary by model. Do not carry an
+        // effort setting across models while capability detection is still in flight.
+        self.user_information.thinking_level = ThinkingLevel::Off;
+        let ip = self.user_information.ip_address.clone();
+        Task::perform(
+            async move {
+                let capabilities = backend::for_host(&ip)
+                    .model_info(&model)
+                    .await
+                    .and_then(|json| model_capabilities(&json));
+                (model, capabilities)
+            },
+            |(model, capabilities)| Message::ModelCapabilitiesKnown(model, capabilities),
+        )
+    }
+
+    fn current_chat_settings(&self) -> ChatSettings {
+        ChatSettings::capture(&self.user_information, &self.system_prompt)
+    }
+
+    /// Records a model or sampling change on the open chat. The picked values
+    /// also become the defaults for new chats, mirroring the global pickers.
+    fn remember_chat_settings(&mut self) {
+        let settings = self.current_chat_settings();
+        self.chat_defaults = ChatSettings {
+            response_format: None,
+            keep_alive: None,
+            ..settings.clone()
+        };
+        if self.temporary_chat {
+            if let Some(session) = self.temporary_chats.get_mut(&self.current_chat_id) {
+                session.settings = settings;
+            }
+            return;
+        }
+
+        if let Some(chat) = self
+            .saved_chats
+            .iter_mut()
+            .find(|chat| chat.id == self.current_chat_id)
+            && chat.settings != settings
+        {
+            chat.settings = settings;
+            self.persist_saved_chats();
+        }
+    }
+
+    /// Restores the settings a chat was last used with, switching models
+    /// through the usual capability lookup.
+    fn apply_chat_settings(&mut self, settings: &ChatSettings) -> Task<Message> {
+        settings.apply(&mut self.user_information, &mut self.system_prompt);
+        let task = match &settings.model {
+            Some(model) if self.user_information.model.as_ref() != Some(model) => {
+                self.select_model(model.clone())
+            }
+            _ => Task::none(),
+        };
+        // Selecting a model resets reasoning until its capabilities are known.
+        if let Some(level) = settings.thinking_level {
+            self.user_information.thinking_level = level;
+        }
+        self.max_response_tokens_input = self.user_information.max_response_tokens.to_string();
+        self.context_tokens_input = self.user_information.context_tokens.to_string();
+        self.response_format_editor = iced::widget::text_editor::Content::with_text(
+            self.user_information
+                .response_format
+                .as_deref()
+                .unwrap_or_default(),
+        );
+        task
+    }
+
+    /// Typing in a per-chat field marks the chat for saving instead of
+    /// rewriting every saved chat on each keystroke.
+    fn remember_typed_chat_setting(&mut self) {
+        if self.temporary_chat {
+            self.remember_chat_settings();
+        } else {
+            self.open_chat_dirty = true;
+        }
+    }
+
+    fn apply_default_chat_settings(&mut self) -> Task<Message> {
+        let defaults = self.chat_defaults.clone();
+        self.apply_chat_settings(&defaults)
     }
 
     fn persist_current_chat_web_search_setting(&mut self) {
@@ -1296,6 +1974,185 @@ impl Program {
         }
     }
 
+    fn persist_control_api_settings(&mut self) {
+        match serde_json::to_value(&self.control_api) {
+            Ok(value) => self.persist_setting_value("control_api", value),
+            Err(error) => eprintln!("Failed to serialize control API settings: {error}"),
+        }
+    }
+
+    /// The messages of an open, running, temporary or saved chat.
+    fn chat_messages(&self, id: &str) -> Option<Vec<Correspondence>> {
+        let history = self
+            .active_prompts
+            .get(id)
+            .map(|job| &job.chat_history)
+            .or_else(|| (id == self.current_chat_id).then_some(&self.user_information.chat_history))
+            .or_else(|| self.temporary_chats.get(id).map(|chat| &chat.chat_history));
+        if let Some(history) = history {
+            return Some(history.lock().unwrap().messages.clone());
+        }
+        self.saved_chats
+            .iter()
+            .find(|chat| chat.id == id)
+            .map(|chat| chat.to_current(&self.chat_image_dir()).messages)
+    }
+
+    /// Routes a control API request through the same messages as the GUI.
+    fn handle_control_request(&mut self, request: ControlRequest) -> Task<Message> {
+        match request.command.clone() {
+            ControlCommand::ListChats => {
+                request.reply(ControlReply::ok(control_api::chat_list(
+                    &self.saved_chats,
+                    &self.current_chat_id,
+                )));
+                Task::none()
+            }
+            ControlCommand::NewChat => {
+                let task = self.update(Message::NewChat);
+                request.reply(ControlReply::ok(
+                    serde_json::json!({ "id": self.current_chat_id }),
+                ));
+                task
+            }
+            ControlCommand::OpenChat(id) => {
+                if self.chat_messages(&id).is_none() {
+                    request.reply(ControlReply::error(
+                        404,
+                        format!("No chat with the id {id}"),
+                    ));
+                    return Task::none();
+                }
+                let task = self.update(Message::OpenChat(id.clone()));
+                request.reply(ControlReply::ok(serde_json::json!({ "id": id })));
+                task
+            }
+            ControlCommand::ReadChat(id) => {
+                let reply = match self.chat_messages(&id) {
+                    Some(messages) => ControlReply::ok(serde_json::json!({
+                        "id": id,
+                        "responding": self.active_prompts.contains_key(&id),
+                        "messages": messages.iter().map(control_api::message_json).collect::<Vec<_>>(),
+                    })),
+                    None => ControlReply::error(404, format!("No chat with the id {id}")),
+                };
+                request.reply(reply);
+                Task::none()
+            }
+            ControlCommand::SendPrompt {
+                chat_id,
+                prompt,
+                images,
+            } => {
+                let images = match images
+                    .iter()
+                    .map(control_api::ImageInput::load)
+                    .collect::<Result<Vec<_>, _>>()
+                {
+                    Ok(images) => images,
+                    Err(error) => {
+                        request.reply(ControlReply::error(400, error));
+                        return Task::none();
+                    }
+                };
+                let mut tasks = Vec::new();
+                if let Some(id) = chat_id
+                    && id != self.current_chat_id
+                {
+                    if self.chat_messages(&id).is_none() {
+                        request.reply(ControlReply::error(
+                            404,
+                            format!("No chat with the id {id}"),
+                        ));
+                        return Task::none();
+                    }
+                    tasks.push(self.update(Message::OpenChat(id)));
+                }
+                if self.current_chat_is_processing() {
+                    request.reply(ControlReply::error(
+                        409,
+                        "This chat is still answering the previous message.",
+                    ));
+                    return Task::batch(tasks);
+                }
+
+                // Older messages must not be mistaken for this prompt's error.
+                self.drain_debug_channel();
+                let failures_before = self.debug_message_set_at;
+
+                // The request must not send or clear what the user is typing.
+                let draft = self.prompt.prompt.clone();
+                let draft_images = std::mem::replace(&mut self.pending_images, images);
+                tasks.push(self.update(Message::Prompt(prompt)));
+                self.pending_images = draft_images;
+                self.prompt.editor = iced::widget::text_editor::Content::with_text(&draft);
+                self.prompt.prompt = draft;
+
+                if self.current_chat_is_processing() {
+                    let (stream, events) = ResponseStream::new(self.current_chat_id.clone());
+                    self.control_streams.push(stream);
+                    request.reply(ControlReply::Stream(events));
+                } else {
+                    let reported =
+                        self.drain_debug_channel() || self.debug_message_set_at != failures_before;
+                    let message = if reported && self.debug_message.is_error {
+                        self.debug_message.message.clone()
+                    } else {
+                        "The prompt could not be sent.".to_string()
+                    };
+                    request.reply(ControlReply::error(400, message));
+                }
+                Task::batch(tasks)
+            }
+        }
+    }
+
+    /// Passes new answer text to API clients and closes the streams of
+    /// answers that have finished.
+    fn feed_control_streams(&mut self) {
+        for mut stream in std::mem::take(&mut self.control_streams) {
+            if let Some(job) = self.active_prompts.get(&stream.chat_id) {
+                if stream.progress(&job.response_text) {
+                    self.control_streams.push(stream);
+                }
+                continue;
+            }
+            let response = self
+                .chat_messages(&stream.chat_id)
+                .and_then(|messages| messages.last().cloned());
+            let error = self
+                .chat_notices
+                .get(&stream.chat_id)
+                .filter(|(notice, _)| notice.is_error)
+                .map(|(notice, _)| notice.message.clone());
+            stream.finish(response.as_ref(), error);
+        }
+    }
+
+    /// Appends a finished exchange to `history.json`.
+    fn record_log(&mut self, log: Log) {
+        self.app_state.logs.push_log(log);
+
+        let path = history_path();
+        let result = path
+            .parent()
+            .map(fs::create_dir_all)
+            .transpose()
+            .and_then(|_| {
+                fs::write(
+                    path,
+                    serde_json::to_string_pretty(&self.app_state.logs).unwrap(),
+                )
+            });
+        if result.is_err() {
+            eprintln!("An error writing to history.json");
+            self.set_debug_message(DebugMessage {
+                message: "Failed to write to history.json".to_string(),
+                is_error: true,
+            });
+        }
+    }
+
     fn persist_boolean_setting(&mut self, key: &str, value: bool) {
         self.persist_setting_value(key, serde_json::Value::Bool(value));
     }
@@ -1320,27 +2177,322 @@ impl Program {
         }
     }
 
-    fn persist_ui_layout(&mut self) {
-        match serde_json::to_value(&self.ui_layout) {
-            Ok(value) => self.persist_setting_value("ui_layout", value),
+    fn persist_sampling_settings(&mut self) {
+        match serde_json::to_value(&self.sampling_settings) {
+            Ok(value) => self.persist_setting_value("sampling", value),
             Err(error) => self.set_debug_message(DebugMessage {
-                message: format!("Could not save UI layout: {error}"),
+                message: format!("Could not save sampling settings: {error}"),
                 is_error: true,
             }),
         }
     }
 
-    fn persist_setting_value(&mut self, key: &str, value: serde_json::Value) {
-        self.pending_settings.insert(key.to_string(), value);
-        self.settings_dirty_at = Some(Instant::now());
+    /// The keep-alive value for requests from the open chat. Text that does not
+    /// parse is left out so Ollama falls back to its own default.
+    fn effective_keep_alive(&self) -> Option<serde_json::Value> {
+        self.keep_alive_for(&self.user_information)
     }
 
-    fn flush_pending_settings_if_ready(&mut self) {
-        let Some(dirty_at) = self.settings_dirty_at else {
-            return;
-        };
-        if dirty_at.elapsed() < Duration::from_millis(SETTINGS_SAVE_DEBOUNCE_MS) {
-            return;
+    /// The keep-alive sent for a chat: its own override, else the global one.
+    fn keep_alive_for(&self, user_info: &UserInformation) -> Option<serde_json::Value> {
+        let text = user_info.keep_alive.as_deref().unwrap_or(&self.keep_alive);
+        model_memory::parse_keep_alive(text).ok().flatten()
+    }
+
+    fn ollama_host(&self) -> HostLocation {
+        self.user_information.ip_address.clone()
+    }
+
+    /// Writes the edited active server back into its profile and saves the
+    /// profile list.
+    fn persist_server_profiles(&mut self) {
+        let mut active = self.ollama_host();
+        active.models = self.app_state.bots_list.lock().unwrap().clone();
+        active.last_model = self.user_information.model.clone();
+        self.server_profiles[self.active_server_profile] = active;
+        match serde_json::to_value(&self.server_profiles) {
+            Ok(value) => self.persist_setting_value("server_profiles", value),
+            Err(error) => eprintln!("Failed to serialize server profiles: {error}"),
+        }
+        self.persist_setting_value(
+            "active_server_profile",
+            serde_json::Value::from(self.active_server_profile),
+        );
+    }
+
+    /// Makes another saved server active. Its remembered models stand in
+    /// until the server lists them again.
+    fn switch_server_profile(&mut self, index: usize) -> Task<Message> {
+        if index >= self.server_profiles.len() {
+            return Task::none();
+        }
+        self.persist_server_profiles();
+        self.activate_server_profile(index)
+    }
+
+    fn activate_server_profile(&mut self, index: usize) -> Task<Message> {
+        self.active_server_profile = index;
+        self.server_profiles_generation += 1;
+        let profile = self.server_profiles[index].clone();
+        self.user_information.ip_address = profile.clone();
+        *self.app_state.bots_list.lock().unwrap() = profile.models.clone();
+        *self.app_state.ollama_state.lock().unwrap() = self
+            .server_health
+            .get(index)
+            .cloned()
+            .unwrap_or_else(|| "Offline".to_string());
+        self.installed_models.clear();
+        self.running_models.clear();
+        self.model_details.clear();
+        self.user_information.model = profile.last_model.clone();
+        self.persist_server_profiles();
+        // Check the new server's health and models on the next ticks.
+        self.current_tick = 0;
+
+        match profile.last_model {
+            Some(model) => self.select_model(model),
+            None => Task::none(),
+        }
+    }
+
+    /// Model management, residency and `/api/show` only exist on Ollama.
+    fn is_ollama_server(&self) -> bool {
+        self.user_information.ip_address.backend == BackendKind::Ollama
+    }
+
+    fn refresh_running_models(&self) -> Task<Message> {
+        if !self.is_ollama_server() {
+            return Task::done(Message::RunningModelsLoaded(Err(OLLAMA_ONLY.to_string())));
+        }
+        let host = self.ollama_host();
+        Task::perform(
+            async move {
+                let response = host
+                    .client()?
+                    .get(format!("{}/api/ps", host.base_url()))
+                    .send()
+                    .await
+                    .map_err(|error| error.to_string())?;
+                if !response.status().is_success() {
+                    return Err(format!("Ollama HTTP {}", response.status()));
+                }
+                let body = response
+                    .json::<serde_json::Value>()
+                    .await
+                    .map_err(|error| error.to_string())?;
+                Ok(model_memory::parse_running_models(&body))
+            },
+            Message::RunningModelsLoaded,
+        )
+    }
+
+    /// Loads or unloads `model` with an empty `/api/generate` request, which
+    /// only applies `keep_alive`.
+    fn set_model_residency(
+        &self,
+        model: String,
+        keep_alive: Option<serde_json::Value>,
+    ) -> Task<Message> {
+        if !self.is_ollama_server() {
+            return Task::done(Message::ModelResidencyChanged(Err(OLLAMA_ONLY.to_string())));
+        }
+        let host = self.ollama_host();
+        Task::perform(
+            async move {
+                let mut body = serde_json::json!({ "model": model });
+                if let Some(keep_alive) = keep_alive {
+                    body["keep_alive"] = keep_alive;
+                }
+                let response = host
+                    .client()?
+                    .post(format!("{}/api/generate", host.base_url()))
+                    .json(&body)
+                    .send()
+                    .await
+                    .map_err(|error| error.to_string())?;
+                if !response.status().is_success() {
+                    let detail = response.text().await.unwrap_or_default();
+                    return Err(format!("Ollama HTTP error: {}", detail.trim()));
+                }
+                Ok(model)
+            },
+            Message::ModelResidencyChanged,
+        )
+    }
+
+    /// Starts the next queued pull unless one is already downloading.
+    fn start_next_pull(&mut self) -> Task<Message> {
+        if self.active_pull.is_some() {
+            return Task::none();
+        }
+        let Some(model) = self.pull_queue.pop_front() else {
+            return Task::none();
+        };
+        let progress = Arc::new(Mutex::new(PullProgress::default()));
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.active_pull = Some(ActivePull {
+            model: model.clone(),
+            progress: Arc::clone(&progress),
+            cancel: Arc::clone(&cancel),
+        });
+        Task::perform(
+            model_pull::pull_model(self.ollama_host(), model.clone(), progress, cancel),
+            move |result| Message::PullFinished(model.clone(), result),
+        )
+    }
+
+    fn refresh_installed_models(&self) -> Task<Message> {
+        if !self.is_ollama_server() {
+            return Task::done(Message::InstalledModelsLoaded(Err(OLLAMA_ONLY.to_string())));
+        }
+        Task::perform(
+            model_manager::list_models(self.ollama_host()),
+            Message::InstalledModelsLoaded,
+        )
+    }
+
+    /// Fetches details for the selected model unless they are already known.
+    fn load_selected_model_details(&self) -> Task<Message> {
+        let Some(model) = self.selected_installed_model.clone() else {
+            return Task::none();
+        };
+        if self.model_details.contains_key(&model) {
+            return Task::none();
+        }
+        let host = self.ollama_host();
+        Task::perform(
+            model_manager::show_model(host, model.clone()),
+            move |result| Message::ModelDetailsLoaded(model.clone(), result),
+        )
+    }
+
+    fn is_compare_chat(&self, chat_id: &str) -> bool {
+        self.compare_columns
+            .iter()
+            .any(|column| column.chat_id == chat_id)
+    }
+
+    /// Sends the compare prompt to every selected model at once, each into a
+    /// fresh temporary chat that starts from the open chat's settings.
+    fn start_comparison(&mut self) -> Task<Message> {
+        let prompt = self.compare_prompt.trim().to_string();
+        if self
+            .compare_columns
+            .iter()
+            .any(|column| self.active_prompts.contains_key(&column.chat_id))
+        {
+            return Task::none();
+        }
+        if prompt.is_empty() || !(2..=MAX_COMPARE_MODELS).contains(&self.compare_models.len()) {
+            self.set_debug_message(DebugMessage {
+                message: "Pick two to four models and type a prompt to compare.".to_string(),
+                is_error: true,
+            });
+            return Task::none();
+        }
+        for column in std::mem::take(&mut self.compare_columns) {
+            self.temporary_chats.remove(&column.chat_id);
+            self.chat_notices.remove(&column.chat_id);
+            self.vision_responses.remove(&column.chat_id);
+        }
+
+        let base_id = Self::new_chat_id();
+        let mut tasks = Vec::new();
+        for (index, model) in self.compare_models.clone().into_iter().enumerate() {
+            let chat_id = format!("{base_id}-{index}");
+            // Columns share the connection and sampling settings, but not the
+            // open chat's per-chat options, which belong to its own model.
+            let user_info = UserInformation {
+                model: Some(model.clone()),
+                thinking_level: ThinkingLevel::Off,
+                thinking_levels: vec![ThinkingLevel::Off],
+                thinking_supported: None,
+                vision_supported: None,
+                image_generation_supported: None,
+                chat_history: Arc::new(Mutex::new(CurrentChat {
+                    chats: vec![],
+                    messages: vec![],
+                    bot_responding: false,
+                    context_summary: None,
+                })),
+                response_format: None,
+                keep_alive: None,
+                ..self.user_information.clone()
+            };
+            self.compare_columns.push(CompareColumn {
+                model,
+                chat_id: chat_id.clone(),
+                markdown: Vec::new(),
+            });
+            tasks.push(self.start_prompt_in(
+                chat_id,
+                user_info,
+                true,
+                false,
+                prompt.clone(),
+                Vec::new(),
+                PromptOrigin::Composer,
+            ));
+        }
+        Task::batch(tasks)
+    }
+
+    fn parse_compare_answer(&mut self, chat_id: &str) {
+        let Some(session) = self.temporary_chats.get(chat_id) else {
+            return;
+        };
+        let answer = session
+            .chat_history
+            .lock()
+            .unwrap()
+            .messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Correspondence::Bot { text, .. } => Some(split_thinking_text(text).1),
+                Correspondence::User { .. } => None,
+            })
+            .unwrap_or_default();
+        if let Some(column) = self
+            .compare_columns
+            .iter_mut()
+            .find(|column| column.chat_id == chat_id)
+        {
+            column.markdown = parse_markdown_items(&answer);
+        }
+    }
+
+    /// Replaces the editor text and validates it straight away.
+    fn seed_modelfile(&mut self, text: String) {
+        self.modelfile_issues = if text.is_empty() {
+            Vec::new()
+        } else {
+            Modelfile::parse(&text).err().unwrap_or_default()
+        };
+        self.modelfile_editor = iced::widget::text_editor::Content::with_text(&text);
+    }
+
+    fn persist_ui_layout(&mut self) {
+        match serde_json::to_value(&self.ui_layout) {
+            Ok(value) => self.persist_setting_value("ui_layout", value),
+            Err(error) => self.set_debug_message(DebugMessage {
+                message: format!("Could not save UI layout: {error}"),
+                is_error: true,
+            }),
+        }
+    }
+
+    fn persist_setting_value(&mut self, key: &str, value: serde_json::Value) {
+        self.pending_settings.insert(key.to_string(), value);
+        self.settings_dirty_at = Some(Instant::now());
+    }
+
+    fn flush_pending_settings_if_ready(&mut self) {
+        let Some(dirty_at) = self.settings_dirty_at else {
+            return;
+        };
+        if dirty_at.elapsed() < Duration::from_millis(SETTINGS_SAVE_DEBOUNCE_MS) {
+            return;
         }
 
         let pending = std::mem::take(&mut self.pending_settings);
@@ -1387,7 +2539,23 @@ impl Program {
         .map_err(|error| error.to_string())
     }
 
-    fn set_debug_message(&mut self, debug_message: DebugMessage) {
+    /// Shows the newest message sent through the debug channel, which is how
+    /// background work and `prepare_prompt_in` report problems. Returns
+    /// whether there was one.
+    fn drain_debug_channel(&mut self) -> bool {
+        let latest = {
+            let guard = self.channels.debug_channel.lock().unwrap();
+            guard.1.try_iter().last()
+        };
+        let received = latest.is_some();
+        if let Some(debug_message) = latest {
+            self.set_debug_message(debug_message);
+        }
+        received
+    }
+
+    fn set_debug_message(&mut self, mut debug_message: DebugMessage) {
+        debug_message.message = self.redact_server_secrets(&debug_message.message);
         let has_message = !debug_message.message.trim().is_empty();
 
         self.debug_message = debug_message;
@@ -1419,6 +2587,18 @@ impl Program {
             .unwrap_or(&self.debug_message)
     }
 
+    /// Cached renders are positional; drop them from `index` after the
+    /// messages there were swapped for another variant or branch.
+    fn truncate_chat_caches(&mut self, index: usize) {
+        self.chat_markdown_cache.truncate(index);
+        self.chat_model_name_cache.truncate(index);
+        self.chat_thinking_cache.truncate(index);
+        self.chat_visible_text_cache.truncate(index);
+        self.expanded_thinking.retain(|expanded| *expanded < index);
+        self.collapsed_json_nodes
+            .retain(|(collapsed, _)| *collapsed < index);
+    }
+
     fn refresh_chat_markdown_cache(&mut self) {
         let chat_history = Arc::clone(&self.user_information.chat_history);
         let chat_history = chat_history.lock().unwrap();
@@ -1458,6 +2638,10 @@ impl Program {
             }
         }
         self.chat_messages_cache.clone_from(&chat_history.messages);
+        self.chat_summary_boundary = chat_history
+            .context_summary
+            .as_ref()
+            .map(|summary| summary.covers);
     }
 
     fn drain_live_updates(&mut self) {
@@ -1471,11 +2655,21 @@ impl Program {
                 job.parsed_markdown = render.markdown;
             }
         }
-        while let Ok((chat_id, notice)) = self.chat_notice_receiver.try_recv() {
+        while let Ok((chat_id, mut notice)) = self.chat_notice_receiver.try_recv() {
+            notice.message = self.redact_server_secrets(&notice.message);
             self.chat_notices.insert(chat_id, (notice, Instant::now()));
         }
     }
 
+    /// Masks the credentials of every saved server, since an error may come
+    /// from a request made before the user switched profiles.
+    fn redact_server_secrets(&self, text: &str) -> String {
+        self.server_profiles
+            .iter()
+            .chain([&self.user_information.ip_address])
+            .fold(text.to_string(), |text, host| host.redact(&text))
+    }
+
     fn clear_copy_feedback_if_old(&mut self) {
         if let Some(copied_at) = self.last_copied_at
             && copied_at.elapsed() >= Duration::from_millis(1400)
@@ -1528,18 +2722,43 @@ impl Program {
             return;
         };
         Self::finalize_response_metadata(&job);
+        if let Some(previous) = &job.replaced_response {
+            let mut chat = job.chat_history.lock().unwrap();
+            let start = job.response_start_index.min(chat.messages.len());
+            match chat.messages[start..]
+                .iter_mut()
+                .rev()
+                .find(|message| matches!(message, Correspondence::Bot { .. }))
+            {
+                Some(response) => response.append_to_variants_of(previous),
+                // A cancelled regeneration produced nothing; keep the old answer.
+                None => chat.push_message(previous.clone()),
+            }
+        }
 
         let completed_chat = job.chat_history.lock().unwrap().clone();
+        // Changes made while the answer streamed belong to the open chat.
+        let settings = if self.current_chat_id == chat_id {
+            self.current_chat_settings()
+        } else {
+            job.settings.clone()
+        };
         if job.temporary {
             self.temporary_chats.insert(
                 chat_id.to_string(),
                 TemporaryChatSession {
                     chat_history: Arc::clone(&job.chat_history),
                     web_search_enabled: job.web_search_enabled,
+                    settings,
                 },
             );
         } else {
-            self.save_chat_snapshot(chat_id.to_string(), &completed_chat, job.web_search_enabled);
+            self.save_chat_snapshot(
+                chat_id.to_string(),
+                &completed_chat,
+                job.web_search_enabled,
+                settings,
+            );
         }
 
         if job.had_image
@@ -1570,8 +2789,125 @@ impl Program {
         }
     }
 
-    fn prompt(&mut self, mut prompt: String) -> Task<Message> {
-        if self.user_information.model.is_none() {
+    fn prompt(&mut self, prompt: String) -> Task<Message> {
+        // Clone the attachment into the request/chat first. The composer owns its
+        // copy until the submission has been accepted, avoiding a transient blank
+        // preview while the async request is being prepared.
+        let attached_images = self.pending_images.clone();
+        self.start_prompt(prompt, attached_images, PromptOrigin::Composer)
+    }
+
+    /// Re-runs the user turn before the latest answer, keeping that answer
+    /// as an earlier variant of the new one.
+    fn regenerate_response(&mut self, index: usize) -> Task<Message> {
+        if self.active_prompts.contains_key(&self.current_chat_id) {
+            return Task::none();
+        }
+        let user_turn = {
+            let chat = self.user_information.chat_history.lock().unwrap();
+            if chat.bot_responding
+                || index + 1 != chat.messages.len()
+                || !matches!(chat.messages[index], Correspondence::Bot { .. })
+            {
+                return Task::none();
+            }
+            match index
+                .checked_sub(1)
+                .map(|user_index| &chat.messages[user_index])
+            {
+                Some(Correspondence::User { text, images, .. }) => (text.clone(), images.clone()),
+                _ => return Task::none(),
+            }
+        };
+        self.start_prompt(
+            user_turn.0,
+            user_turn.1,
+            PromptOrigin::Regenerate(index - 1),
+        )
+    }
+
+    /// Sends an edited version of the user turn at `index` on a new branch.
+    fn submit_edited_turn(&mut self, index: usize, text: String) -> Task<Message> {
+        if self.active_prompts.contains_key(&self.current_chat_id) || text.trim().is_empty() {
+            return Task::none();
+        }
+        let images = match self
+            .user_information
+            .chat_history
+            .lock()
+            .unwrap()
+            .messages
+            .get(index)
+        {
+            Some(Correspondence::User { images, .. }) => images.clone(),
+            _ => return Task::none(),
+        };
+        self.start_prompt(text, images, PromptOrigin::Edit(index))
+    }
+
+    fn start_prompt(
+        &mut self,
+        prompt: String,
+        attached_images: Vec<ChatImage>,
+        origin: PromptOrigin,
+    ) -> Task<Message> {
+        self.start_prompt_in(
+            self.current_chat_id.clone(),
+            self.user_information.clone(),
+            self.temporary_chat,
+            self.web_search_for_chat,
+            prompt,
+            attached_images,
+            origin,
+        )
+    }
+
+    /// Sends a prompt into the chat `chat_id`, whose history and model are
+    /// those of `user_info`. Only the open chat's view state is updated, so
+    /// background chats such as compare columns can run alongside it.
+    #[allow(clippy::too_many_arguments)]
+    fn start_prompt_in(
+        &mut self,
+        chat_id: String,
+        user_info: UserInformation,
+        temporary: bool,
+        web_search_enabled: bool,
+        prompt: String,
+        attached_images: Vec<ChatImage>,
+        origin: PromptOrigin,
+    ) -> Task<Message> {
+        let completion_chat_id = chat_id.clone();
+        match self.prepare_prompt_in(
+            chat_id,
+            user_info,
+            temporary,
+            web_search_enabled,
+            prompt,
+            attached_images,
+            origin,
+        ) {
+            Some(generation) => Task::perform(generation, move |()| {
+                Message::PromptFinished(completion_chat_id.clone())
+            }),
+            None => Task::none(),
+        }
+    }
+
+    /// Records the prompt in its chat and returns the generation that streams
+    /// the answer into it, or `None` when the prompt cannot be sent. Headless
+    /// mode runs the generation itself instead of through an iced task.
+    #[allow(clippy::too_many_arguments)]
+    fn prepare_prompt_in(
+        &mut self,
+        chat_id: String,
+        user_info: UserInformation,
+        temporary: bool,
+        web_search_enabled: bool,
+        mut prompt: String,
+        attached_images: Vec<ChatImage>,
+        origin: PromptOrigin,
+    ) -> Option<PromptGeneration> {
+        if user_info.model.is_none() {
             Channels::send_request_to_channel(
                 Arc::clone(&self.channels.debug_channel),
                 DebugMessage {
@@ -1579,15 +2915,27 @@ impl Program {
                     is_error: true,
                 },
             );
-            println!("Model is None");
-            return Task::none();
+            eprintln!("Model is None");
+            return None;
         }
 
+        let response_format =
+            match ResponseFormat::parse(user_info.response_format.as_deref().unwrap_or_default()) {
+                Ok(format) => format,
+                Err(error) => {
+                    self.set_debug_message(DebugMessage {
+                        message: format!("Structured output: {error}"),
+                        is_error: true,
+                    });
+                    return None;
+                }
+            };
+
         if self.app_state.filtering {
             prompt = censor_text(&prompt);
         }
 
-        let model_name = self.user_information.model.clone().unwrap();
+        let model_name = user_info.model.clone().unwrap();
         let started_at = Instant::now();
 
         let cancel = Arc::new(AtomicBool::new(false));
@@ -1600,7 +2948,7 @@ impl Program {
 
         // Backpressure bounds token memory if highlighting a large response is
         // temporarily slower than Ollama's stream.
-        let (tx, mut rx) = tokio::sync::mpsc::channel::<GenerationResponse>(64);
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<StreamToken>(64);
         let batch_tokens = self.batch_tokens;
         let fast_streaming = self.fast_streaming;
         std::thread::spawn(move || {
@@ -1626,7 +2974,18 @@ impl Program {
             let mut total_tokens = 0;
 
             while let Some(token) = rx.blocking_recv() {
-                buffer.push_str(&token.response);
+                match token {
+                    StreamToken::Content(text) => buffer.push_str(&text),
+                    StreamToken::Thinking(thinking) => {
+                        buffer.push_str(&format!("<think>{thinking}</think>"));
+                    }
+                    // A web-search turn turned out to be a tool call, not the answer.
+                    StreamToken::Discard => {
+                        buffer.clear();
+                        render(&buffer, &render_sender, &render_receiver_for_renderer);
+                        continue;
+                    }
+                }
 
                 total_tokens += 1;
 
@@ -1664,42 +3023,60 @@ impl Program {
                     is_error: true,
                 },
             );
-            return Task::none();
+            return None;
         }
 
-        // Clone the attachment into the request/chat first. The composer owns its
-        // copy until the submission has been accepted, avoiding a transient blank
-        // preview while the async request is being prepared.
-        let attached_images = self.pending_images.clone();
         let had_image = !attached_images.is_empty();
         let logging = self.app_state.logging;
         let filtering = self.app_state.filtering;
-        let user_info = self.user_information.clone();
         let channels = self.channels.clone();
-        let web_search_enabled = self.web_search_for_chat;
+        let sampling_settings = self.sampling_settings.clone();
+        let keep_alive = self.keep_alive_for(&user_info);
         let mut web_search_settings = self.web_search_settings.clone();
         web_search_settings.enabled = web_search_enabled;
         let (web_search_state_sender, web_search_state_receiver) = crossbeam_channel::unbounded();
-        let chat_id = self.current_chat_id.clone();
-        let completion_chat_id = chat_id.clone();
+        let opens_chat = chat_id == self.current_chat_id;
         let notice_chat_id = chat_id.clone();
         let chat_notice_sender = self.chat_notice_sender.clone();
         self.chat_notices.remove(&chat_id);
 
-        let response_start_index = {
+        let (history, response_start_index, replaced_response) = {
             let mut chat = user_info.chat_history.lock().unwrap();
-            chat.push_message(Correspondence::User {
-                text: prompt.clone(),
-                images: attached_images.clone(),
-            });
-            chat.messages.len()
+            let replaced_response = match origin {
+                PromptOrigin::Composer => {
+                    chat.push_message(Correspondence::user(
+                        prompt.clone(),
+                        attached_images.clone(),
+                    ));
+                    None
+                }
+                PromptOrigin::Regenerate(user_index) => chat.take_response_after(user_index),
+                PromptOrigin::Edit(user_index) => {
+                    chat.fork_at(user_index, prompt.clone());
+                    None
+                }
+            };
+            // The user turn being answered is now last; only earlier turns are history.
+            let earlier_turns = chat.messages.len().saturating_sub(1);
+            let history = if user_info.current_chat_history_enabled {
+                chat_history_messages(&chat.messages[..earlier_turns])
+            } else {
+                Vec::new()
+            };
+            (history, chat.messages.len(), replaced_response)
         };
-        self.open_chat_dirty = true;
-
-        self.refresh_chat_markdown_cache();
-        self.pending_images.clear();
+        if opens_chat {
+            self.open_chat_dirty = true;
+            match origin {
+                PromptOrigin::Composer => self.pending_images.clear(),
+                PromptOrigin::Regenerate(user_index) | PromptOrigin::Edit(user_index) => {
+                    self.truncate_chat_caches(user_index);
+                }
+            }
+            self.refresh_chat_markdown_cache();
+        }
         user_info.chat_history.lock().unwrap().bot_responding = true;
-        if self.temporary_chat {
+        if temporary {
             self.temporary_chats.remove(&chat_id);
         }
         self.active_prompts.insert(
@@ -1718,33 +3095,57 @@ impl Program {
                 response_start_index,
                 had_image,
                 web_search_enabled,
-                temporary: self.temporary_chat,
+                temporary,
+                replaced_response,
+                settings: ChatSettings::capture(&user_info, &self.system_prompt),
             },
         );
 
-        Task::perform(
-            async move {
-                println!("Received prompt: {}", prompt.clone());
-
-                let system_prompt: String = system_prompt.unwrap();
-                let ip = user_info.ip_address.clone();
-                let to_send_prompt: String = if user_info.current_chat_history_enabled {
-                    format!(
-                        "The following is a conversation between an AI language model and a User. You are the AI language model:
-                    {}
-                    [END CONVERSATION CONTEXT]
-                    Now, the user is sending another message: {}
-                    Respond:
-                    ",
-                        user_info.chat_history.lock().unwrap().unravel(),
-                        prompt.clone()
-                    )
-                } else {
-                    prompt.clone()
-                };
+        Some(Box::pin(async move {
+            let system_prompt: String = system_prompt.unwrap();
+            let ip = user_info.ip_address.clone();
+            let model_backend = backend::for_host(&ip);
+            if let Err(message) = ip.client() {
+                {
+                    send_chat_notice(
+                        &chat_notice_sender,
+                        &notice_chat_id,
+                        DebugMessage {
+                            message: message.clone(),
+                            is_error: true,
+                        },
+                    );
+                    append_failed_response(
+                        &user_info.chat_history,
+                        user_info.model.clone(),
+                        message,
+                    );
+                    user_info.chat_history.lock().unwrap().bot_responding = false;
+                    return;
+                }
+            }
+            let encoded_images = attached_images
+                .iter()
+                .map(|image| BASE64.encode(&image.bytes))
+                .collect::<Vec<_>>();
+            let new_turn = user_message(prompt.clone(), encoded_images.clone());
+            let history = tokio::select! {
+                history = fit_history_to_context(
+                    &user_info,
+                    &system_prompt,
+                    history,
+                    &new_turn,
+                    |notice| send_chat_notice(&chat_notice_sender, &notice_chat_id, notice),
+                ) => history,
+                () = wait_until_cancelled(&cancel) => {
+                    user_info.chat_history.lock().unwrap().bot_responding = false;
+                    return;
+                }
+            };
 
-                if web_search_enabled {
-                    let provider = match BraveSearchProvider::new(&web_search_settings) {
+            if web_search_enabled {
+                let provider =
+                    match BraveSearchProvider::new(&web_search_settings) {
                         Ok(provider) => Arc::new(provider),
                         Err(error) => {
                             let _ = web_search_state_sender.send(WebSearchState::Failed {
@@ -1765,205 +3166,101 @@ impl Program {
                                     thinking_seconds: None,
                                     sources: Vec::new(),
                                     web_search_used: true,
+                                    stats: None,
+                                    format_check: None,
+                                    alternatives: Vec::new(),
+                                    active_variant: 0,
                                 },
                             );
                             user_info.chat_history.lock().unwrap().bot_responding = false;
                             return;
                         }
                     };
-                    let result = run_tool_loop(ToolLoopRequest {
-                        ollama_url: format!("http://{}:{}/api/chat", ip.ip, ip.port),
-                        model: user_info.model.clone().unwrap(),
-                        prompt: to_send_prompt,
-                        system_prompt: system_prompt.clone(),
-                        temperature: user_info.temperature / 10.0,
-                        context_tokens: user_info.context_tokens,
-                        max_response_tokens: user_info.max_response_tokens,
-                        images: attached_images
-                            .iter()
-                            .map(|image| BASE64.encode(&image.bytes))
-                            .collect(),
-                        thinking: user_info.thinking_level.api_value(),
-                        settings: web_search_settings.clone(),
-                        provider,
-                        state_sender: web_search_state_sender.clone(),
-                        cancel: Arc::clone(&cancel),
-                    })
-                    .await;
+                let result = run_tool_loop(ToolLoopRequest {
+                    backend: Arc::clone(&model_backend),
+                    model: user_info.model.clone().unwrap(),
+                    prompt: prompt.clone(),
+                    history: history.clone(),
+                    system_prompt: system_prompt.clone(),
+                    temperature: user_info.temperature / 10.0,
+                    context_tokens: user_info.context_tokens,
+                    max_response_tokens: user_info.max_response_tokens,
+                    images: encoded_images.clone(),
+                    thinking: user_info.thinking_level.api_value(),
+                    settings: web_search_settings.clone(),
+                    provider,
+                    sampling: sampling_settings.clone(),
+                    format: response_format.as_ref().map(ResponseFormat::api_value),
+                    keep_alive: keep_alive.clone(),
+                    state_sender: web_search_state_sender.clone(),
+                    token_sender: (!filtering).then(|| tx.clone()),
+                    cancel: Arc::clone(&cancel),
+                })
+                .await;
 
-                    match result {
-                        Ok(result) => {
-                            let answer = if filtering {
-                                censor_text(&result.answer)
-                            } else {
-                                result.answer
-                            };
-                            let _ = tx
-                                .send(GenerationResponse {
-                                    model: user_info.model.clone().unwrap(),
-                                    created_at: Local::now().to_rfc3339(),
-                                    response: answer.clone(),
-                                    done: true,
-                                    context: None,
-                                    total_duration: None,
-                                    load_duration: None,
-                                    prompt_eval_count: None,
-                                    prompt_eval_duration: None,
-                                    eval_count: None,
-                                    eval_duration: None,
-                                    thinking: None,
-                                    logprobs: None,
-                                })
-                                .await;
-                            if logging {
-                                Channels::send_request_to_channel(
-                                    Arc::clone(&channels.logging_channel),
-                                    Log::create_with_current_time(
-                                        filtering,
-                                        user_info.model.clone(),
-                                        vec![answer.clone()],
-                                        Some(system_prompt),
-                                        prompt.clone(),
-                                    ),
-                                );
-                            }
-                            if user_info.current_chat_history_enabled {
-                                user_info
-                                    .chat_history
-                                    .lock()
-                                    .unwrap()
-                                    .generate_and_push(prompt.clone(), answer.clone());
-                            }
-                            user_info.chat_history.lock().unwrap().push_message(
-                                Correspondence::Bot {
-                                    text: answer,
-                                    model: user_info.model.clone(),
-                                    thinking_seconds: None,
-                                    sources: result.sources,
-                                    web_search_used: true,
-                                },
-                            );
+                match result {
+                    Ok(result) => {
+                        let (answer, thinking) = if filtering {
+                            (censor_text(&result.answer), censor_text(&result.thinking))
+                        } else {
+                            (result.answer, result.thinking)
+                        };
+                        // Unfiltered answers have already streamed in.
+                        if filtering {
+                            let _ = tx.send(StreamToken::Content(answer.clone())).await;
                         }
-                        Err(crate::web_search::WebSearchError::Cancelled) => {}
-                        Err(error) => {
-                            let message = error.user_message().to_string();
-                            eprintln!(
-                                "Web-search failure: {}",
-                                error.diagnostic(web_search_settings.api_key.as_deref())
-                            );
-                            let _ = web_search_state_sender.send(WebSearchState::Failed {
-                                message: message.clone(),
-                            });
-                            send_chat_notice(
-                                &chat_notice_sender,
-                                &notice_chat_id,
-                                DebugMessage {
-                                    message: message.clone(),
-                                    is_error: true,
-                                },
-                            );
-                            user_info.chat_history.lock().unwrap().push_message(
-                                Correspondence::Bot {
-                                    text: format!("Web search failed: {message}"),
-                                    model: user_info.model.clone(),
-                                    thinking_seconds: None,
-                                    sources: Vec::new(),
-                                    web_search_used: true,
-                                },
+                        if logging {
+                            Channels::send_request_to_channel(
+                                Arc::clone(&channels.logging_channel),
+                                Log::create_with_current_time(
+                                    filtering,
+                                    user_info.model.clone(),
+                                    vec![answer.clone()],
+                                    Some(system_prompt),
+                                    prompt.clone(),
+                                ),
                             );
                         }
-                    }
-                    user_info.chat_history.lock().unwrap().bot_responding = false;
-                    return;
-                }
-
-                let request: GenerationRequest<'_> =
-                    GenerationRequest::new(user_info.model.clone().unwrap(), to_send_prompt)
-                        .options(
-                            ModelOptions::default()
-                                .temperature(user_info.temperature / 10.0)
-                                .num_predict(user_info.max_response_tokens as i32)
-                                .num_ctx(user_info.context_tokens as u64),
-                        )
-                        .system(system_prompt.clone());
-
-                println!("System prompt: {}", system_prompt.clone());
-
-                let mut request_body = match serde_json::to_value(request) {
-                    Ok(body) => body,
-                    Err(e) => {
-                        eprintln!("Error serializing request: {}", e);
-                        let message = "Could not prepare the Ollama request".to_string();
-                        send_chat_notice(
-                            &chat_notice_sender,
-                            &notice_chat_id,
-                            DebugMessage {
-                                message: message.clone(),
-                                is_error: true,
-                            },
-                        );
-                        append_failed_response(
-                            &user_info.chat_history,
-                            user_info.model.clone(),
-                            message,
-                        );
-                        user_info.chat_history.lock().unwrap().bot_responding = false;
-                        return;
-                    }
-                };
-
-                request_body["stream"] = serde_json::Value::Bool(true);
-                request_body["think"] = user_info.thinking_level.api_value();
-                if !attached_images.is_empty() {
-                    request_body["images"] = serde_json::json!(
-                        attached_images
-                            .iter()
-                            .map(|image| BASE64.encode(&image.bytes))
-                            .collect::<Vec<_>>()
-                    );
-                }
-
-                let url = format!("http://{}:{}/api/generate", ip.ip, ip.port);
-                let request = reqwest::Client::new().post(url).json(&request_body).send();
-                let response = tokio::select! {
-                    response = request => Some(response),
-                    () = wait_until_cancelled(&cancel) => None,
-                };
-                let Some(response) = response else {
-                    user_info.chat_history.lock().unwrap().bot_responding = false;
-                    return;
-                };
-                let mut response = match response {
-                    Ok(response) if response.status().is_success() => response,
-                    Ok(response) => {
-                        let status = response.status();
-                        let response_text = response.text();
-                        let detail = tokio::select! {
-                            detail = response_text => detail.unwrap_or_default(),
-                            () = wait_until_cancelled(&cancel) => {
-                                user_info.chat_history.lock().unwrap().bot_responding = false;
-                                return;
-                            }
+                        if user_info.current_chat_history_enabled {
+                            user_info
+                                .chat_history
+                                .lock()
+                                .unwrap()
+                                .generate_and_push(prompt.clone(), answer.clone());
+                        }
+                        let format_check =
+                            response_format.as_ref().map(|format| format.check(&answer));
+                        let text = if thinking.is_empty() {
+                            answer
+                        } else {
+                            format!("<think>{thinking}</think>{answer}")
                         };
-                        let message = format!("Ollama rejected the request ({status}): {detail}");
-                        send_chat_notice(
-                            &chat_notice_sender,
-                            &notice_chat_id,
-                            DebugMessage {
-                                message: message.clone(),
-                                is_error: true,
-                            },
-                        );
-                        append_failed_response(
-                            &user_info.chat_history,
-                            user_info.model.clone(),
-                            message,
-                        );
-                        user_info.chat_history.lock().unwrap().bot_responding = false;
-                        return;
+                        user_info
+                            .chat_history
+                            .lock()
+                            .unwrap()
+                            .push_message(Correspondence::Bot {
+                                text,
+                                model: user_info.model.clone(),
+                                thinking_seconds: None,
+                                sources: result.sources,
+                                web_search_used: true,
+                                stats: result.stats,
+                                format_check,
+                                alternatives: Vec::new(),
+                                active_variant: 0,
+                            });
                     }
+                    Err(crate::web_search::WebSearchError::Cancelled) => {}
                     Err(error) => {
-                        let message = format!("Could not reach Ollama: {error}");
+                        let message = error.user_message().to_string();
+                        eprintln!(
+                            "Web-search failure: {}",
+                            ip.redact(&error.diagnostic(web_search_settings.api_key.as_deref()))
+                        );
+                        let _ = web_search_state_sender.send(WebSearchState::Failed {
+                            message: message.clone(),
+                        });
                         send_chat_notice(
                             &chat_notice_sender,
                             &notice_chat_id,
@@ -1972,105 +3269,159 @@ impl Program {
                                 is_error: true,
                             },
                         );
-                        append_failed_response(
-                            &user_info.chat_history,
-                            user_info.model.clone(),
-                            message,
-                        );
-                        user_info.chat_history.lock().unwrap().bot_responding = false;
-                        return;
+                        user_info
+                            .chat_history
+                            .lock()
+                            .unwrap()
+                            .push_message(Correspondence::Bot {
+                                text: format!("Web search failed: {message}"),
+                                model: user_info.model.clone(),
+                                thinking_seconds: None,
+                                sources: Vec::new(),
+                                web_search_used: true,
+                                stats: None,
+                                format_check: None,
+                                alternatives: Vec::new(),
+                                active_variant: 0,
+                            });
                     }
-                };
+                }
+                user_info.chat_history.lock().unwrap().bot_responding = false;
+                return;
+            }
 
-                let mut final_response: Vec<String> = vec![];
-                let mut stream_buffer = String::new();
+            let options = ModelOptions::default()
+                .temperature(user_info.temperature / 10.0)
+                .num_predict(user_info.max_response_tokens as i32)
+                .num_ctx(user_info.context_tokens as u64);
 
-                'response_stream: while !cancel.load(Ordering::Relaxed) {
-                    let chunk_result = tokio::select! {
-                        chunk = response.chunk() => chunk,
-                        _ = tokio::time::sleep(Duration::from_millis(50)) => continue,
-                    };
-                    let Ok(Some(chunk)) = chunk_result else {
-                        break;
-                    };
-                    stream_buffer.push_str(&String::from_utf8_lossy(&chunk));
-                    while let Some(newline) = stream_buffer.find('\n') {
-                        let line = stream_buffer[..newline].trim().to_string();
-                        stream_buffer.drain(..=newline);
-                        if line.is_empty() {
-                            continue;
-                        }
-                        match decode_generation_line(&line) {
-                            Ok((mut token, done_reason)) => {
-                                if token.done
-                                    && (done_reason.as_deref() == Some("length")
-                                        || token.eval_count.unwrap_or_default()
-                                            >= user_info.max_response_tokens as u64)
-                                {
-                                    send_chat_notice(
-                                        &chat_notice_sender,
-                                        &notice_chat_id,
-                                        DebugMessage {
-                                            message: format!(
-                                                "The model reached the generation limit ({} tokens). Increase Maximum response or Context window in Settings.",
-                                                user_info.max_response_tokens
-                                            ),
-                                            is_error: true,
-                                        },
-                                    );
-                                }
-                                if !filtering {
-                                    print!("{}", token.response);
-                                }
+            let options = match serde_json::to_value(options) {
+                Ok(mut options) => {
+                    sampling_settings.apply_to(&mut options);
+                    options
+                }
+                Err(e) => {
+                    eprintln!("Error serializing request: {}", e);
+                    let message = "Could not prepare the Ollama request".to_string();
+                    send_chat_notice(
+                        &chat_notice_sender,
+                        &notice_chat_id,
+                        DebugMessage {
+                            message: message.clone(),
+                            is_error: true,
+                        },
+                    );
+                    append_failed_response(
+                        &user_info.chat_history,
+                        user_info.model.clone(),
+                        message,
+                    );
+                    user_info.chat_history.lock().unwrap().bot_responding = false;
+                    return;
+                }
+            };
 
-                                // Ollama may return reasoning in its dedicated `thinking`
-                                // field, while some models emit literal <think> tags.
-                                // Normalize both forms so the renderer can disclose them alike.
-                                if let Some(thinking) = token.thinking.take()
-                                    && !thinking.is_empty()
-                                {
-                                    token.response =
-                                        format!("<think>{thinking}</think>{}", token.response);
-                                }
+            let mut messages =
+                vec![serde_json::json!({"role": "system", "content": system_prompt})];
+            messages.extend(history);
+            messages.push(new_turn);
+            let mut request_body = serde_json::json!({
+                "model": user_info.model.clone().unwrap(),
+                "messages": messages,
+                "options": options,
+                "stream": true,
+                "think": user_info.thinking_level.api_value(),
+            });
+            if let Some(format) = &response_format {
+                request_body["format"] = format.api_value();
+            }
+            if let Some(keep_alive) = keep_alive {
+                request_body["keep_alive"] = keep_alive;
+            }
 
-                                final_response.push(token.response.clone());
-
-                                // Filtering must see the complete response: Ollama can
-                                // split a profane word across arbitrary stream tokens.
-                                if !filtering {
-                                    let sent = tokio::select! {
-                                        result = tx.send(token) => result.is_ok(),
-                                        () = wait_until_cancelled(&cancel) => false,
-                                    };
-                                    if !sent {
-                                        break 'response_stream;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("Error decoding Ollama response: {}", e);
-                                send_chat_notice(
-                                    &chat_notice_sender,
-                                    &notice_chat_id,
-                                    DebugMessage {
-                                        message: "Ollama returned an invalid streaming response"
-                                            .to_string(),
-                                        is_error: true,
-                                    },
-                                );
-                            }
+            let request = async {
+                model_backend
+                    .chat_request(&request_body)?
+                    .send()
+                    .await
+                    .map_err(|error| format!("Could not reach the model server: {error}"))
+            };
+            let response = tokio::select! {
+                response = request => Some(response),
+                () = wait_until_cancelled(&cancel) => None,
+            };
+            let Some(response) = response else {
+                user_info.chat_history.lock().unwrap().bot_responding = false;
+                return;
+            };
+            let mut response = match response {
+                Ok(response) if response.status().is_success() => response,
+                Ok(response) => {
+                    let status = response.status();
+                    let response_text = response.text();
+                    let detail = tokio::select! {
+                        detail = response_text => detail.unwrap_or_default(),
+                        () = wait_until_cancelled(&cancel) => {
+                            user_info.chat_history.lock().unwrap().bot_responding = false;
+                            return;
                         }
-                    }
+                    };
+                    let message = ip.redact(&format!(
+                        "The model server rejected the request ({status}): {}",
+                        backend::error_detail(&detail).unwrap_or(detail)
+                    ));
+                    send_chat_notice(
+                        &chat_notice_sender,
+                        &notice_chat_id,
+                        DebugMessage {
+                            message: message.clone(),
+                            is_error: true,
+                        },
+                    );
+                    append_failed_response(
+                        &user_info.chat_history,
+                        user_info.model.clone(),
+                        message,
+                    );
+                    user_info.chat_history.lock().unwrap().bot_responding = false;
+                    return;
                 }
+                Err(error) => {
+                    let message = ip.redact(&error);
+                    send_chat_notice(
+                        &chat_notice_sender,
+                        &notice_chat_id,
+                        DebugMessage {
+                            message: message.clone(),
+                            is_error: true,
+                        },
+                    );
+                    append_failed_response(
+                        &user_info.chat_history,
+                        user_info.model.clone(),
+                        message,
+                    );
+                    user_info.chat_history.lock().unwrap().bot_responding = false;
+                    return;
+                }
+            };
+
+            let mut final_response: Vec<String> = vec![];
+            let mut stream = model_backend.chat_stream();
+            let mut stats = None;
 
-                let was_cancelled = cancel.load(Ordering::Relaxed);
-                // NDJSON normally ends with a newline, but accepting a final
-                // unterminated object avoids dropping the last token from
-                // proxies or older Ollama builds.
-                let trailing_line = stream_buffer.trim();
-                if !was_cancelled && !trailing_line.is_empty() {
-                    match decode_generation_line(trailing_line) {
-                        Ok((mut token, done_reason)) => {
+            'response_stream: while !cancel.load(Ordering::Relaxed) {
+                let chunk_result = tokio::select! {
+                    chunk = response.chunk() => chunk,
+                    _ = tokio::time::sleep(Duration::from_millis(50)) => continue,
+                };
+                let Ok(Some(chunk)) = chunk_result else {
+                    break;
+                };
+                for line in stream.push(&chunk) {
+                    match line.and_then(decode_generation_value) {
+                        Ok((mut token, done_reason, line_stats)) => {
+                            stats = stats.or(line_stats);
                             if token.done
                                 && (done_reason.as_deref() == Some("length")
                                     || token.eval_count.unwrap_or_default()
@@ -2088,24 +3439,37 @@ impl Program {
                                     },
                                 );
                             }
+                            // Ollama may return reasoning in its dedicated `thinking`
+                            // field, while some models emit literal <think> tags.
+                            // Normalize both forms so the renderer can disclose them alike.
                             if let Some(thinking) = token.thinking.take()
                                 && !thinking.is_empty()
                             {
                                 token.response =
                                     format!("<think>{thinking}</think>{}", token.response);
                             }
+
                             final_response.push(token.response.clone());
+
+                            // Filtering must see the complete response: Ollama can
+                            // split a profane word across arbitrary stream tokens.
                             if !filtering {
-                                let _ = tx.send(token).await;
+                                let sent = tokio::select! {
+                                    result = tx.send(StreamToken::Content(token.response)) => result.is_ok(),
+                                    () = wait_until_cancelled(&cancel) => false,
+                                };
+                                if !sent {
+                                    break 'response_stream;
+                                }
                             }
                         }
-                        Err(error) => {
-                            eprintln!("Error decoding final Ollama response: {error}");
+                        Err(e) => {
+                            eprintln!("Error decoding Ollama response: {}", e);
                             send_chat_notice(
                                 &chat_notice_sender,
                                 &notice_chat_id,
                                 DebugMessage {
-                                    message: "Ollama returned an invalid final streaming response"
+                                    message: "Ollama returned an invalid streaming response"
                                         .to_string(),
                                     is_error: true,
                                 },
@@ -2113,101 +3477,152 @@ impl Program {
                         }
                     }
                 }
+            }
 
-                if !was_cancelled && final_response.concat().trim().is_empty() {
-                    let message =
-                        "Ollama ended the response without returning content.".to_string();
-                    send_chat_notice(
-                        &chat_notice_sender,
-                        &notice_chat_id,
-                        DebugMessage {
-                            message: message.clone(),
-                            is_error: true,
-                        },
-                    );
-                    append_failed_response(
-                        &user_info.chat_history,
-                        user_info.model.clone(),
-                        message,
-                    );
-                    user_info.chat_history.lock().unwrap().bot_responding = false;
-                    return;
+            let was_cancelled = cancel.load(Ordering::Relaxed);
+            // NDJSON normally ends with a newline, but accepting a final
+            // unterminated object avoids dropping the last token from
+            // proxies or older Ollama builds.
+            let trailing_lines = if was_cancelled {
+                Vec::new()
+            } else {
+                stream.finish()
+            };
+            for line in trailing_lines {
+                match line.and_then(decode_generation_value) {
+                    Ok((mut token, done_reason, line_stats)) => {
+                        stats = stats.or(line_stats);
+                        if token.done
+                            && (done_reason.as_deref() == Some("length")
+                                || token.eval_count.unwrap_or_default()
+                                    >= user_info.max_response_tokens as u64)
+                        {
+                            send_chat_notice(
+                                &chat_notice_sender,
+                                &notice_chat_id,
+                                DebugMessage {
+                                    message: format!(
+                                        "The model reached the generation limit ({} tokens). Increase Maximum response or Context window in Settings.",
+                                        user_info.max_response_tokens
+                                    ),
+                                    is_error: true,
+                                },
+                            );
+                        }
+                        if let Some(thinking) = token.thinking.take()
+                            && !thinking.is_empty()
+                        {
+                            token.response = format!("<think>{thinking}</think>{}", token.response);
+                        }
+                        final_response.push(token.response.clone());
+                        if !filtering {
+                            let _ = tx.send(StreamToken::Content(token.response)).await;
+                        }
+                    }
+                    Err(error) => {
+                        eprintln!("Error decoding final Ollama response: {error}");
+                        send_chat_notice(
+                            &chat_notice_sender,
+                            &notice_chat_id,
+                            DebugMessage {
+                                message: "Ollama returned an invalid final streaming response"
+                                    .to_string(),
+                                is_error: true,
+                            },
+                        );
+                    }
                 }
+            }
 
-                if filtering && !final_response.is_empty() {
-                    let filtered = censor_text(&final_response.join(""));
-                    final_response = vec![filtered.clone()];
-                    let _ = tx
-                        .send(GenerationResponse {
-                            model: user_info.model.clone().unwrap_or_default(),
-                            created_at: Local::now().to_rfc3339(),
-                            response: filtered,
-                            done: true,
-                            context: None,
-                            total_duration: None,
-                            load_duration: None,
-                            prompt_eval_count: None,
-                            prompt_eval_duration: None,
-                            eval_count: None,
-                            eval_duration: None,
-                            thinking: None,
-                            logprobs: None,
-                        })
-                        .await;
-                }
-
-                if logging && !was_cancelled {
-                    Channels::send_request_to_channel(
-                        Arc::clone(&channels.logging_channel),
-                        Log::create_with_current_time(
-                            filtering,
-                            user_info.model,
-                            final_response.clone(),
-                            Some(system_prompt),
-                            prompt.clone(),
-                        ),
-                    );
-                }
+            if !was_cancelled && final_response.concat().trim().is_empty() {
+                let message = "Ollama ended the response without returning content.".to_string();
+                send_chat_notice(
+                    &chat_notice_sender,
+                    &notice_chat_id,
+                    DebugMessage {
+                        message: message.clone(),
+                        is_error: true,
+                    },
+                );
+                append_failed_response(&user_info.chat_history, user_info.model.clone(), message);
+                user_info.chat_history.lock().unwrap().bot_responding = false;
+                return;
+            }
 
-                if user_info.current_chat_history_enabled && !was_cancelled {
-                    let complete = final_response.join("");
-                    let (_, visible_response) = split_thinking_text(&complete);
-                    user_info
-                        .chat_history
-                        .lock()
-                        .unwrap()
-                        .generate_and_push(prompt.clone(), visible_response);
-                }
+            if filtering && !final_response.is_empty() {
+                let filtered = censor_text(&final_response.join(""));
+                final_response = vec![filtered.clone()];
+                let _ = tx.send(StreamToken::Content(filtered)).await;
+            }
 
-                let partial_response = final_response.join("");
-                if !partial_response.is_empty() {
-                    let partial_response = disabled_web_tool_message(&partial_response)
-                        .unwrap_or(&partial_response)
-                        .to_string();
-                    user_info
-                        .chat_history
-                        .lock()
-                        .unwrap()
-                        .push_message(Correspondence::Bot {
-                            text: partial_response,
-                            model: None,
-                            thinking_seconds: None,
-                            sources: Vec::new(),
-                            web_search_used: false,
-                        });
-                }
+            if logging && !was_cancelled {
+                Channels::send_request_to_channel(
+                    Arc::clone(&channels.logging_channel),
+                    Log::create_with_current_time(
+                        filtering,
+                        user_info.model,
+                        final_response.clone(),
+                        Some(system_prompt),
+                        prompt.clone(),
+                    ),
+                );
+            }
 
-                user_info.chat_history.lock().unwrap().bot_responding = false;
-            },
-            move |_| Message::PromptFinished(completion_chat_id.clone()),
-        )
+            if user_info.current_chat_history_enabled && !was_cancelled {
+                let complete = final_response.join("");
+                let (_, visible_response) = split_thinking_text(&complete);
+                user_info
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .generate_and_push(prompt.clone(), visible_response);
+            }
+
+            let partial_response = final_response.join("");
+            if !partial_response.is_empty() {
+                let partial_response = disabled_web_tool_message(&partial_response)
+                    .unwrap_or(&partial_response)
+                    .to_string();
+                // A stopped response is incomplete JSON, not a failed one.
+                let format_check = response_format
+                    .as_ref()
+                    .filter(|_| !was_cancelled)
+                    .map(|format| format.check(&split_thinking_text(&partial_response).1));
+                user_info
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .push_message(Correspondence::Bot {
+                        text: partial_response,
+                        model: None,
+                        thinking_seconds: None,
+                        sources: Vec::new(),
+                        web_search_used: false,
+                        stats,
+                        format_check,
+                        alternatives: Vec::new(),
+                        active_variant: 0,
+                    });
+            }
+
+            user_info.chat_history.lock().unwrap().bot_responding = false;
+        }))
     }
 
     fn boot() -> (Program, Task<Message>) {
         let mut program = Program::default();
+        program.chat_defaults = program.current_chat_settings();
+        let restored_settings = program
+            .saved_chats
+            .iter()
+            .find(|chat| chat.id == program.current_chat_id)
+            .map(|chat| chat.settings.clone());
+        let settings_task = restored_settings
+            .map(|settings| program.apply_chat_settings(&settings))
+            .unwrap_or_else(Task::none);
         program.refresh_chat_markdown_cache();
         let task = program.queue_missing_markdown_images();
-        (program, task)
+        (program, Task::batch([settings_task, task]))
     }
 
     fn update(&mut self, message: Message) -> Task<Message> {
@@ -2216,6 +3631,10 @@ impl Program {
 
             Message::PromptFinished(chat_id) => {
                 self.finish_prompt(&chat_id);
+                // Pick up error notices sent just before the answer ended.
+                self.drain_live_updates();
+                self.feed_control_streams();
+                self.parse_compare_answer(&chat_id);
                 self.begin_page_transition();
                 self.queue_missing_markdown_images()
             }
@@ -2360,12 +3779,13 @@ impl Program {
                     return Task::none();
                 }
                 self.is_generating_image = true;
-                let host = format!(
-                    "http://{}:{}",
-                    self.user_information.ip_address.ip, self.user_information.ip_address.port
-                );
                 Task::perform(
-                    generate_image_via_ollama(host, model, prompt),
+                    generate_image_via_ollama(
+                        self.ollama_host(),
+                        model,
+                        prompt,
+                        self.effective_keep_alive(),
+                    ),
                     Message::ImageGenerated,
                 )
             }
@@ -2545,7 +3965,7 @@ impl Program {
                 self.web_search_for_chat = self.web_search_settings.enabled;
                 self.clear_open_chat();
                 self.begin_page_transition();
-                Task::none()
+                self.apply_default_chat_settings()
             }
 
             Message::OpenChat(id) => {
@@ -2558,6 +3978,15 @@ impl Program {
                     .active_prompts
                     .get(&id)
                     .map(|job| (job.temporary, job.web_search_enabled));
+                let remembered_settings = self
+                    .active_prompts
+                    .get(&id)
+                    .map(|job| job.settings.clone())
+                    .or_else(|| {
+                        self.temporary_chats
+                            .get(&id)
+                            .map(|chat| chat.settings.clone())
+                    });
                 let temporary_history = self
                     .temporary_chats
                     .get(&id)
@@ -2570,10 +3999,13 @@ impl Program {
                 let saved = self.saved_chats.iter().find(|chat| chat.id == id).cloned();
                 let saved_web_search_enabled =
                     saved.as_ref().and_then(|chat| chat.web_search_enabled);
-                if let Some(chat_history) = running_history
-                    .or(temporary_history)
-                    .or_else(|| saved.map(|chat| Arc::new(Mutex::new(chat.to_current()))))
-                {
+                let remembered_settings = remembered_settings
+                    .or_else(|| saved.as_ref().map(|chat| chat.settings.clone()));
+                let mut settings_task = Task::none();
+                if let Some(chat_history) = running_history.or(temporary_history).or_else(|| {
+                    let image_dir = self.chat_image_dir();
+                    saved.map(|chat| Arc::new(Mutex::new(chat.to_current(&image_dir))))
+                }) {
                     self.current_chat_id = id;
                     if let Some((_, shown_at)) = self.chat_notices.get_mut(&self.current_chat_id) {
                         *shown_at = Instant::now();
@@ -2587,6 +4019,9 @@ impl Program {
                         .unwrap_or(self.web_search_settings.enabled);
                     self.user_information.chat_history = chat_history;
                     self.open_chat_dirty = false;
+                    if let Some(settings) = remembered_settings {
+                        settings_task = self.apply_chat_settings(&settings);
+                    }
                     // Rendering caches are positional and belong only to the
                     // previously open chat.
                     self.chat_messages_cache.clear();
@@ -2595,12 +4030,15 @@ impl Program {
                     self.chat_markdown_cache.clear();
                     self.chat_model_name_cache.clear();
                     self.expanded_thinking.clear();
+                    self.collapsed_json_nodes.clear();
+                    self.editing_message = None;
+                    self.search_highlight = None;
                     self.last_copied_text = None;
                     self.last_copied_at = None;
                     self.refresh_chat_markdown_cache();
                     self.begin_page_transition();
                 }
-                self.queue_missing_markdown_images()
+                Task::batch([settings_task, self.queue_missing_markdown_images()])
             }
 
             Message::DeleteChat(id) => {
@@ -2614,13 +4052,17 @@ impl Program {
                 self.saved_chats.retain(|chat| chat.id != id);
                 self.chat_notices.remove(&id);
                 self.vision_responses.remove(&id);
+                let mut settings_task = Task::none();
                 if self.current_chat_id == id {
                     self.current_chat_id = Self::new_chat_id();
                     self.clear_open_chat();
                     self.begin_page_transition();
+                    settings_task = self.apply_default_chat_settings();
                 }
-                self.persist_saved_chats();
-                Task::none()
+                if self.persist_saved_chats() {
+                    remove_unreferenced_images(&self.chat_image_dir(), &self.saved_chats);
+                }
+                settings_task
             }
 
             Message::DeleteTemporaryChat(id) => {
@@ -2633,6 +4075,7 @@ impl Program {
                     self.web_search_for_chat = self.web_search_settings.enabled;
                     self.clear_open_chat();
                     self.begin_page_transition();
+                    return self.apply_default_chat_settings();
                 }
                 Task::none()
             }
@@ -2667,7 +4110,7 @@ impl Program {
                     self.clear_open_chat();
                 }
                 self.begin_page_transition();
-                Task::none()
+                self.apply_default_chat_settings()
             }
 
             Message::ChooseChatFolder => {
@@ -2686,19 +4129,183 @@ impl Program {
                 }
             }
 
-            Message::ChatFolderSelected(Some(folder)) => {
-                // The folder picker is asynchronous, so a prompt may have
-                // started after it opened. Keep the current storage location
-                // stable until every background chat has finished.
-                if !self.active_prompts.is_empty() {
+            Message::ExportChats(scope) => {
+                let (chats, file_name) = match scope {
+                    ExportScope::OpenChat => {
+                        let chat = self.open_chat_document();
+                        let file_name = chat_export::suggested_file_name(&chat.title);
+                        (vec![chat], file_name)
+                    }
+                    ExportScope::AllChats => {
+                        let image_dir = self.chat_image_dir();
+                        let chats = self
+                            .saved_chats
+                            .iter()
+                            .map(|chat| ChatDocument::from_saved(chat, &image_dir))
+                            .collect();
+                        (chats, "ollama-gui-chats".to_string())
+                    }
+                };
+                if chats.iter().all(|chat| chat.messages.is_empty()) {
                     self.set_debug_message(DebugMessage {
-                        message: "Wait for running chats before changing the chat folder."
-                            .to_string(),
+                        message: "There are no messages to export yet.".to_string(),
                         is_error: false,
                     });
                     return Task::none();
                 }
-                self.save_open_chat();
+                Task::perform(
+                    async move {
+                        let mut dialog =
+                            rfd::FileDialog::new().set_file_name(format!("{file_name}.md"));
+                        for format in ExportFormat::ALL {
+                            dialog = dialog.add_filter(format.to_string(), &[format.extension()]);
+                        }
+                        let Some(path) = dialog.save_file() else {
+                            return Ok(None);
+                        };
+                        let (path, format) = ExportFormat::for_path(path);
+                        let contents =
+                            chat_export::export(&chats, format, &Local::now().to_rfc3339());
+                        fs::write(&path, contents).map_err(|error| error.to_string())?;
+                        Ok(Some(path))
+                    },
+                    Message::ChatsExported,
+                )
+            }
+
+            Message::ChatsExported(result) => {
+                match result {
+                    Ok(Some(path)) => self.set_debug_message(DebugMessage {
+                        message: format!("Exported to {}", path.display()),
+                        is_error: false,
+                    }),
+                    Ok(None) => {}
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not export chats: {error}"),
+                        is_error: true,
+                    }),
+                }
+                Task::none()
+            }
+
+            Message::ImportChats => Task::perform(
+                async {
+                    let Some(path) = rfd::FileDialog::new()
+                        .add_filter("JSON", &["json"])
+                        .pick_file()
+                    else {
+                        return Ok(None);
+                    };
+                    let data = fs::read_to_string(&path).map_err(|error| error.to_string())?;
+                    let (source, chats) = chat_import::parse_export(&data)?;
+                    Ok(Some(ImportPreview {
+                        source,
+                        path,
+                        chats,
+                    }))
+                },
+                Message::ChatImportLoaded,
+            ),
+
+            Message::ChatImportLoaded(result) => {
+                match result {
+                    Ok(Some(preview)) => self.chat_import = Some(preview),
+                    Ok(None) => {}
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not import chats: {error}"),
+                        is_error: true,
+                    }),
+                }
+                Task::none()
+            }
+
+            Message::ToggleImportedChat(index) => {
+                if let Some(chat) = self
+                    .chat_import
+                    .as_mut()
+                    .and_then(|preview| preview.chats.get_mut(index))
+                {
+                    chat.selected = !chat.selected;
+                }
+                Task::none()
+            }
+
+            Message::SelectAllImportedChats(selected) => {
+                if let Some(preview) = &mut self.chat_import {
+                    for chat in &mut preview.chats {
+                        chat.selected = selected;
+                    }
+                }
+                Task::none()
+            }
+
+            Message::ConfirmChatImport => {
+                if let Some(preview) = self.chat_import.take() {
+                    self.merge_imported_chats(preview);
+                }
+                Task::none()
+            }
+
+            Message::CancelChatImport => {
+                self.chat_import = None;
+                Task::none()
+            }
+
+            Message::ChatSearchChanged(query) => {
+                self.chat_search_query = query;
+                if self.chat_search_index.is_empty()
+                    && !self.chat_search_indexing
+                    && !self.saved_chats.is_empty()
+                {
+                    // Reading every chat can take a moment with a large
+                    // history; later searches only re-read changed chats.
+                    self.chat_search_indexing = true;
+                    let chats = self.saved_chats.clone();
+                    return Task::perform(
+                        async move {
+                            let mut index = SearchIndex::default();
+                            index.sync(&chats);
+                            index
+                        },
+                        Message::ChatSearchIndexed,
+                    );
+                }
+                self.refresh_chat_search();
+                Task::none()
+            }
+
+            Message::ChatSearchIndexed(index) => {
+                self.chat_search_index = index;
+                self.chat_search_indexing = false;
+                self.refresh_chat_search();
+                Task::none()
+            }
+
+            Message::OpenSearchResult(id, message) => {
+                let task = self.update(Message::OpenChat(id.clone()));
+                if self.current_chat_id != id {
+                    return task;
+                }
+                let Some(index) = message else {
+                    return task;
+                };
+                self.search_highlight = Some((id, index));
+                Task::batch([task, self.scroll_to_message(index)])
+            }
+
+            Message::ChatFolderSelected(Some(folder)) => {
+                // The folder picker is asynchronous, so a prompt may have
+                // started after it opened. Keep the current storage location
+                // stable until every background chat has finished.
+                if !self.active_prompts.is_empty() {
+                    self.set_debug_message(DebugMessage {
+                        message: "Wait for running chats before changing the chat folder."
+                            .to_string(),
+                        is_error: false,
+                    });
+                    return Task::none();
+                }
+                self.save_open_chat();
                 let previous_directory = self.chat_storage_dir.clone();
                 let result = fs::create_dir_all(&folder)
                     .map_err(|error| error.to_string())
@@ -2734,6 +4341,7 @@ impl Program {
 
             Message::FrameTick => {
                 self.drain_live_updates();
+                self.feed_control_streams();
                 self.advance_ui_motion();
                 self.queue_missing_markdown_images()
             }
@@ -2752,64 +4360,36 @@ impl Program {
                 self.current_tick += 1;
 
                 if self.current_tick == VERSION_TICK {
-                    let ollama_state = Arc::clone(&self.app_state.ollama_state);
-                    let user_info = self.user_information.clone();
+                    let mut profiles = self.server_profiles.clone();
+                    // The active profile may hold edits not yet written back.
+                    profiles[self.active_server_profile] = self.ollama_host();
+                    let generation = self.server_profiles_generation;
 
                     return Task::perform(
                         async move {
                             println!("Checking Ollama version...");
-                            let ip = user_info.ip_address;
-                            let url = format!("http://{}:{}/api/version", ip.ip, ip.port);
-
-                            match reqwest::get(url).await {
-                                Ok(response) => {
-                                    println!("API responded with status: {}", response.status());
-
-                                    if response.status().is_success() {
-                                        match response.json::<serde_json::Value>().await {
-                                            Ok(json) => {
-                                                if let Some(version) =
-                                                    json.get("version").and_then(|v| v.as_str())
-                                                {
-                                                    *ollama_state.lock().unwrap() =
-                                                        format!("Online (v{})", version);
-                                                } else {
-                                                    *ollama_state.lock().unwrap() =
-                                                        "Online (unknown version)".to_string();
-                                                }
-                                            }
-                                            Err(_) => {
-                                                *ollama_state.lock().unwrap() =
-                                                    "Online (version parse error)".to_string();
-                                            }
-                                        }
-                                    } else {
-                                        *ollama_state.lock().unwrap() = "Offline".to_string();
-                                    }
-                                }
-                                Err(err) => {
-                                    println!("Failed to reach API: {}", err);
-                                    *ollama_state.lock().unwrap() = "Offline".to_string();
-                                }
-                            }
+                            // Checked together, so one offline server does not
+                            // hold up the others.
+                            let checks = profiles.iter().map(|profile| async move {
+                                backend::for_host(profile).status().await
+                            });
+                            iced::futures::future::join_all(checks).await
                         },
-                        Message::AsyncResult,
+                        move |health| Message::ServerHealthChecked(generation, health),
                     );
+                } else if self.current_tick == RUNNING_MODELS_TICK
+                    && self.app_state.gui_state == GUIState::AdvancedSettings
+                {
+                    return self.refresh_running_models();
                 } else if self.current_tick == BOT_LIST_TICK {
-                    let ip = self.user_information.ip_address.clone();
-                    let ollama = Ollama::builder()
-                        .host(format!("http://{}", ip.ip))
-                        .port(convert_port_to_u16(ip.port))
-                        .build();
+                    let host = self.ollama_host();
                     let bots_list = Arc::clone(&self.app_state.bots_list);
                     let channels = self.channels.clone();
 
                     return Task::perform(
                         async move {
-                            match ollama.list_local_models().await {
-                                Ok(bots) => {
-                                    let mut names =
-                                        bots.into_iter().map(|bot| bot.name).collect::<Vec<_>>();
+                            match backend::for_host(&host).list_models().await {
+                                Ok(mut names) => {
                                     names.sort();
                                     names.dedup();
                                     *bots_list.lock().unwrap() = names;
@@ -2832,14 +4412,7 @@ impl Program {
                     );
                 }
 
-                let debug_result = {
-                    let guard = self.channels.debug_channel.lock().unwrap();
-                    guard.1.try_recv()
-                };
-
-                if let Ok(debug_msg) = debug_result {
-                    self.set_debug_message(debug_msg);
-                }
+                self.drain_debug_channel();
 
                 let log_result = {
                     let guard = self.channels.logging_channel.lock().unwrap();
@@ -2847,29 +4420,7 @@ impl Program {
                 };
 
                 if let Ok(log) = log_result {
-                    self.app_state.logs.push_log(log);
-
-                    let path = history_path();
-                    let result = path
-                        .parent()
-                        .map(fs::create_dir_all)
-                        .transpose()
-                        .and_then(|_| {
-                            fs::write(
-                                path,
-                                serde_json::to_string_pretty(&self.app_state.logs).unwrap(),
-                            )
-                        });
-                    match result {
-                        Ok(_) => {}
-                        Err(_) => {
-                            eprintln!("An error writing to history.json");
-                            self.set_debug_message(DebugMessage {
-                                message: "Failed to write to history.json".to_string(),
-                                is_error: true,
-                            });
-                        }
-                    };
+                    self.record_log(log);
                 }
 
                 self.queue_missing_markdown_images()
@@ -2877,14 +4428,194 @@ impl Program {
 
             Message::ChangeIp(ip) => {
                 self.user_information.ip_address.ip = ip;
+                self.persist_server_profiles();
                 Task::none()
             }
 
             Message::ChangePort(port) => {
                 self.user_information.ip_address.port = port;
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::ChangeServerName(name) => {
+                self.user_information.ip_address.name = name;
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::ToggleServerHttps => {
+                let host = &mut self.user_information.ip_address;
+                host.scheme = if host.scheme == "https" {
+                    "http".to_string()
+                } else {
+                    "https".to_string()
+                };
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::ChangeServerBackend(kind) => {
+                self.user_information.ip_address.backend = kind;
+                self.installed_models.clear();
+                self.running_models.clear();
+                self.model_details.clear();
+                self.persist_server_profiles();
+                // Re-check the server and its models with the new protocol.
+                self.current_tick = 0;
                 Task::none()
             }
 
+            Message::ControlApi(Ok(request)) => self.handle_control_request(request),
+
+            Message::ControlApi(Err(error)) => {
+                self.set_debug_message(DebugMessage {
+                    message: format!("Control API: {error}"),
+                    is_error: true,
+                });
+                Task::none()
+            }
+
+            Message::ToggleControlApi => {
+                self.control_api.enabled = !self.control_api.enabled;
+                if self.control_api.token.is_empty() {
+                    self.control_api.token = control_api::new_token();
+                }
+                self.persist_control_api_settings();
+                Task::none()
+            }
+
+            Message::EditControlApiPort(value) => {
+                if let Ok(port) = value.trim().parse::<u16>()
+                    && port != 0
+                {
+                    self.control_api.port = port;
+                    self.persist_control_api_settings();
+                }
+                self.control_api_port_input = value;
+                Task::none()
+            }
+
+            Message::NewControlApiToken => {
+                self.control_api.token = control_api::new_token();
+                self.persist_control_api_settings();
+                self.set_debug_message(DebugMessage {
+                    message: "Created a new control API token. Clients using the old one must be updated.".to_string(),
+                    is_error: false,
+                });
+                Task::none()
+            }
+
+            Message::ChangeServerApiKey(api_key) => {
+                self.user_information.ip_address.api_key = (!api_key.is_empty()).then_some(api_key);
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::SwitchServerProfile(choice) => self.switch_server_profile(choice.index),
+
+            Message::ServerHealthChecked(generation, health) => {
+                if generation == self.server_profiles_generation
+                    && health.len() == self.server_profiles.len()
+                {
+                    *self.app_state.ollama_state.lock().unwrap() =
+                        health[self.active_server_profile].clone();
+                    self.server_health = health;
+                }
+                Task::none()
+            }
+
+            Message::AddServerProfile => {
+                let name = server_profiles::unique_profile_name(&self.server_profiles, "Server");
+                self.server_profiles.push(HostLocation {
+                    name,
+                    ..HostLocation::default()
+                });
+                self.switch_server_profile(self.server_profiles.len() - 1)
+            }
+
+            Message::PickServerCertificate(kind) => Task::perform(
+                async {
+                    rfd::FileDialog::new()
+                        .add_filter("PEM certificate", &["pem", "crt", "cer"])
+                        .pick_file()
+                },
+                move |path| Message::ServerCertificatePicked(kind, path),
+            ),
+
+            Message::ServerCertificatePicked(kind, Some(path)) => {
+                *kind.path(&mut self.user_information.ip_address) =
+                    Some(path.to_string_lossy().into_owned());
+                // Report an unusable file now rather than on the next request.
+                if let Err(error) = self.ollama_host().client() {
+                    self.set_debug_message(DebugMessage {
+                        message: error,
+                        is_error: true,
+                    });
+                }
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::ServerCertificatePicked(_, None) => Task::none(),
+
+            Message::ClearServerCertificate(kind) => {
+                *kind.path(&mut self.user_information.ip_address) = None;
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::AddServerHeader => {
+                self.user_information
+                    .ip_address
+                    .headers
+                    .push((String::new(), String::new()));
+                self.persist_server_profiles();
+                Task::none()
+            }
+
+            Message::EditServerHeaderName(index, name) => {
+                if let Some(header) = self.user_information.ip_address.headers.get_mut(index) {
+                    header.0 = name;
+                    self.persist_server_profiles();
+                }
+                Task::none()
+            }
+
+            Message::EditServerHeaderValue(index, value) => {
+                if let Some(header) = self.user_information.ip_address.headers.get_mut(index) {
+                    header.1 = value;
+                    self.persist_server_profiles();
+                }
+                Task::none()
+            }
+
+            Message::RemoveServerHeader(index) => {
+                let headers = &mut self.user_information.ip_address.headers;
+                if index < headers.len() {
+                    headers.remove(index);
+                    self.persist_server_profiles();
+                }
+                Task::none()
+            }
+
+            Message::DeleteServerProfile => {
+                if self.server_profiles.len() == 1 {
+                    self.set_debug_message(DebugMessage {
+                        message: "The last server profile cannot be deleted".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                }
+                let removed = self.active_server_profile;
+                self.server_profiles.remove(removed);
+                if removed < self.server_health.len() {
+                    self.server_health.remove(removed);
+                }
+                // Activate without writing the deleted profile back first.
+                self.activate_server_profile(removed.saturating_sub(1))
+            }
+
             Message::ToggleChatHistory => {
                 self.user_information.current_chat_history_enabled =
                     !self.user_information.current_chat_history_enabled;
@@ -2959,6 +4690,8 @@ impl Program {
                     self.app_state.gui_state = GUIState::Settings;
                 } else {
                     self.app_state.gui_state = GUIState::AdvancedSettings;
+                    self.begin_page_transition();
+                    return self.refresh_running_models();
                 }
                 self.begin_page_transition();
 
@@ -2967,6 +4700,7 @@ impl Program {
 
             Message::UpdateTemperature(n) => {
                 self.user_information.temperature = n;
+                self.remember_chat_settings();
                 Task::none()
             }
 
@@ -2978,6 +4712,7 @@ impl Program {
                 self.user_information.max_response_tokens = tokens;
                 self.max_response_tokens_input = tokens.to_string();
                 self.persist_setting_value("max_response_tokens", serde_json::Value::from(tokens));
+                self.remember_chat_settings();
                 Task::none()
             }
 
@@ -2994,6 +4729,7 @@ impl Program {
                             "max_response_tokens",
                             serde_json::Value::from(tokens),
                         );
+                        self.remember_chat_settings();
                     }
                     _ => self.set_debug_message(DebugMessage {
                         message: format!(
@@ -3013,6 +4749,7 @@ impl Program {
                 self.user_information.context_tokens = tokens;
                 self.context_tokens_input = tokens.to_string();
                 self.persist_setting_value("context_tokens", serde_json::Value::from(tokens));
+                self.remember_chat_settings();
                 Task::none()
             }
 
@@ -3029,6 +4766,7 @@ impl Program {
                             "context_tokens",
                             serde_json::Value::from(tokens),
                         );
+                        self.remember_chat_settings();
                     }
                     _ => self.set_debug_message(DebugMessage {
                         message: format!(
@@ -3125,6 +4863,7 @@ impl Program {
 
             Message::ThinkingLevelChange(level) => {
                 self.user_information.thinking_level = level;
+                self.remember_chat_settings();
                 Task::none()
             }
 
@@ -3135,6 +4874,90 @@ impl Program {
                 Task::none()
             }
 
+            Message::RegenerateResponse(index) => {
+                self.begin_page_transition();
+                self.regenerate_response(index)
+            }
+
+            Message::StartEditTurn(index) => {
+                if let Some(Correspondence::User { text, .. }) = self
+                    .user_information
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .messages
+                    .get(index)
+                {
+                    self.editing_message =
+                        Some((index, iced::widget::text_editor::Content::with_text(text)));
+                }
+                Task::none()
+            }
+
+            Message::EditTurnAction(action) => {
+                if let Some((_, editor)) = &mut self.editing_message {
+                    editor.perform(action);
+                }
+                Task::none()
+            }
+
+            Message::SubmitEditedTurn => {
+                let Some((index, editor)) = self.editing_message.take() else {
+                    return Task::none();
+                };
+                self.begin_page_transition();
+                self.submit_edited_turn(index, editor.text().trim_end().to_string())
+            }
+
+            Message::CancelEditTurn => {
+                self.editing_message = None;
+                Task::none()
+            }
+
+            Message::SelectBranch(index, branch) => {
+                if self.active_prompts.contains_key(&self.current_chat_id) {
+                    return Task::none();
+                }
+                let changed = self
+                    .user_information
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .select_branch(index, branch);
+                if changed {
+                    self.editing_message = None;
+                    self.truncate_chat_caches(index);
+                    self.refresh_chat_markdown_cache();
+                    self.open_chat_dirty = true;
+                    self.save_open_chat();
+                    self.begin_page_transition();
+                }
+                self.queue_missing_markdown_images()
+            }
+
+            Message::SelectResponseVariant(index, variant) => {
+                let changed = self
+                    .user_information
+                    .chat_history
+                    .lock()
+                    .unwrap()
+                    .messages
+                    .get_mut(index)
+                    .is_some_and(|message| message.select_response_variant(variant));
+                if changed {
+                    self.user_information
+                        .chat_history
+                        .lock()
+                        .unwrap()
+                        .forget_summary_from(index);
+                    self.truncate_chat_caches(index);
+                    self.refresh_chat_markdown_cache();
+                    self.open_chat_dirty = true;
+                    self.save_open_chat();
+                }
+                Task::none()
+            }
+
             Message::ModelCapabilitiesKnown(model, capabilities) => {
                 if self.user_information.model.as_ref() == Some(&model)
                     && let Some(capabilities) = capabilities
@@ -3164,99 +4987,667 @@ impl Program {
                 Task::none()
             }
 
+            Message::EditSamplingOption(option, value) => {
+                if self.sampling_settings.set_text(option, &value).is_ok() {
+                    self.persist_sampling_settings();
+                }
+                self.sampling_inputs.insert(option, value);
+                Task::none()
+            }
+
+            Message::ResetSamplingToModelDefaults => {
+                let Some(model) = self.user_information.model.clone() else {
+                    self.set_debug_message(DebugMessage {
+                        message: "Select a model before loading its defaults.".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                };
+                let ip = self.user_information.ip_address.clone();
+                Task::perform(
+                    async move {
+                        let json =
+                            backend::for_host(&ip)
+                                .model_info(&model)
+                                .await
+                                .ok_or_else(|| {
+                                    "this server does not report model defaults".to_string()
+                                })?;
+                        // Models without a Modelfile `PARAMETER` omit the field.
+                        Ok(json["parameters"].as_str().unwrap_or_default().to_string())
+                    },
+                    Message::ModelParametersLoaded,
+                )
+            }
+
+            Message::ModelParametersLoaded(result) => {
+                match result {
+                    Ok(parameters) => {
+                        self.sampling_settings =
+                            SamplingSettings::from_model_parameters(&parameters);
+                        self.sampling_inputs = sampling_inputs(&self.sampling_settings);
+                        self.persist_sampling_settings();
+                        if let Some(temperature) = sampling::model_temperature(&parameters) {
+                            self.user_information.temperature =
+                                (temperature * 10.0).clamp(0.0, 10.0);
+                            self.remember_chat_settings();
+                        }
+                        self.set_debug_message(DebugMessage {
+                            message: "Sampling options reset to the model's defaults.".to_string(),
+                            is_error: false,
+                        });
+                    }
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not read the model's defaults: {error}"),
+                        is_error: true,
+                    }),
+                }
+                Task::none()
+            }
+
+            Message::ToggleStructuredOutput => {
+                self.structured_output_open = !self.structured_output_open;
+                if !self.structured_output_open {
+                    self.remember_chat_settings();
+                }
+                Task::none()
+            }
+
+            Message::EditResponseFormat(action) => {
+                let edited = action.is_edit();
+                self.response_format_editor.perform(action);
+                if edited {
+                    let text = self.response_format_editor.text();
+                    self.user_information.response_format =
+                        (!text.trim().is_empty()).then_some(text);
+                    self.remember_typed_chat_setting();
+                }
+                Task::none()
+            }
+
+            Message::UseJsonFormat => {
+                self.response_format_editor = iced::widget::text_editor::Content::with_text("json");
+                self.user_information.response_format = Some("json".to_string());
+                self.remember_chat_settings();
+                Task::none()
+            }
+
+            Message::ClearResponseFormat => {
+                self.response_format_editor = iced::widget::text_editor::Content::new();
+                self.user_information.response_format = None;
+                self.remember_chat_settings();
+                Task::none()
+            }
+
+            Message::LoadResponseFormat => Task::perform(
+                async {
+                    let path = rfd::FileDialog::new()
+                        .add_filter("JSON Schema", &["json"])
+                        .pick_file()?;
+                    Some(fs::read_to_string(path).map_err(|error| error.to_string()))
+                },
+                Message::ResponseFormatLoaded,
+            ),
+
+            Message::ResponseFormatLoaded(Some(Ok(schema))) => {
+                if let Err(error) = ResponseFormat::parse(&schema) {
+                    self.set_debug_message(DebugMessage {
+                        message: format!("Structured output: {error}"),
+                        is_error: true,
+                    });
+                    return Task::none();
+                }
+                self.response_format_editor =
+                    iced::widget::text_editor::Content::with_text(&schema);
+                self.user_information.response_format =
+                    (!schema.trim().is_empty()).then_some(schema);
+                self.remember_chat_settings();
+                Task::none()
+            }
+
+            Message::ResponseFormatLoaded(Some(Err(error))) => {
+                self.set_debug_message(DebugMessage {
+                    message: format!("Could not read the schema file: {error}"),
+                    is_error: true,
+                });
+                Task::none()
+            }
+
+            Message::ResponseFormatLoaded(None) => Task::none(),
+
+            Message::ToggleJsonNode(index, path) => {
+                let node = (index, path);
+                if !self.collapsed_json_nodes.remove(&node) {
+                    self.collapsed_json_nodes.insert(node);
+                }
+                Task::none()
+            }
+
+            Message::RefreshRunningModels => self.refresh_running_models(),
+
+            Message::RunningModelsLoaded(result) => {
+                match result {
+                    Ok(models) => {
+                        self.running_models = models;
+                        self.running_models_error = None;
+                    }
+                    Err(error) => {
+                        self.running_models.clear();
+                        self.running_models_error = Some(error);
+                    }
+                }
+                Task::none()
+            }
+
+            Message::UnloadModel(model) => {
+                self.set_model_residency(model, Some(serde_json::Value::from(0)))
+            }
+
+            Message::PreloadModel => {
+                let Some(model) = self.user_information.model.clone() else {
+                    self.set_debug_message(DebugMessage {
+                        message: "Select a model to preload.".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                };
+                self.set_debug_message(DebugMessage {
+                    message: format!("Loading {model}..."),
+                    is_error: false,
+                });
+                self.set_model_residency(model, self.effective_keep_alive())
+            }
+
+            Message::ModelResidencyChanged(result) => {
+                match result {
+                    Ok(model) => self.set_debug_message(DebugMessage {
+                        message: format!("Updated memory residency of {model}."),
+                        is_error: false,
+                    }),
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not change the loaded model: {error}"),
+                        is_error: true,
+                    }),
+                }
+                self.refresh_running_models()
+            }
+
+            Message::EditKeepAlive(text) => {
+                if model_memory::parse_keep_alive(&text).is_ok() {
+                    self.persist_setting_value(
+                        "keep_alive",
+                        serde_json::Value::String(text.trim().to_string()),
+                    );
+                }
+                self.keep_alive = text;
+                Task::none()
+            }
+
+            Message::EditChatKeepAlive(text) => {
+                self.user_information.keep_alive = (!text.trim().is_empty()).then_some(text);
+                self.remember_typed_chat_setting();
+                Task::none()
+            }
+
+            Message::ToggleModelManager => {
+                self.pending_model_delete = None;
+                if self.app_state.gui_state == GUIState::Models {
+                    self.app_state.gui_state = GUIState::AdvancedSettings;
+                    self.begin_page_transition();
+                    return Task::none();
+                }
+                self.app_state.gui_state = GUIState::Models;
+                self.begin_page_transition();
+                self.refresh_installed_models()
+            }
+
+            Message::InstalledModelsLoaded(result) => {
+                match result {
+                    Ok(mut models) => {
+                        models.sort_by(|left, right| left.name.cmp(&right.name));
+                        // The manager's list is fresher than the periodic poll.
+                        *self.app_state.bots_list.lock().unwrap() =
+                            models.iter().map(|model| model.name.clone()).collect();
+                        if !self
+                            .selected_installed_model
+                            .as_ref()
+                            .is_some_and(|selected| {
+                                models.iter().any(|model| &model.name == selected)
+                            })
+                        {
+                            self.selected_installed_model =
+                                models.first().map(|model| model.name.clone());
+                        }
+                        self.installed_models = models;
+                        self.installed_models_error = None;
+                    }
+                    Err(error) => {
+                        self.installed_models.clear();
+                        self.installed_models_error = Some(error);
+                    }
+                }
+                self.load_selected_model_details()
+            }
+
+            Message::SelectInstalledModel(model) => {
+                self.selected_installed_model = Some(model);
+                self.pending_model_delete = None;
+                self.model_copy_name.clear();
+                self.load_selected_model_details()
+            }
+
+            Message::ModelDetailsLoaded(model, result) => {
+                self.model_details.insert(model, result);
+                Task::none()
+            }
+
+            Message::ShowModelText(text) => {
+                self.model_text = text;
+                Task::none()
+            }
+
+            Message::EditModelCopyName(name) => {
+                self.model_copy_name = name;
+                Task::none()
+            }
+
+            Message::CopyInstalledModel | Message::RenameInstalledModel => {
+                let rename = matches!(message, Message::RenameInstalledModel);
+                let Some(source) = self.selected_installed_model.clone() else {
+                    return Task::none();
+                };
+                let destination = self.model_copy_name.trim().to_string();
+                if destination.is_empty() || destination == source {
+                    self.set_debug_message(DebugMessage {
+                        message: "Enter a new name for the model.".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                }
+                let host = self.ollama_host();
+                self.selected_installed_model = Some(destination.clone());
+                self.model_copy_name.clear();
+                Task::perform(
+                    async move {
+                        model_manager::copy_model(&host, &source, &destination).await?;
+                        if rename {
+                            model_manager::delete_model(&host, &source).await?;
+                            Ok(format!("Renamed {source} to {destination}."))
+                        } else {
+                            Ok(format!("Copied {source} to {destination}."))
+                        }
+                    },
+                    Message::InstalledModelChanged,
+                )
+            }
+
+            Message::DeleteInstalledModel(model) => {
+                if self.pending_model_delete.as_ref() != Some(&model) {
+                    self.pending_model_delete = Some(model);
+                    return Task::none();
+                }
+                self.pending_model_delete = None;
+                let host = self.ollama_host();
+                Task::perform(
+                    async move {
+                        model_manager::delete_model(&host, &model).await?;
+                        Ok(format!("Deleted {model}."))
+                    },
+                    Message::InstalledModelChanged,
+                )
+            }
+
+            Message::InstalledModelChanged(result) => {
+                match result {
+                    Ok(message) => self.set_debug_message(DebugMessage {
+                        message,
+                        is_error: false,
+                    }),
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Model change failed: {error}"),
+                        is_error: true,
+                    }),
+                }
+                self.model_details.clear();
+                self.refresh_installed_models()
+            }
+
+            Message::OpenModelfileEditor(source) => {
+                self.app_state.gui_state = GUIState::ModelfileEditor;
+                self.begin_page_transition();
+                self.modelfile_log.lock().unwrap().clear();
+                let Some(model) = source else {
+                    self.seed_modelfile("FROM \n".to_string());
+                    return Task::none();
+                };
+                if let Some(Ok(details)) = self.model_details.get(&model) {
+                    let text = modelfile::derive_from(&model, &details.modelfile);
+                    self.seed_modelfile(text);
+                    return Task::none();
+                }
+                self.seed_modelfile(String::new());
+                let host = self.ollama_host();
+                Task::perform(
+                    model_manager::show_model(host, model.clone()),
+                    move |result| Message::ModelfileSourceLoaded(model.clone(), result),
+                )
+            }
+
+            Message::ModelfileSourceLoaded(model, result) => {
+                match &result {
+                    Ok(details) => {
+                        let text = modelfile::derive_from(&model, &details.modelfile);
+                        self.seed_modelfile(text);
+                    }
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not read the Modelfile of {model}: {error}"),
+                        is_error: true,
+                    }),
+                }
+                self.model_details.insert(model, result);
+                Task::none()
+            }
+
+            Message::CloseModelfileEditor => {
+                self.app_state.gui_state = GUIState::Models;
+                self.begin_page_transition();
+                Task::none()
+            }
+
+            Message::EditModelfile(action) => {
+                let edited = action.is_edit();
+                self.modelfile_editor.perform(action);
+                if edited {
+                    self.modelfile_issues = Modelfile::parse(&self.modelfile_editor.text())
+                        .err()
+                        .unwrap_or_default();
+                }
+                Task::none()
+            }
+
+            Message::EditNewModelName(name) => {
+                self.new_model_name = name;
+                Task::none()
+            }
+
+            Message::CreateModelFromModelfile => {
+                let name = self.new_model_name.trim().to_string();
+                if self.creating_model.is_some() {
+                    return Task::none();
+                }
+                if name.is_empty() {
+                    self.set_debug_message(DebugMessage {
+                        message: "Enter a name for the new model.".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                }
+                let modelfile = match Modelfile::parse(&self.modelfile_editor.text()) {
+                    Ok(modelfile) => modelfile,
+                    Err(issues) => {
+                        self.modelfile_issues = issues;
+                        return Task::none();
+                    }
+                };
+                self.modelfile_log.lock().unwrap().clear();
+                self.creating_model = Some(name.clone());
+                Task::perform(
+                    modelfile::create_model(
+                        self.ollama_host(),
+                        modelfile.create_request(&name),
+                        self.modelfile_log.clone(),
+                    ),
+                    move |result| Message::ModelCreated(name.clone(), result),
+                )
+            }
+
+            Message::ModelCreated(name, result) => {
+                self.creating_model = None;
+                match result {
+                    Ok(()) => {
+                        self.set_debug_message(DebugMessage {
+                            message: format!("Created {name}."),
+                            is_error: false,
+                        });
+                        self.selected_installed_model = Some(name);
+                        self.new_model_name.clear();
+                        self.model_details.clear();
+                        self.refresh_installed_models()
+                    }
+                    Err(error) => {
+                        self.set_debug_message(DebugMessage {
+                            message: format!("Could not create {name}: {error}"),
+                            is_error: true,
+                        });
+                        Task::none()
+                    }
+                }
+            }
+
+            Message::ToggleCompare => {
+                if self.app_state.gui_state == GUIState::Compare {
+                    self.app_state.gui_state = GUIState::Main;
+                } else {
+                    self.app_state.gui_state = GUIState::Compare;
+                    if self.compare_models.is_empty() {
+                        self.compare_models
+                            .extend(self.user_information.model.clone());
+                    }
+                }
+                self.begin_page_transition();
+                Task::none()
+            }
+
+            Message::ToggleCompareModel(model) => {
+                if let Some(index) = self.compare_models.iter().position(|item| item == &model) {
+                    self.compare_models.remove(index);
+                } else if self.compare_models.len() < MAX_COMPARE_MODELS {
+                    self.compare_models.push(model);
+                }
+                Task::none()
+            }
+
+            Message::EditComparePrompt(prompt) => {
+                self.compare_prompt = prompt;
+                Task::none()
+            }
+
+            Message::StartComparison => self.start_comparison(),
+
+            Message::StopComparison => {
+                for column in &self.compare_columns {
+                    if let Some(job) = self.active_prompts.get(&column.chat_id) {
+                        job.cancel.store(true, Ordering::Relaxed);
+                    }
+                }
+                Task::none()
+            }
+
+            Message::ContinueWithComparison(chat_id) => {
+                if self.active_prompts.contains_key(&chat_id) {
+                    return Task::none();
+                }
+                let Some(session) = self.temporary_chats.remove(&chat_id) else {
+                    return Task::none();
+                };
+                self.compare_columns
+                    .retain(|column| column.chat_id != chat_id);
+                let chat = session.chat_history.lock().unwrap().clone();
+                self.save_chat_snapshot(
+                    chat_id.clone(),
+                    &chat,
+                    session.web_search_enabled,
+                    session.settings,
+                );
+                self.app_state.gui_state = GUIState::Main;
+                self.update(Message::OpenChat(chat_id))
+            }
+
+            Message::ToggleGgufImport => {
+                self.gguf_import_open = !self.gguf_import_open;
+                Task::none()
+            }
+
+            Message::PickGgufFile => Task::perform(
+                async {
+                    rfd::FileDialog::new()
+                        .add_filter("GGUF model", &["gguf"])
+                        .pick_file()
+                },
+                Message::GgufFilePicked,
+            ),
+
+            Message::GgufFilePicked(Some(path)) => {
+                if self.gguf_model_name.trim().is_empty() {
+                    self.gguf_model_name = gguf_import::model_name_for(&path);
+                }
+                self.gguf_path = Some(path);
+                Task::none()
+            }
+
+            Message::GgufFilePicked(None) => Task::none(),
+
+            Message::EditGgufModelName(name) => {
+                self.gguf_model_name = name;
+                Task::none()
+            }
+
+            Message::EditGgufTemplate(action) => {
+                self.gguf_template.perform(action);
+                Task::none()
+            }
+
+            Message::EditGgufSystem(system) => {
+                self.gguf_system = system;
+                Task::none()
+            }
+
+            Message::ImportGguf => {
+                if self.active_import.is_some() {
+                    return Task::none();
+                }
+                let model = self.gguf_model_name.trim().to_string();
+                let Some(path) = self.gguf_path.clone().filter(|_| !model.is_empty()) else {
+                    self.set_debug_message(DebugMessage {
+                        message: "Choose a GGUF file and a name for the model.".to_string(),
+                        is_error: true,
+                    });
+                    return Task::none();
+                };
+                let import = GgufImport {
+                    path,
+                    model: model.clone(),
+                    template: self.gguf_template.text(),
+                    system: self.gguf_system.clone(),
+                };
+                let progress = Arc::new(Mutex::new(ImportProgress::default()));
+                let log = Arc::new(Mutex::new(Vec::new()));
+                let cancel = Arc::new(AtomicBool::new(false));
+                self.active_import = Some(ActiveImport {
+                    model: model.clone(),
+                    progress: progress.clone(),
+                    log: log.clone(),
+                    cancel: cancel.clone(),
+                });
+                Task::perform(
+                    gguf_import::import_gguf(self.ollama_host(), import, progress, log, cancel),
+                    move |result| Message::GgufImportFinished(model.clone(), result),
+                )
+            }
+
+            Message::CancelGgufImport => {
+                if let Some(import) = &self.active_import {
+                    import.cancel.store(true, Ordering::Relaxed);
+                }
+                Task::none()
+            }
+
+            Message::GgufImportFinished(model, result) => {
+                self.active_import = None;
+                match result {
+                    Ok(PullOutcome::Completed) => {
+                        self.set_debug_message(DebugMessage {
+                            message: format!("Imported {model}."),
+                            is_error: false,
+                        });
+                        self.gguf_import_open = false;
+                        self.gguf_path = None;
+                        self.gguf_model_name.clear();
+                        self.selected_installed_model = Some(model);
+                    }
+                    Ok(PullOutcome::Cancelled) => self.set_debug_message(DebugMessage {
+                        message: format!("Cancelled importing {model}."),
+                        is_error: false,
+                    }),
+                    Err(error) => self.set_debug_message(DebugMessage {
+                        message: format!("Could not import {model}: {error}"),
+                        is_error: true,
+                    }),
+                }
+                self.model_details.clear();
+                self.refresh_installed_models()
+            }
+
             Message::SystemPromptChange(system_prompt) => {
                 self.system_prompt.system_prompt = Some(system_prompt);
+                self.remember_chat_settings();
                 Task::none()
             }
 
             Message::InstallModel(model_install) => {
-                Channels::send_request_to_channel(
-                    Arc::clone(&self.channels.debug_channel),
-                    DebugMessage {
-                        message: format!("Installing model... {}", model_install),
-                        is_error: false,
-                    },
-                );
+                let model = model_install.trim().to_string();
+                let already_listed = self
+                    .active_pull
+                    .as_ref()
+                    .is_some_and(|pull| pull.model == model)
+                    || self.pull_queue.contains(&model);
+                if model.is_empty() || already_listed {
+                    return Task::none();
+                }
+                self.installing_model.clear();
+                self.pull_queue.push_back(model);
+                self.start_next_pull()
+            }
 
-                let ip = self.user_information.ip_address.clone();
-                let ollama = Ollama::builder()
-                    .host(format!("http://{}", ip.ip))
-                    .port(convert_port_to_u16(ip.port))
-                    .build();
-                let channels = self.channels.clone();
+            Message::CancelPull => {
+                if let Some(pull) = &self.active_pull {
+                    pull.cancel.store(true, Ordering::Relaxed);
+                }
+                Task::none()
+            }
 
-                Task::perform(
-                    async move {
-                        match ollama.pull_model(model_install.clone(), false).await {
-                            Ok(outcome) => {
-                                println!(
-                                    "Model {} installed successfully: {}",
-                                    model_install, outcome.message
-                                );
-                                Channels::send_request_to_channel(
-                                    Arc::clone(&channels.debug_channel),
-                                    DebugMessage {
-                                        message: format!(
-                                            "Installed model {}: {}",
-                                            model_install, outcome.message
-                                        ),
-                                        is_error: false,
-                                    },
-                                );
-                            }
-                            Err(outcome) => {
-                                println!(
-                                    "Failed to install model {}: {:?}",
-                                    model_install, outcome
-                                );
-                                Channels::send_request_to_channel(
-                                    Arc::clone(&channels.debug_channel),
-                                    DebugMessage {
-                                        message: format!(
-                                            "Failed to install model {}",
-                                            model_install
-                                        ),
-                                        is_error: true,
-                                    },
-                                );
-                            }
-                        };
-                    },
-                    Message::AsyncResult,
-                )
+            Message::RemoveQueuedPull(index) => {
+                self.pull_queue.remove(index);
+                Task::none()
+            }
+
+            Message::PullFinished(model, result) => {
+                self.active_pull = None;
+                match result {
+                    Ok(PullOutcome::Completed) => self.set_debug_message(DebugMessage {
+                        message: format!("Installed model {model}."),
+                        is_error: false,
+                    }),
+                    Ok(PullOutcome::Cancelled) => self.set_debug_message(DebugMessage {
+                        message: format!("Cancelled installing {model}."),
+                        is_error: false,
+                    }),
+                    Err(error) => {
+                        eprintln!("Failed to install model {model}: {error}");
+                        self.set_debug_message(DebugMessage {
+                            message: format!("Failed to install model {model}: {error}"),
+                            is_error: true,
+                        });
+                    }
+                }
+                Task::batch([self.refresh_installed_models(), self.start_next_pull()])
             }
 
             Message::ModelChange(model) => {
-                self.user_information.model = Some(model.clone());
-                self.user_information.thinking_supported = None;
-                self.user_information.vision_supported = None;
-                self.user_information.image_generation_supported = None;
-                self.user_information.thinking_levels = vec![ThinkingLevel::Off];
-                // Reasoning support and accepted effort values vary by model. Do not carry an
-                // effort setting across models while capability detection is still in flight.
-                self.user_information.thinking_level = ThinkingLevel::Off;
-                let ip = self.user_information.ip_address.clone();
-                Task::perform(
-                    async move {
-                        let url = format!("http://{}:{}/api/show", ip.ip, ip.port);
-                        let result = reqwest::Client::new()
-                            .post(url)
-                            .json(&serde_json::json!({ "model": model }))
-                            .send()
-                            .await
-                            .ok();
-                        let capabilities = match result {
-                            Some(response) if response.status().is_success() => response
-                                .json::<serde_json::Value>()
-                                .await
-                                .ok()
-                                .and_then(|json| model_capabilities(&json)),
-                            _ => None,
-                        };
-                        (model, capabilities)
-                    },
-                    |(model, capabilities)| Message::ModelCapabilitiesKnown(model, capabilities),
-                )
+                let task = self.select_model(model);
+                self.remember_chat_settings();
+                self.persist_server_profiles();
+                task
             }
 
             Message::InstallationPrompt => open_url("https://ollama.com/download".to_string()),
@@ -3456,6 +5847,15 @@ impl Program {
                 },
             ));
         }
+        if self.control_api.enabled && !self.control_api.token.is_empty() {
+            subscriptions.push(
+                Subscription::run_with(
+                    (self.control_api.port, self.control_api.token.clone()),
+                    control_api::serve,
+                )
+                .map(Message::ControlApi),
+            );
+        }
         // The UI has a restrained ambient motion layer (status pulse, loading
         // shimmer, page reveals) in addition to response and sidebar animation.
         subscriptions
@@ -3472,7 +5872,7 @@ impl Default for Program {
         let data_prompts: String = match fs::read_to_string(&prompts_path) {
             Ok(dp) => dp,
             Err(_e) => {
-                println!("An error occurred reading default prompts");
+                eprintln!("An error occurred reading default prompts");
                 json_error.push_str("| Failed to read the installed default prompts");
                 "{}".to_string()
             }
@@ -3482,7 +5882,7 @@ impl Default for Program {
             match serde_json::from_str(&data_prompts) {
                 Ok(sp) => sp,
                 Err(_e) => {
-                    println!("An error occurred reading default prompts (bad format)");
+                    eprintln!("An error occurred reading default prompts (bad format)");
                     json_error.push_str(
                         "| Failed to read: ./config/defaultprompts.json (bad formatting)",
                     );
@@ -3501,12 +5901,10 @@ impl Default for Program {
             system_prompts.first().cloned()
         };
 
-        println!("Loaded system prompts:\n{:?} ", system_prompts);
-
         let settings = match load_settings_text() {
             Some(dp) => dp,
             None => {
-                println!("An error occurred reading settings");
+                eprintln!("An error occurred reading settings");
                 json_error.push_str("| Failed to read settings");
                 "{}".to_string()
             }
@@ -3516,7 +5914,7 @@ impl Default for Program {
             match serde_json::from_str(&settings) {
                 Ok(sp) => sp,
                 Err(_e) => {
-                    println!("An error occurred reading settings (bad format)");
+                    eprintln!("An error occurred reading settings (bad format)");
                     json_error.push_str(
                     "| Failed to read: ./config/settings.json (bad formatting. reset to default)",
                 );
@@ -3550,6 +5948,16 @@ impl Default for Program {
             .cloned()
             .and_then(|value| serde_json::from_value::<DynamicPromptSettings>(value).ok())
             .unwrap_or_default();
+        let sampling_settings = settings_hmap
+            .get("sampling")
+            .cloned()
+            .and_then(|value| serde_json::from_value::<SamplingSettings>(value).ok())
+            .unwrap_or_default();
+        let keep_alive = settings_hmap
+            .get("keep_alive")
+            .and_then(serde_json::Value::as_str)
+            .unwrap_or_default()
+            .to_string();
         let web_search_settings = settings_hmap
             .get("web_search")
             .cloned()
@@ -3574,6 +5982,28 @@ impl Default for Program {
             MIN_CONTEXT_TOKENS,
             MAX_CONTEXT_TOKENS,
         );
+        let (mut server_profiles, mut active_server_profile) = server_profiles::load_profiles(
+            settings_hmap.get("server_profiles"),
+            settings_hmap.get("active_server_profile"),
+        );
+        let mut environment = EnvironmentOverrides::from_env();
+        let control_api = settings_hmap
+            .get("control_api")
+            .cloned()
+            .and_then(|value| serde_json::from_value::<ControlApiSettings>(value).ok())
+            .unwrap_or_default();
+        if let Some(value) = &environment.ollama_host {
+            match environment::parse_ollama_host(value) {
+                Ok(host) => {
+                    active_server_profile =
+                        environment::apply_ollama_host(&mut server_profiles, host);
+                }
+                Err(error) => {
+                    json_error.push_str(&format!("| Ignored OLLAMA_HOST: {error}"));
+                    environment.ollama_host_error = Some(error);
+                }
+            }
+        }
         let language = match settings_hmap
             .get("language")
             .and_then(|value| value.as_str())
@@ -3619,15 +6049,15 @@ impl Default for Program {
             .as_ref()
             .and_then(|chat| chat.web_search_enabled)
             .unwrap_or(web_search_settings.enabled);
-        let current_chat =
-            restored_chat
-                .as_ref()
-                .map(SavedChat::to_current)
-                .unwrap_or(CurrentChat {
-                    chats: vec![],
-                    messages: vec![],
-                    bot_responding: false,
-                });
+        let current_chat = restored_chat
+            .as_ref()
+            .map(|chat| chat.to_current(&chat_storage_dir.join(CHAT_IMAGE_DIR)))
+            .unwrap_or(CurrentChat {
+                chats: vec![],
+                messages: vec![],
+                bot_responding: false,
+                context_summary: None,
+            });
 
         let history: History = History {
             began_logging: Local::now().to_rfc3339(),
@@ -3671,12 +6101,54 @@ impl Default for Program {
             temporary_chat: false,
             web_search_for_chat,
             web_search_settings,
+            chat_defaults: ChatSettings::default(),
             current_chat_id,
             open_chat_dirty: false,
+            editing_message: None,
             saved_chats,
             chat_storage_dir,
             code_checking_enabled,
             dynamic_prompt_settings,
+            sampling_inputs: sampling_inputs(&sampling_settings),
+            keep_alive,
+            running_models: Vec::new(),
+            running_models_error: None,
+            installed_models: Vec::new(),
+            installed_models_error: None,
+            selected_installed_model: None,
+            model_details: HashMap::new(),
+            model_text: ModelText::Modelfile,
+            model_copy_name: String::new(),
+            pending_model_delete: None,
+            modelfile_editor: iced::widget::text_editor::Content::new(),
+            new_model_name: String::new(),
+            modelfile_issues: Vec::new(),
+            modelfile_log: Arc::new(Mutex::new(Vec::new())),
+            creating_model: None,
+            gguf_import_open: false,
+            compare_models: Vec::new(),
+            compare_prompt: String::new(),
+            compare_columns: Vec::new(),
+            gguf_path: None,
+            server_health: Vec::new(),
+            server_profiles_generation: 0,
+            server_profiles: server_profiles.clone(),
+            active_server_profile,
+            environment,
+            control_api_port_input: control_api.port.to_string(),
+            control_api,
+            control_streams: Vec::new(),
+            chat_import: None,
+            chat_search_query: String::new(),
+            chat_search_index: SearchIndex::default(),
+            chat_search_indexing: false,
+            chat_search_results: Vec::new(),
+            search_highlight: None,
+            gguf_model_name: String::new(),
+            gguf_template: iced::widget::text_editor::Content::new(),
+            gguf_system: String::new(),
+            active_import: None,
+            sampling_settings,
             max_response_tokens_input: max_response_tokens.to_string(),
             context_tokens_input: context_tokens.to_string(),
             pending_settings: serde_json::Map::new(),
@@ -3688,6 +6160,8 @@ impl Default for Program {
             chat_notice_receiver,
             current_tick: 0,
             installing_model: String::new(),
+            active_pull: None,
+            pull_queue: VecDeque::new(),
 
             debug_message: DebugMessage {
                 message: json_error.clone(),
@@ -3702,6 +6176,7 @@ impl Default for Program {
             chat_messages_cache: Vec::new(),
             chat_thinking_cache: Vec::new(),
             chat_visible_text_cache: Vec::new(),
+            chat_summary_boundary: None,
             chat_model_name_cache: Vec::new(),
             last_copied_text: None,
             last_copied_at: None,
@@ -3711,6 +6186,9 @@ impl Default for Program {
             vision_responses: HashMap::new(),
             markdown_images: HashMap::new(),
             expanded_thinking: HashSet::new(),
+            collapsed_json_nodes: HashSet::new(),
+            structured_output_open: false,
+            response_format_editor: iced::widget::text_editor::Content::new(),
 
             system_prompt: SystemPrompt {
                 system_prompts_as_hashmap: system_prompts_as_prompt,
@@ -3734,11 +6212,10 @@ impl Default for Program {
                 context_tokens,
                 temperature: 7.0,
                 text_size: 24.0,
-                ip_address: HostLocation {
-                    ip: "127.0.0.1".to_string(),
-                    port: "11434".to_string(),
-                },
+                ip_address: server_profiles[active_server_profile].clone(),
                 language,
+                response_format: None,
+                keep_alive: None,
             },
             prompt: Prompt {
                 prompt: String::new(),
@@ -3755,13 +6232,20 @@ impl Default for Program {
                 logs: history,
                 logging,
                 ollama_state: Arc::new(Mutex::new("Offline".to_string())),
-                bots_list: Arc::new(Mutex::new(vec![])),
+                bots_list: Arc::new(Mutex::new(
+                    server_profiles[active_server_profile].models.clone(),
+                )),
             },
         }
     }
 }
 
 pub fn main() -> iced::Result {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.first().map(String::as_str) == Some(cli::COMMAND) {
+        std::process::exit(cli::run(&args[1..]));
+    }
+
     let icon = match image::ImageReader::open(resource_path("assets/icon.ico")) {
         Ok(image_reader) => match image_reader.decode() {
             Ok(img) => {
@@ -3814,20 +6298,48 @@ pub fn main() -> iced::Result {
 
 #[cfg(test)]
 mod tests {
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::{Arc, Mutex};
     use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
     use iced_widget::markdown;
 
+    use crate::app::StoredImage;
+
     use super::{
-        ActivePrompt, Correspondence, CurrentChat, Message, ModelCapabilities, Point, Program,
-        Size, ThinkingLevel, UiResizeTarget, WebSearchState, app_data_dir, canonical_code_language,
-        censor_text, decode_generation_line, disabled_web_tool_message, generated_image_payload,
-        model_capabilities, normalize_code_fence_languages, parse_markdown_items,
-        remote_image_url_is_safe, split_thinking_text,
+        ActivePrompt, ChatImage, ChatSettings, CompareColumn, ControlCommand, ControlReply,
+        ControlRequest, Correspondence, CurrentChat, GUIState, HostLocation, Message,
+        ModelCapabilities, Point, Program, SavedChat, Size, ThinkingLevel, UiResizeTarget,
+        WebSearchState, canonical_code_language, censor_text, chat_history_messages,
+        decode_generation_value, decoded_image_handle, disabled_web_tool_message,
+        generated_image_payload, model_capabilities, normalize_code_fence_languages,
+        parse_markdown_items, remote_image_url_is_safe, remove_unreferenced_images,
+        split_thinking_text, store_chat_images,
     };
 
+    /// A program with no saved, temporary or running chats that stores its
+    /// chats in a directory of its own. Settings still go to the shared test
+    /// data directory, which individual tests must not delete.
+    fn test_program() -> (Program, PathBuf) {
+        static NEXT_STORAGE_DIR: AtomicUsize = AtomicUsize::new(0);
+        let mut program = Program::default();
+        program.active_prompts.clear();
+        program.saved_chats.clear();
+        program.temporary_chats.clear();
+        let storage_dir = std::env::temp_dir().join(format!(
+            "ollama-gui-test-chats-{}-{}-{}",
+            std::process::id(),
+            NEXT_STORAGE_DIR.fetch_add(1, Ordering::Relaxed),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        program.chat_storage_dir.clone_from(&storage_dir);
+        (program, storage_dir)
+    }
+
     fn test_active_prompt(
         chat_history: Arc<Mutex<CurrentChat>>,
         cancel: Arc<AtomicBool>,
@@ -3849,6 +6361,8 @@ mod tests {
             had_image: false,
             web_search_enabled: true,
             temporary: false,
+            replaced_response: None,
+            settings: ChatSettings::default(),
         }
     }
 
@@ -3916,8 +6430,13 @@ mod tests {
                 thinking_seconds: Some(1),
                 sources: Vec::new(),
                 web_search_used: false,
+                stats: None,
+                format_check: None,
+                alternatives: Vec::new(),
+                active_variant: 0,
             }],
             bot_responding: false,
+            context_summary: None,
         };
 
         program.refresh_chat_markdown_cache();
@@ -4062,11 +6581,75 @@ mod tests {
             "done_reason":"length",
             "eval_count":10240
         }"#;
-        let (response, reason) = decode_generation_line(line).unwrap();
+        let (response, reason, _) =
+            decode_generation_value(serde_json::from_str(line).unwrap()).unwrap();
         assert!(response.done);
         assert_eq!(reason.as_deref(), Some("length"));
     }
 
+    #[test]
+    fn decodes_chat_endpoint_message_lines() {
+        let line = r#"{
+            "model":"test",
+            "created_at":"2026-01-01T00:00:00Z",
+            "message":{"role":"assistant","content":"Hi","thinking":"hmm"},
+            "done":false
+        }"#;
+        let (response, reason, stats) =
+            decode_generation_value(serde_json::from_str(line).unwrap()).unwrap();
+        assert_eq!(response.response, "Hi");
+        assert_eq!(response.thinking.as_deref(), Some("hmm"));
+        assert_eq!(reason, None);
+        assert_eq!(stats, None);
+    }
+
+    #[test]
+    fn final_line_carries_generation_statistics() {
+        let line = r#"{
+            "model":"test",
+            "created_at":"2026-01-01T00:00:00Z",
+            "message":{"role":"assistant","content":""},
+            "done":true,
+            "done_reason":"stop",
+            "total_duration":3000000000,
+            "load_duration":500000000,
+            "prompt_eval_count":26,
+            "prompt_eval_duration":100000000,
+            "eval_count":120,
+            "eval_duration":2000000000
+        }"#;
+        let (_, reason, stats) =
+            decode_generation_value(serde_json::from_str(line).unwrap()).unwrap();
+        let stats = stats.unwrap();
+        assert_eq!(reason.as_deref(), Some("stop"));
+        assert_eq!(stats.prompt_eval_count, 26);
+        assert_eq!(stats.eval_count, 120);
+        assert_eq!(stats.tokens_per_second(), Some(60.0));
+        assert_eq!(stats.load_seconds(), 0.5);
+    }
+
+    #[test]
+    fn chat_history_replays_answers_without_reasoning() {
+        let history = chat_history_messages(&[
+            Correspondence::user("Question".into(), Vec::new()),
+            Correspondence::Bot {
+                text: "<think>Work</think>Answer".into(),
+                model: None,
+                thinking_seconds: None,
+                sources: Vec::new(),
+                web_search_used: false,
+                stats: None,
+                format_check: None,
+                alternatives: Vec::new(),
+                active_variant: 0,
+            },
+        ]);
+        assert_eq!(history[0]["role"], "user");
+        assert_eq!(history[0]["content"], "Question");
+        assert_eq!(history[1]["role"], "assistant");
+        assert_eq!(history[1]["content"], "Answer");
+    }
+
     #[test]
     fn explains_disabled_model_web_tool_attempts() {
         assert!(
@@ -4086,8 +6669,13 @@ mod tests {
                 thinking_seconds: None,
                 sources: Vec::new(),
                 web_search_used: false,
+                stats: None,
+                format_check: None,
+                alternatives: Vec::new(),
+                active_variant: 0,
             }],
             bot_responding: false,
+            context_summary: None,
         };
 
         Program::apply_response_metadata(&mut chat, 1, "new-model", 9);
@@ -4113,20 +6701,26 @@ mod tests {
                     thinking_seconds: None,
                     sources: Vec::new(),
                     web_search_used: false,
+                    stats: None,
+                    format_check: None,
+                    alternatives: Vec::new(),
+                    active_variant: 0,
                 },
-                Correspondence::User {
-                    text: "New question".into(),
-                    images: Vec::new(),
-                },
+                Correspondence::user("New question".into(), Vec::new()),
                 Correspondence::Bot {
                     text: "New answer".into(),
                     model: None,
                     thinking_seconds: None,
                     sources: Vec::new(),
                     web_search_used: false,
+                    stats: None,
+                    format_check: None,
+                    alternatives: Vec::new(),
+                    active_variant: 0,
                 },
             ],
             bot_responding: false,
+            context_summary: None,
         };
 
         Program::apply_response_metadata(&mut chat, 2, "new-model", 9);
@@ -4151,30 +6745,17 @@ mod tests {
 
     #[test]
     fn navigating_away_keeps_prompt_running_and_finishes_its_original_chat() {
-        let test_app_data_dir = app_data_dir();
-        let _ = std::fs::remove_dir_all(&test_app_data_dir);
-        let mut program = Program::default();
-        program.active_prompts.clear();
-        program.saved_chats.clear();
-        program.temporary_chats.clear();
-        let test_storage_dir = std::env::temp_dir().join(format!(
-            "ollama-gui-background-test-{}-{}",
-            std::process::id(),
-            SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap()
-                .as_nanos()
-        ));
-        program.chat_storage_dir.clone_from(&test_storage_dir);
+        let (mut program, test_storage_dir) = test_program();
 
         let chat_a_id = "background-chat-a".to_string();
         let chat_a = Arc::new(Mutex::new(CurrentChat {
             chats: Vec::new(),
-            messages: vec![Correspondence::User {
-                text: "Background question".to_string(),
-                images: Vec::new(),
-            }],
+            messages: vec![Correspondence::user(
+                "Background question".into(),
+                Vec::new(),
+            )],
             bot_responding: true,
+            context_summary: None,
         }));
         let cancel = Arc::new(AtomicBool::new(false));
 
@@ -4203,6 +6784,10 @@ mod tests {
             thinking_seconds: None,
             sources: Vec::new(),
             web_search_used: true,
+            stats: None,
+            format_check: None,
+            alternatives: Vec::new(),
+            active_variant: 0,
         });
         chat_a.lock().unwrap().bot_responding = false;
 
@@ -4224,9 +6809,9 @@ mod tests {
             .iter()
             .find(|chat| chat.id == chat_a_id)
             .expect("background chat should be updated in saved chats");
-        assert_eq!(saved_chat.messages.len(), 2);
+        assert_eq!(saved_chat.thread.messages.len(), 2);
         assert_eq!(saved_chat.web_search_enabled, Some(true));
-        let reopened_chat = saved_chat.to_current();
+        let reopened_chat = saved_chat.to_current(&program.chat_image_dir());
         assert!(matches!(
             &reopened_chat.messages[1],
             Correspondence::Bot {
@@ -4249,8 +6834,344 @@ mod tests {
             Some(false)
         );
 
-        std::fs::remove_dir_all(test_storage_dir).unwrap();
-        std::fs::remove_dir_all(test_app_data_dir).unwrap();
+        let _ = std::fs::remove_dir_all(test_storage_dir);
+    }
+
+    #[test]
+    fn server_health_from_before_a_profile_change_is_dropped() {
+        let mut program = Program {
+            server_profiles: vec![HostLocation::default()],
+            ..Program::default()
+        };
+        drop(program.activate_server_profile(0));
+        let stale = program.server_profiles_generation;
+
+        drop(program.update(Message::AddServerProfile));
+        drop(program.update(Message::ServerHealthChecked(
+            stale,
+            vec!["Online".to_string()],
+        )));
+        assert!(program.server_health.is_empty());
+
+        let current = program.server_profiles_generation;
+        let health = vec!["Online".to_string(), "Offline".to_string()];
+        drop(program.update(Message::ServerHealthChecked(current, health.clone())));
+        assert_eq!(program.server_health, health);
+        assert_eq!(*program.app_state.ollama_state.lock().unwrap(), "Offline");
+    }
+
+    #[test]
+    fn compare_columns_do_not_inherit_the_open_chats_options() {
+        let mut program = Program::default();
+        program.active_prompts.clear();
+        if program.system_prompt.system_prompt.is_none() {
+            program.system_prompt.system_prompt = program
+                .system_prompt
+                .system_prompts_as_hashmap
+                .keys()
+                .next()
+                .cloned();
+        }
+        program.user_information.thinking_level = ThinkingLevel::High;
+        program.user_information.response_format = Some("json".to_string());
+        program.user_information.keep_alive = Some("-1".to_string());
+        program.web_search_for_chat = true;
+        program.compare_models = vec!["first-model".to_string(), "second-model".to_string()];
+        program.compare_prompt = "Which is faster?".to_string();
+
+        drop(program.start_comparison());
+        assert_eq!(program.compare_columns.len(), 2);
+        for column in &program.compare_columns {
+            let job = &program.active_prompts[&column.chat_id];
+            assert_eq!(job.settings.model.as_deref(), Some(column.model.as_str()));
+            assert_eq!(job.settings.thinking_level, Some(ThinkingLevel::Off));
+            assert_eq!(job.settings.response_format, None);
+            assert_eq!(job.settings.keep_alive, None);
+            assert!(!job.web_search_enabled);
+        }
+        assert!(program.web_search_for_chat);
+    }
+
+    #[test]
+    fn a_finished_compare_column_continues_as_a_saved_chat() {
+        let (mut program, test_storage_dir) = test_program();
+        program.app_state.gui_state = GUIState::Compare;
+
+        let column_id = "compare-column".to_string();
+        let column_chat = Arc::new(Mutex::new(CurrentChat {
+            chats: Vec::new(),
+            messages: vec![Correspondence::user("Which is faster?".into(), Vec::new())],
+            bot_responding: true,
+            context_summary: None,
+        }));
+        let mut job =
+            test_active_prompt(Arc::clone(&column_chat), Arc::new(AtomicBool::new(false)));
+        job.temporary = true;
+        program.active_prompts.insert(column_id.clone(), job);
+        program.compare_columns.push(CompareColumn {
+            model: "test-model".to_string(),
+            chat_id: column_id.clone(),
+            markdown: Vec::new(),
+        });
+        column_chat
+            .lock()
+            .unwrap()
+            .push_message(Correspondence::Bot {
+                text: "<think>Hmm</think>**Quicksort**".to_string(),
+                model: None,
+                thinking_seconds: None,
+                sources: Vec::new(),
+                web_search_used: false,
+                stats: None,
+                format_check: None,
+                alternatives: Vec::new(),
+                active_variant: 0,
+            });
+        column_chat.lock().unwrap().bot_responding = false;
+
+        drop(program.update(Message::PromptFinished(column_id.clone())));
+        assert!(program.temporary_chats.contains_key(&column_id));
+        assert!(program.is_compare_chat(&column_id));
+        assert!(!program.compare_columns[0].markdown.is_empty());
+
+        drop(program.update(Message::ContinueWithComparison(column_id.clone())));
+        assert!(program.compare_columns.is_empty());
+        assert!(!program.temporary_chats.contains_key(&column_id));
+        assert!(program.app_state.gui_state == GUIState::Main);
+        assert_eq!(program.current_chat_id, column_id);
+        assert!(program.saved_chats.iter().any(|chat| chat.id == column_id));
+        assert_eq!(
+            program
+                .user_information
+                .chat_history
+                .lock()
+                .unwrap()
+                .messages
+                .len(),
+            2
+        );
+
+        let _ = std::fs::remove_dir_all(test_storage_dir);
+    }
+
+    #[test]
+    fn opening_a_chat_restores_its_settings_and_new_chats_use_the_defaults() {
+        let (mut program, test_storage_dir) = test_program();
+        program.chat_defaults = program.current_chat_settings();
+        let default_temperature = program.user_information.temperature;
+
+        let chat = CurrentChat {
+            chats: Vec::new(),
+            messages: vec![Correspondence::user("Question".into(), Vec::new())],
+            bot_responding: false,
+            context_summary: None,
+        };
+        let settings = ChatSettings {
+            temperature: Some(2.0),
+            max_response_tokens: Some(2_048),
+            ..ChatSettings::default()
+        };
+        program.saved_chats.push(SavedChat::from_current(
+            "settings-chat".into(),
+            "Settings".into(),
+            &chat,
+            false,
+            settings,
+        ));
+
+        drop(program.update(Message::OpenChat("settings-chat".into())));
+        assert_eq!(program.user_information.temperature, 2.0);
+        assert_eq!(program.user_information.max_response_tokens, 2_048);
+        assert_eq!(program.max_response_tokens_input, "2048");
+        let overrides = program
+            .current_chat_settings()
+            .overrides(&program.chat_defaults);
+        assert!(overrides.iter().any(|(label, _)| *label == "Temperature"));
+
+        drop(program.update(Message::UpdateTemperature(4.0)));
+        assert_eq!(program.saved_chats[0].settings.temperature, Some(4.0));
+        assert_eq!(program.chat_defaults.temperature, Some(4.0));
+
+        drop(program.update(Message::NewChat));
+        assert_eq!(program.user_information.temperature, 4.0);
+        assert_ne!(program.user_information.temperature, default_temperature);
+
+        let _ = std::fs::remove_dir_all(test_storage_dir);
+    }
+
+    #[test]
+    fn control_requests_behave_like_gui_actions() {
+        let (mut program, test_storage_dir) = test_program();
+        let chat = CurrentChat {
+            chats: Vec::new(),
+            messages: vec![Correspondence::user("Question".into(), Vec::new())],
+            bot_responding: false,
+            context_summary: None,
+        };
+        program.saved_chats.push(SavedChat::from_current(
+            "api-chat".into(),
+            "API".into(),
+            &chat,
+            false,
+            ChatSettings::default(),
+        ));
+        let send = |program: &mut Program, command| {
+            let (request, mut reply) = ControlRequest::new(command);
+            drop(program.handle_control_request(request));
+            match reply.try_recv() {
+                Ok(ControlReply::Json(status, value)) => (status, value),
+                _ => panic!("expected a JSON reply"),
+            }
+        };
+
+        let (status, chats) = send(&mut program, ControlCommand::ListChats);
+        assert_eq!(status, 200);
+        assert_eq!(chats[0]["id"], "api-chat");
+        let (status, _) = send(&mut program, ControlCommand::OpenChat("missing".into()));
+        assert_eq!(status, 404);
+
+        program.prompt.prompt = "Half-typed draft".into();
+        program.user_information.model = None;
+        let (status, error) = send(
+            &mut program,
+            ControlCommand::SendPrompt {
+                chat_id: Some("api-chat".into()),
+                prompt: "Hello".into(),
+                images: Vec::new(),
+            },
+        );
+        assert_eq!(status, 400);
+        assert_eq!(error["error"], "Select a model before sending a message.");
+        assert_eq!(program.current_chat_id, "api-chat");
+        assert_eq!(program.prompt.prompt, "Half-typed draft");
+
+        // Errors from `prepare_prompt_in` arrive through the debug channel.
+        program.user_information.model = Some("test-model".into());
+        program.system_prompt.system_prompt = None;
+        let (status, error) = send(
+            &mut program,
+            ControlCommand::SendPrompt {
+                chat_id: None,
+                prompt: "Hello".into(),
+                images: Vec::new(),
+            },
+        );
+        assert_eq!(status, 400);
+        assert_eq!(
+            error["error"],
+            "Could not get system prompt, is it selected?"
+        );
+
+        let _ = std::fs::remove_dir_all(test_storage_dir);
+    }
+
+    #[test]
+    fn saved_chat_images_reload_and_are_collected_once_unreferenced() {
+        let image_dir = std::env::temp_dir().join(format!(
+            "ollama-gui-image-store-test-{}-{}",
+            std::process::id(),
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let bytes = png.into_inner();
+        let image = ChatImage::new(
+            "pixel.png".into(),
+            "image/png".into(),
+            bytes.clone(),
+            decoded_image_handle(&bytes).unwrap(),
+        );
+        let image_digest = image.digest.clone();
+        assert_eq!(image_digest.len(), 64);
+        let chat = CurrentChat {
+            chats: Vec::new(),
+            messages: vec![Correspondence::user("What is this?".into(), vec![image])],
+            bot_responding: false,
+            context_summary: None,
+        };
+
+        store_chat_images(&image_dir, &chat.messages).unwrap();
+        let saved = SavedChat::from_current(
+            "chat-1".into(),
+            "Image".into(),
+            &chat,
+            false,
+            ChatSettings::default(),
+        );
+        let reopened = saved.to_current(&image_dir);
+        for digest in ["../pixel.png".to_string(), image_digest.to_uppercase()] {
+            let stored = StoredImage {
+                name: "pixel.png".into(),
+                mime_type: "image/png".into(),
+                digest,
+            };
+            assert!(ChatImage::load_stored(&image_dir, &stored).is_none());
+        }
+        assert!(matches!(
+            &reopened.messages[0],
+            Correspondence::User { images, .. } if images.len() == 1 && images[0].name == "pixel.png"
+        ));
+        assert!(matches!(
+            &reopened.messages[0],
+            Correspondence::User { images, .. } if images[0].digest == image_digest
+        ));
+
+        remove_unreferenced_images(&image_dir, std::slice::from_ref(&saved));
+        assert_eq!(std::fs::read_dir(&image_dir).unwrap().count(), 1);
+        remove_unreferenced_images(&image_dir, &[]);
+        assert_eq!(std::fs::read_dir(&image_dir).unwrap().count(), 0);
+
+        std::fs::remove_dir_all(image_dir).unwrap();
+    }
+
+    #[test]
+    fn resaving_an_edited_chat_removes_attachments_it_no_longer_uses() {
+        let (mut program, test_storage_dir) = test_program();
+        let mut png = std::io::Cursor::new(Vec::new());
+        image::RgbaImage::new(2, 2)
+            .write_to(&mut png, image::ImageFormat::Png)
+            .unwrap();
+        let bytes = png.into_inner();
+        let image = ChatImage::new(
+            "pixel.png".into(),
+            "image/png".into(),
+            bytes.clone(),
+            decoded_image_handle(&bytes).unwrap(),
+        );
+        let mut chat = CurrentChat {
+            chats: Vec::new(),
+            messages: vec![Correspondence::user("What is this?".into(), vec![image])],
+            bot_responding: false,
+            context_summary: None,
+        };
+        let image_count = |program: &Program| {
+            std::fs::read_dir(program.chat_image_dir()).map_or(0, Iterator::count)
+        };
+
+        program.save_chat_snapshot("chat-1".into(), &chat, false, ChatSettings::default());
+        assert_eq!(image_count(&program), 1);
+        let with_image = chat.messages.clone();
+        chat.messages = vec![Correspondence::user("Never mind".into(), Vec::new())];
+        program.save_chat_snapshot("chat-1".into(), &chat, false, ChatSettings::default());
+        assert_eq!(program.saved_chats.len(), 1);
+        assert_eq!(image_count(&program), 0);
+
+        // While chats.json cannot be written it still refers to the image.
+        chat.messages = with_image;
+        program.save_chat_snapshot("chat-1".into(), &chat, false, ChatSettings::default());
+        let chats_file = program.chat_storage_dir.join("chats.json");
+        std::fs::remove_file(&chats_file).unwrap();
+        std::fs::create_dir(&chats_file).unwrap();
+        chat.messages = vec![Correspondence::user("Never mind".into(), Vec::new())];
+        program.save_chat_snapshot("chat-1".into(), &chat, false, ChatSettings::default());
+        assert_eq!(image_count(&program), 1);
+
+        let _ = std::fs::remove_dir_all(test_storage_dir);
     }
 
     #[test]
@@ -0,0 +1,210 @@
+use ollama_rs::models::LocalModel;
+use serde_json::Value;
+
+use crate::app::HostLocation;
+
+/// What `/api/show` reports about an installed model, flattened for display.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ModelDetails {
+    pub family: String,
+    pub quantization: String,
+    /// Ollama's rounded label, e.g. `8.0B`, or the exact count when only that is known.
+    pub parameter_size: String,
+    pub context_length: Option<u64>,
+    pub license: String,
+    pub modelfile: String,
+    pub template: String,
+}
+
+impl ModelDetails {
+    pub fn from_show(body: &Value) -> Self {
+        let text = |value: Option<&Value>| {
+            value
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        let details = body.get("details");
+        let model_info = body.get("model_info");
+        let architecture = model_info
+            .and_then(|info| info.get("general.architecture"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        // Context length is keyed by architecture, e.g. `llama.context_length`.
+        let context_length = model_info
+            .and_then(|info| info.get(format!("{architecture}.context_length")))
+            .and_then(Value::as_u64);
+        let parameter_size = match text(details.and_then(|details| details.get("parameter_size"))) {
+            size if !size.is_empty() => size,
+            _ => model_info
+                .and_then(|info| info.get("general.parameter_count"))
+                .and_then(Value::as_u64)
+                .map(format_parameter_count)
+                .unwrap_or_default(),
+        };
+        Self {
+            family: text(details.and_then(|details| details.get("family"))),
+            quantization: text(details.and_then(|details| details.get("quantization_level"))),
+            parameter_size,
+            context_length,
+            license: text(body.get("license")),
+            modelfile: text(body.get("modelfile")),
+            template: text(body.get("template")),
+        }
+    }
+
+    /// The first line of the license, which usually names it.
+    pub fn license_name(&self) -> &str {
+        self.license
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or_default()
+    }
+}
+
+/// Which stored text of the selected model the manager shows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ModelText {
+    Modelfile,
+    Template,
+    License,
+}
+
+pub fn format_parameter_count(count: u64) -> String {
+    let count = count as f64;
+    if count >= 1e9 {
+        format!("{:.1}B", count / 1e9)
+    } else {
+        format!("{:.0}M", count / 1e6)
+    }
+}
+
+/// Formats Ollama's RFC 3339 `modified_at` as a local date and time.
+pub fn format_modified_at(modified_at: &str) -> String {
+    chrono::DateTime::parse_from_rfc3339(modified_at)
+        .map(|time| {
+            time.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|_| modified_at.to_string())
+}
+
+/// Lists installed models through `/api/tags`.
+pub async fn list_models(host: HostLocation) -> Result<Vec<LocalModel>, String> {
+    let response = host
+        .client()?
+        .get(format!("{}/api/tags", host.base_url()))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(ollama_failure(response).await);
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| error.to_string())?;
+    serde_json::from_value(body.get("models").cloned().unwrap_or_default())
+        .map_err(|error| error.to_string())
+}
+
+pub async fn show_model(host: HostLocation, model: String) -> Result<ModelDetails, String> {
+    let response = host
+        .client()?
+        .post(format!("{}/api/show", host.base_url()))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if !response.status().is_success() {
+        return Err(ollama_failure(response).await);
+    }
+    let body = response
+        .json::<Value>()
+        .await
+        .map_err(|error| error.to_string())?;
+    Ok(ModelDetails::from_show(&body))
+}
+
+pub async fn copy_model(
+    host: &HostLocation,
+    source: &str,
+    destination: &str,
+) -> Result<(), String> {
+    let response = host
+        .client()?
+        .post(format!("{}/api/copy", host.base_url()))
+        .json(&serde_json::json!({ "source": source, "destination": destination }))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(ollama_failure(response).await)
+    }
+}
+
+pub async fn delete_model(host: &HostLocation, model: &str) -> Result<(), String> {
+    let response = host
+        .client()?
+        .delete(format!("{}/api/delete", host.base_url()))
+        .json(&serde_json::json!({ "model": model }))
+        .send()
+        .await
+        .map_err(|error| error.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(ollama_failure(response).await)
+    }
+}
+
+async fn ollama_failure(response: reqwest::Response) -> String {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|body| {
+            body.get("error")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .unwrap_or_else(|| format!("Ollama HTTP {status}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn show_response_is_flattened_for_display() {
+        let details = ModelDetails::from_show(&serde_json::json!({
+            "license": "\nLLAMA 3.2 COMMUNITY LICENSE AGREEMENT\nMore text",
+            "modelfile": "FROM llama3.2",
+            "template": "{{ .Prompt }}",
+            "details": {"family": "llama", "parameter_size": "3.2B", "quantization_level": "Q4_K_M"},
+            "model_info": {"general.architecture": "llama", "llama.context_length": 131072}
+        }));
+        assert_eq!(details.family, "llama");
+        assert_eq!(details.quantization, "Q4_K_M");
+        assert_eq!(details.parameter_size, "3.2B");
+        assert_eq!(details.context_length, Some(131_072));
+        assert_eq!(
+            details.license_name(),
+            "LLAMA 3.2 COMMUNITY LICENSE AGREEMENT"
+        );
+    }
+
+    #[test]
+    fn parameter_count_is_used_without_a_size_label() {
+        let details = ModelDetails::from_show(&serde_json::json!({
+            "model_info": {"general.architecture": "gemma3", "general.parameter_count": 494_032_768u64}
+        }));
+        assert_eq!(details.parameter_size, "494M");
+        assert_eq!(details.context_length, None);
+        assert_eq!(format_parameter_count(8_030_261_248), "8.0B");
+    }
+}
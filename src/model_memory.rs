@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// A model Ollama currently holds in memory, as reported by `/api/ps`.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct LoadedModel {
+    pub name: String,
+    /// Total bytes in memory; `size_vram` of them are on the GPU.
+    pub size: u64,
+    pub size_vram: u64,
+    /// RFC 3339 time at which Ollama unloads the model.
+    pub expires_at: String,
+}
+
+impl LoadedModel {
+    pub fn memory_summary(&self) -> String {
+        let ram = self.size.saturating_sub(self.size_vram);
+        match (self.size_vram, ram) {
+            (0, ram) => format!("{} RAM", format_bytes(ram)),
+            (vram, 0) => format!("{} VRAM", format_bytes(vram)),
+            (vram, ram) => format!("{} VRAM · {} RAM", format_bytes(vram), format_bytes(ram)),
+        }
+    }
+
+    /// How long the model stays resident. Ollama reports models kept forever
+    /// with an expiry hundreds of years away.
+    pub fn expiry_summary(&self, now: DateTime<Utc>) -> String {
+        let Ok(expires_at) = DateTime::parse_from_rfc3339(&self.expires_at) else {
+            return "expiry unknown".to_string();
+        };
+        let remaining = expires_at.with_timezone(&Utc) - now;
+        if remaining.num_days() > 365 {
+            "kept loaded".to_string()
+        } else if remaining.num_seconds() <= 0 {
+            "unloading".to_string()
+        } else if remaining.num_minutes() < 1 {
+            format!("unloads in {} s", remaining.num_seconds())
+        } else if remaining.num_hours() < 1 {
+            format!("unloads in {} min", remaining.num_minutes())
+        } else {
+            format!(
+                "unloads in {} h {} min",
+                remaining.num_hours(),
+                remaining.num_minutes() % 60
+            )
+        }
+    }
+}
+
+pub fn parse_running_models(body: &Value) -> Vec<LoadedModel> {
+    body.get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|model| serde_json::from_value(model.clone()).ok())
+        .collect()
+}
+
+/// Reads a keep-alive field into the value Ollama expects. Blank text leaves
+/// the server default in place; plain numbers are seconds and anything else
+/// must be a Go duration. As in Ollama, any negative value keeps the model
+/// loaded indefinitely and `0` unloads it after the request.
+pub fn parse_keep_alive(text: &str) -> Result<Option<Value>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if let Ok(seconds) = text.parse::<i64>() {
+        return Ok(Some(Value::from(seconds)));
+    }
+
+    // A Go duration such as `30s`, `10m`, `1h30m` or `-1m`.
+    let mut rest = text.strip_prefix(['-', '+']).unwrap_or(text);
+    if rest.is_empty() {
+        return Err(format!(
+            "\"{text}\" is not a duration. Try 30s, 10m, 1h or -1."
+        ));
+    }
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|character: char| !character.is_ascii_digit() && character != '.')
+            .unwrap_or(rest.len());
+        let unit_end = rest[digits..]
+            .find(|character: char| character.is_ascii_digit())
+            .map_or(rest.len(), |offset| digits + offset);
+        let valid_number = rest[..digits].parse::<f64>().is_ok();
+        let valid_unit = matches!(
+            &rest[digits..unit_end],
+            "ns" | "us" | "µs" | "μs" | "ms" | "s" | "m" | "h"
+        );
+        if !valid_number || !valid_unit {
+            return Err(format!(
+                "\"{text}\" is not a duration. Try 30s, 10m, 1h or -1."
+            ));
+        }
+        rest = &rest[unit_end..];
+    }
+    Ok(Some(Value::String(text.to_string())))
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const GIB: f64 = 1024.0 * 1024.0 * 1024.0;
+    const MIB: f64 = 1024.0 * 1024.0;
+    let bytes = bytes as f64;
+    if bytes >= GIB {
+        format!("{:.1} GB", bytes / GIB)
+    } else {
+        format!("{:.0} MB", bytes / MIB)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keep_alive_accepts_seconds_and_durations() {
+        assert_eq!(parse_keep_alive(" ").unwrap(), None);
+        assert_eq!(parse_keep_alive("-1").unwrap(), Some(Value::from(-1)));
+        assert_eq!(parse_keep_alive("0").unwrap(), Some(Value::from(0)));
+        assert_eq!(
+            parse_keep_alive("1h30m").unwrap(),
+            Some(Value::String("1h30m".to_string()))
+        );
+        assert_eq!(parse_keep_alive("-5").unwrap(), Some(Value::from(-5)));
+        for duration in ["-1m", "+2h", "1.5s", "500ms", "250us", "250µs", "10ns"] {
+            assert_eq!(
+                parse_keep_alive(duration).unwrap(),
+                Some(Value::String(duration.to_string()))
+            );
+        }
+        assert!(parse_keep_alive("10 minutes").is_err());
+        assert!(parse_keep_alive("m").is_err());
+        assert!(parse_keep_alive("-").is_err());
+    }
+
+    #[test]
+    fn ps_response_describes_memory_and_expiry() {
+        let body = serde_json::json!({"models": [
+            {
+                "name": "llama3.2:3b",
+                "model": "llama3.2:3b",
+                "size": 3_221_225_472u64,
+                "size_vram": 2_147_483_648u64,
+                "expires_at": "2026-10-17T12:10:30+00:00"
+            },
+            {"name": "forever", "size": 524_288_000, "expires_at": "2318-01-01T00:00:00Z"}
+        ]});
+        let models = parse_running_models(&body);
+        let now = "2026-10-17T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].memory_summary(), "2.0 GB VRAM · 1.0 GB RAM");
+        assert_eq!(models[0].expiry_summary(now), "unloads in 10 min");
+        assert_eq!(models[1].memory_summary(), "500 MB RAM");
+        assert_eq!(models[1].expiry_summary(now), "kept loaded");
+    }
+}
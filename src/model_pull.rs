@@ -0,0 +1,244 @@
+use std::{
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::Value;
+
+use crate::app::HostLocation;
+
+/// Download state of one blob of a model being pulled.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LayerProgress {
+    pub digest: String,
+    pub total: u64,
+    pub completed: u64,
+}
+
+/// Progress of a streaming `/api/pull`, shared with the view while it runs.
+#[derive(Clone, Debug, Default)]
+pub struct PullProgress {
+    pub status: String,
+    pub layers: Vec<LayerProgress>,
+    /// Smoothed download speed over the whole model.
+    pub bytes_per_second: f64,
+    last_sample: Option<(Instant, u64)>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PullOutcome {
+    Completed,
+    Cancelled,
+}
+
+impl PullProgress {
+    /// Applies one NDJSON line from Ollama. Returns the error it reports, if any.
+    pub fn absorb(&mut self, line: &Value, now: Instant) -> Result<(), String> {
+        if let Some(error) = line.get("error").and_then(Value::as_str) {
+            return Err(error.to_string());
+        }
+        if let Some(status) = line.get("status").and_then(Value::as_str) {
+            self.status = status.to_string();
+        }
+        let Some(digest) = line.get("digest").and_then(Value::as_str) else {
+            return Ok(());
+        };
+        let total = line
+            .get("total")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        let completed = line
+            .get("completed")
+            .and_then(Value::as_u64)
+            .unwrap_or_default();
+        match self.layers.iter_mut().find(|layer| layer.digest == digest) {
+            Some(layer) => {
+                layer.total = total.max(layer.total);
+                layer.completed = completed.max(layer.completed);
+            }
+            None => self.layers.push(LayerProgress {
+                digest: digest.to_string(),
+                total,
+                completed,
+            }),
+        }
+        self.sample_speed(now);
+        Ok(())
+    }
+
+    fn sample_speed(&mut self, now: Instant) {
+        let completed = self.completed_bytes();
+        let Some((sampled_at, sampled_bytes)) = self.last_sample else {
+            self.last_sample = Some((now, completed));
+            return;
+        };
+        let elapsed = now.duration_since(sampled_at).as_secs_f64();
+        // Ollama reports many times a second; very short intervals are noise.
+        if elapsed < 0.5 {
+            return;
+        }
+        let speed = completed.saturating_sub(sampled_bytes) as f64 / elapsed;
+        self.bytes_per_second = if self.bytes_per_second == 0.0 {
+            speed
+        } else {
+            self.bytes_per_second * 0.7 + speed * 0.3
+        };
+        self.last_sample = Some((now, completed));
+    }
+
+    pub fn total_bytes(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.total).sum()
+    }
+
+    pub fn completed_bytes(&self) -> u64 {
+        self.layers.iter().map(|layer| layer.completed).sum()
+    }
+
+    pub fn fraction(&self) -> f32 {
+        match self.total_bytes() {
+            0 => 0.0,
+            total => self.completed_bytes() as f32 / total as f32,
+        }
+    }
+
+    /// Time left at the current speed, once there is a speed to go by.
+    pub fn eta(&self) -> Option<Duration> {
+        (self.bytes_per_second > 0.0).then(|| {
+            let remaining = self.total_bytes().saturating_sub(self.completed_bytes());
+            Duration::from_secs_f64(remaining as f64 / self.bytes_per_second)
+        })
+    }
+}
+
+pub fn format_eta(eta: Duration) -> String {
+    let seconds = eta.as_secs();
+    if seconds < 60 {
+        format!("{seconds} s")
+    } else if seconds < 3600 {
+        format!("{} min", seconds.div_ceil(60))
+    } else {
+        format!("{} h {} min", seconds / 3600, (seconds % 3600) / 60)
+    }
+}
+
+/// Pulls `model`, publishing progress until Ollama reports success or
+/// `cancel` is set. Dropping the response stops the download on the server.
+pub async fn pull_model(
+    host: HostLocation,
+    model: String,
+    progress: Arc<Mutex<PullProgress>>,
+    cancel: Arc<AtomicBool>,
+) -> Result<PullOutcome, String> {
+    let mut response = host
+        .client()?
+        .post(format!("{}/api/pull", host.base_url()))
+        .json(&serde_json::json!({ "model": model, "stream": true }))
+        .send()
+        .await
+        .map_err(|error| format!("Could not reach Ollama: {error}"))?;
+    if !response.status().is_success() {
+        let status = response.status();
+        let detail = response.text().await.unwrap_or_default();
+        return Err(format!(
+            "Ollama rejected the pull ({status}): {}",
+            detail.trim()
+        ));
+    }
+
+    let mut buffer = String::new();
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(PullOutcome::Cancelled);
+        }
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk.map_err(|error| error.to_string())?,
+            _ = tokio::time::sleep(Duration::from_millis(100)) => continue,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        buffer.push_str(&String::from_utf8_lossy(&chunk));
+        while let Some(newline) = buffer.find('\n') {
+            let line = buffer[..newline].trim().to_string();
+            buffer.drain(..=newline);
+            absorb_line(&line, &progress)?;
+        }
+    }
+    absorb_line(buffer.trim(), &progress)?;
+
+    if progress.lock().unwrap().status == "success" {
+        Ok(PullOutcome::Completed)
+    } else {
+        Err("Ollama ended the download before it finished.".to_string())
+    }
+}
+
+fn absorb_line(line: &str, progress: &Mutex<PullProgress>) -> Result<(), String> {
+    if line.is_empty() {
+        return Ok(());
+    }
+    let value = serde_json::from_str::<Value>(line)
+        .map_err(|error| format!("Ollama sent an invalid progress line: {error}"))?;
+    progress.lock().unwrap().absorb(&value, Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers_accumulate_into_overall_progress() {
+        let start = Instant::now();
+        let mut progress = PullProgress::default();
+        let line = |digest: &str, total: u64, completed: u64| {
+            serde_json::json!({
+                "status": format!("pulling {digest}"),
+                "digest": digest,
+                "total": total,
+                "completed": completed
+            })
+        };
+
+        progress
+            .absorb(&serde_json::json!({"status": "pulling manifest"}), start)
+            .unwrap();
+        progress.absorb(&line("sha256:a", 1_000, 0), start).unwrap();
+        progress
+            .absorb(
+                &line("sha256:b", 3_000, 0),
+                start + Duration::from_millis(100),
+            )
+            .unwrap();
+        progress
+            .absorb(
+                &line("sha256:a", 1_000, 1_000),
+                start + Duration::from_secs(1),
+            )
+            .unwrap();
+
+        assert_eq!(progress.layers.len(), 2);
+        assert_eq!(progress.status, "pulling sha256:a");
+        assert_eq!(progress.total_bytes(), 4_000);
+        assert_eq!(progress.completed_bytes(), 1_000);
+        assert_eq!(progress.fraction(), 0.25);
+        assert_eq!(progress.bytes_per_second, 1_000.0);
+        assert_eq!(progress.eta(), Some(Duration::from_secs(3)));
+    }
+
+    #[test]
+    fn error_lines_fail_the_pull() {
+        let mut progress = PullProgress::default();
+        assert_eq!(
+            progress.absorb(
+                &serde_json::json!({"error": "pull model manifest: file does not exist"}),
+                Instant::now()
+            ),
+            Err("pull model manifest: file does not exist".to_string())
+        );
+        assert_eq!(format_eta(Duration::from_secs(150)), "3 min");
+        assert_eq!(format_eta(Duration::from_secs(3_720)), "1 h 2 min");
+    }
+}
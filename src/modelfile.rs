@@ -0,0 +1,384 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use serde_json::{Map, Value};
+
+use crate::app::HostLocation;
+
+/// A Modelfile reduced to the fields `/api/create` accepts.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Modelfile {
+    pub from: String,
+    pub parameters: Map<String, Value>,
+    pub template: Option<String>,
+    pub system: Option<String>,
+    pub license: Vec<String>,
+    pub messages: Vec<(String, String)>,
+}
+
+/// A problem with one directive, reported against its 1-based line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ModelfileIssue {
+    pub line: usize,
+    pub message: String,
+}
+
+#[derive(Clone, Copy)]
+enum ParameterKind {
+    Integer,
+    Float,
+    Boolean,
+    Text,
+}
+
+const PARAMETERS: [(&str, ParameterKind); 21] = [
+    ("num_ctx", ParameterKind::Integer),
+    ("num_predict", ParameterKind::Integer),
+    ("num_keep", ParameterKind::Integer),
+    ("num_batch", ParameterKind::Integer),
+    ("num_gpu", ParameterKind::Integer),
+    ("num_thread", ParameterKind::Integer),
+    ("repeat_last_n", ParameterKind::Integer),
+    ("seed", ParameterKind::Integer),
+    ("top_k", ParameterKind::Integer),
+    ("mirostat", ParameterKind::Integer),
+    ("temperature", ParameterKind::Float),
+    ("top_p", ParameterKind::Float),
+    ("min_p", ParameterKind::Float),
+    ("typical_p", ParameterKind::Float),
+    ("repeat_penalty", ParameterKind::Float),
+    ("presence_penalty", ParameterKind::Float),
+    ("frequency_penalty", ParameterKind::Float),
+    ("mirostat_eta", ParameterKind::Float),
+    ("mirostat_tau", ParameterKind::Float),
+    ("use_mmap", ParameterKind::Boolean),
+    ("stop", ParameterKind::Text),
+];
+
+impl Modelfile {
+    /// Parses and validates `text`, collecting every issue rather than
+    /// stopping at the first.
+    pub fn parse(text: &str) -> Result<Self, Vec<ModelfileIssue>> {
+        let mut modelfile = Modelfile::default();
+        let mut issues = Vec::new();
+        let mut from_line = None;
+        let lines = text.lines().collect::<Vec<_>>();
+        let mut index = 0;
+
+        while index < lines.len() {
+            let line_number = index + 1;
+            let line = lines[index].trim();
+            index += 1;
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (directive, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let mut issue = |message: String| {
+                issues.push(ModelfileIssue {
+                    line: line_number,
+                    message,
+                })
+            };
+            let rest = match read_value(rest.trim(), &lines, &mut index) {
+                Ok(rest) => rest,
+                Err(message) => {
+                    issue(message);
+                    continue;
+                }
+            };
+
+            match directive.to_ascii_uppercase().as_str() {
+                "FROM" => {
+                    if from_line.is_some() {
+                        issue("Only one FROM is allowed.".to_string());
+                    } else if rest.is_empty() {
+                        issue("FROM needs a model name.".to_string());
+                    } else if is_local_path(&rest) {
+                        issue(
+                            "FROM a local file is not supported here; import the file instead."
+                                .to_string(),
+                        );
+                    }
+                    from_line = Some(line_number);
+                    modelfile.from = rest;
+                }
+                "PARAMETER" => {
+                    let (name, value) = rest.split_once(char::is_whitespace).unwrap_or((&rest, ""));
+                    match parameter_value(name, unquote(value.trim())) {
+                        Ok(Value::String(stop)) if name == "stop" => {
+                            let stops = modelfile
+                                .parameters
+                                .entry("stop")
+                                .or_insert_with(|| Value::Array(Vec::new()));
+                            if let Value::Array(stops) = stops {
+                                stops.push(Value::String(stop));
+                            }
+                        }
+                        Ok(value) => {
+                            modelfile.parameters.insert(name.to_string(), value);
+                        }
+                        Err(message) => issue(message),
+                    }
+                }
+                "TEMPLATE" => modelfile.template = Some(rest),
+                "SYSTEM" => modelfile.system = Some(rest),
+                "LICENSE" => modelfile.license.push(rest),
+                "MESSAGE" => {
+                    let (role, content) =
+                        rest.split_once(char::is_whitespace).unwrap_or((&rest, ""));
+                    if matches!(role, "system" | "user" | "assistant") {
+                        modelfile
+                            .messages
+                            .push((role.to_string(), unquote(content.trim()).to_string()));
+                    } else {
+                        issue(format!(
+                            "MESSAGE role must be system, user or assistant, not \"{role}\"."
+                        ));
+                    }
+                }
+                "ADAPTER" => issue(
+                    "ADAPTER needs a file upload, which the editor does not support.".to_string(),
+                ),
+                other => issue(format!("Unknown directive \"{other}\".")),
+            }
+        }
+
+        if from_line.is_none() {
+            issues.push(ModelfileIssue {
+                line: 1,
+                message: "A Modelfile must start from a model with FROM.".to_string(),
+            });
+        }
+        if issues.is_empty() {
+            Ok(modelfile)
+        } else {
+            Err(issues)
+        }
+    }
+
+    pub fn create_request(&self, model: &str) -> Value {
+        let mut request = serde_json::json!({
+            "model": model,
+            "from": self.from,
+            "stream": true,
+        });
+        if !self.parameters.is_empty() {
+            request["parameters"] = Value::Object(self.parameters.clone());
+        }
+        if let Some(template) = &self.template {
+            request["template"] = Value::String(template.clone());
+        }
+        if let Some(system) = &self.system {
+            request["system"] = Value::String(system.clone());
+        }
+        if !self.license.is_empty() {
+            request["license"] = serde_json::json!(self.license);
+        }
+        if !self.messages.is_empty() {
+            request["messages"] = self
+                .messages
+                .iter()
+                .map(|(role, content)| serde_json::json!({"role": role, "content": content}))
+                .collect();
+        }
+        request
+    }
+}
+
+/// Points the Modelfile `ollama show` printed for `model` at the model by
+/// name; the printed FROM is a blob path on the server.
+pub fn derive_from(model: &str, modelfile: &str) -> String {
+    modelfile
+        .lines()
+        .map(|line| {
+            let directive = line.split_whitespace().next().unwrap_or_default();
+            if directive.eq_ignore_ascii_case("FROM") {
+                format!("FROM {model}")
+            } else {
+                line.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reads a directive's argument, continuing onto later lines for `"""` blocks.
+fn read_value(rest: &str, lines: &[&str], index: &mut usize) -> Result<String, String> {
+    let Some(opening) = rest.find("\"\"\"") else {
+        return Ok(rest.to_string());
+    };
+    let prefix = &rest[..opening];
+    let body = &rest[opening + 3..];
+    if let Some(closing) = body.find("\"\"\"") {
+        return Ok(format!("{prefix}{}", &body[..closing]));
+    }
+    let mut value = body.to_string();
+    while *index < lines.len() {
+        let line = lines[*index];
+        *index += 1;
+        if let Some(closing) = line.find("\"\"\"") {
+            value.push('\n');
+            value.push_str(&line[..closing]);
+            return Ok(format!("{prefix}{value}"));
+        }
+        value.push('\n');
+        value.push_str(line);
+    }
+    Err("This \"\"\" block is never closed.".to_string())
+}
+
+fn unquote(value: &str) -> &str {
+    value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+        .unwrap_or(value)
+}
+
+fn is_local_path(from: &str) -> bool {
+    from.starts_with('.') || from.starts_with('/') || from.starts_with('~') || from.contains('\\')
+}
+
+fn parameter_value(name: &str, value: &str) -> Result<Value, String> {
+    let Some((_, kind)) = PARAMETERS.iter().find(|(known, _)| *known == name) else {
+        return Err(format!("Unknown parameter \"{name}\"."));
+    };
+    if value.is_empty() {
+        return Err(format!("PARAMETER {name} needs a value."));
+    }
+    let invalid = |expected: &str| format!("PARAMETER {name} expects {expected}, not \"{value}\".");
+    match kind {
+        ParameterKind::Integer => value
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| invalid("a whole number")),
+        ParameterKind::Float => value
+            .parse::<f64>()
+            .map(Value::from)
+            .map_err(|_| invalid("a number")),
+        ParameterKind::Boolean => value
+            .parse::<bool>()
+            .map(Value::from)
+            .map_err(|_| invalid("true or false")),
+        ParameterKind::Text => Ok(Value::String(value.to_string())),
+    }
+}
+
+/// Submits `request` to `/api/create`, appending each status Ollama streams
+/// back to `log`.
+pub async fn create_model(
+    host: HostLocation,
+    request: Value,
+    log: Arc<Mutex<Vec<String>>>,
+) -> Result<(), String> {
+    let mut response = host
+        .client()?
+        .post(format!("{}/api/create", host.base_url()))
+        .json(&request)
+        .send()
+        .await
+        .map_err(|error| format!("Could not reach Ollama: {error}"))?;
+    let status = response.status();
+    let mut buffer = String::new();
+    let mut succeeded = false;
+    loop {
+        let chunk = tokio::time::timeout(Duration::from_secs(600), response.chunk())
+            .await
+            .map_err(|_| "Ollama stopped responding while creating the model.".to_string())?
+            .map_err(|error| error.to_string())?;
+        let finished = chunk.is_none();
+        if let Some(chunk) = chunk {
+            buffer.push_str(&String::from_utf8_lossy(&chunk));
+        } else {
+            buffer.push('\n');
+        }
+        while let Some(newline) = buffer.find('\n') {
+            let line = buffer[..newline].trim().to_string();
+            buffer.drain(..=newline);
+            if line.is_empty() {
+                continue;
+            }
+            let value = serde_json::from_str::<Value>(&line)
+                .map_err(|_| format!("Ollama rejected the Modelfile ({status}): {line}"))?;
+            if let Some(error) = value.get("error").and_then(Value::as_str) {
+                return Err(error.to_string());
+            }
+            if let Some(status) = value.get("status").and_then(Value::as_str) {
+                succeeded = status == "success";
+                log.lock().unwrap().push(status.to_string());
+            }
+        }
+        if finished {
+            break;
+        }
+    }
+    if succeeded {
+        Ok(())
+    } else {
+        Err(format!(
+            "Ollama did not finish creating the model ({status})."
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn show_output_becomes_a_create_request() {
+        let shown = "# Modelfile generated by \"ollama show\"\n\
+            FROM /usr/share/ollama/.ollama/models/blobs/sha256-74701a8c35f6\n\
+            TEMPLATE \"\"\"{{ if .System }}<|system|>\n{{ .System }}{{ end }}\n\
+            <|user|>{{ .Prompt }}\"\"\"\n\
+            PARAMETER stop <|user|>\n\
+            PARAMETER stop \"<|end|>\"\n\
+            PARAMETER temperature 0.2\n\
+            SYSTEM You review Rust code.\n\
+            MESSAGE user Hello\n";
+        let modelfile = Modelfile::parse(&derive_from("llama3.2:3b", shown)).unwrap();
+
+        assert_eq!(
+            modelfile.create_request("reviewer"),
+            serde_json::json!({
+                "model": "reviewer",
+                "from": "llama3.2:3b",
+                "stream": true,
+                "parameters": {"stop": ["<|user|>", "<|end|>"], "temperature": 0.2},
+                "template": "{{ if .System }}<|system|>\n{{ .System }}{{ end }}\n<|user|>{{ .Prompt }}",
+                "system": "You review Rust code.",
+                "messages": [{"role": "user", "content": "Hello"}],
+            })
+        );
+    }
+
+    #[test]
+    fn every_invalid_directive_is_reported_by_line() {
+        let issues = Modelfile::parse(
+            "PARAMETER temperature warm\n\
+             PARAMETER top_q 1\n\
+             MESSAGE robot hi\n\
+             FORM llama3\n\
+             SYSTEM \"\"\"never closed",
+        )
+        .unwrap_err();
+        let lines = issues.iter().map(|issue| issue.line).collect::<Vec<_>>();
+        assert_eq!(lines, [1, 2, 3, 4, 5, 1]);
+        assert_eq!(
+            issues[0].message,
+            "PARAMETER temperature expects a number, not \"warm\"."
+        );
+        assert_eq!(
+            issues[5].message,
+            "A Modelfile must start from a model with FROM."
+        );
+    }
+
+    #[test]
+    fn local_files_and_repeated_from_are_rejected() {
+        let issues = Modelfile::parse("FROM ./model.gguf\nFROM llama3").unwrap_err();
+        assert_eq!(issues.len(), 2);
+        assert_eq!(issues[1].message, "Only one FROM is allowed.");
+    }
+}
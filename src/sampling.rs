@@ -0,0 +1,397 @@
+//! Ollama sampling options beyond temperature and the token limits.
+//!
+//! Every option is optional: an unset value is left out of the request so
+//! the model's own Modelfile default applies.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SamplingOption {
+    TopP,
+    TopK,
+    MinP,
+    RepeatPenalty,
+    RepeatLastN,
+    PresencePenalty,
+    FrequencyPenalty,
+    Seed,
+    Mirostat,
+    MirostatTau,
+    MirostatEta,
+    Stop,
+}
+
+impl SamplingOption {
+    pub const ALL: [Self; 12] = [
+        Self::TopP,
+        Self::TopK,
+        Self::MinP,
+        Self::RepeatPenalty,
+        Self::RepeatLastN,
+        Self::PresencePenalty,
+        Self::FrequencyPenalty,
+        Self::Seed,
+        Self::Mirostat,
+        Self::MirostatTau,
+        Self::MirostatEta,
+        Self::Stop,
+    ];
+
+    /// The key used in request `options` and in Modelfile `PARAMETER` lines.
+    pub fn api_name(self) -> &'static str {
+        match self {
+            Self::TopP => "top_p",
+            Self::TopK => "top_k",
+            Self::MinP => "min_p",
+            Self::RepeatPenalty => "repeat_penalty",
+            Self::RepeatLastN => "repeat_last_n",
+            Self::PresencePenalty => "presence_penalty",
+            Self::FrequencyPenalty => "frequency_penalty",
+            Self::Seed => "seed",
+            Self::Mirostat => "mirostat",
+            Self::MirostatTau => "mirostat_tau",
+            Self::MirostatEta => "mirostat_eta",
+            Self::Stop => "stop",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::TopP => "Top P",
+            Self::TopK => "Top K",
+            Self::MinP => "Min P",
+            Self::RepeatPenalty => "Repeat penalty",
+            Self::RepeatLastN => "Repeat window",
+            Self::PresencePenalty => "Presence penalty",
+            Self::FrequencyPenalty => "Frequency penalty",
+            Self::Seed => "Seed",
+            Self::Mirostat => "Mirostat",
+            Self::MirostatTau => "Mirostat tau",
+            Self::MirostatEta => "Mirostat eta",
+            Self::Stop => "Stop sequences",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SamplingSettings {
+    pub top_p: Option<f32>,
+    pub top_k: Option<u32>,
+    pub min_p: Option<f32>,
+    pub repeat_penalty: Option<f32>,
+    /// Tokens looked back for repeats; 0 disables and -1 uses the context.
+    pub repeat_last_n: Option<i32>,
+    pub presence_penalty: Option<f32>,
+    pub frequency_penalty: Option<f32>,
+    pub seed: Option<i32>,
+    /// 0 disables Mirostat; 1 and 2 pick the algorithm version.
+    pub mirostat: Option<u8>,
+    pub mirostat_tau: Option<f32>,
+    pub mirostat_eta: Option<f32>,
+    pub stop: Vec<String>,
+}
+
+fn parse_optional<T: FromStr>(input: &str) -> Result<Option<T>, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+    input
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("\"{input}\" is not a valid value."))
+}
+
+fn within(value: Option<f32>, min: f32, max: f32) -> Result<Option<f32>, String> {
+    match value {
+        Some(value) if !(min..=max).contains(&value) => {
+            Err(format!("Use a value between {min} and {max}."))
+        }
+        value => Ok(value),
+    }
+}
+
+fn format_optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+/// Stop sequences are typed comma-separated; `\n` and `\t` stand for
+/// newlines and tabs, and `\,` for a literal comma.
+fn parse_stop_sequences(input: &str) -> Vec<String> {
+    // Split on unescaped commas and trim the raw text, so escaped
+    // whitespace such as `\n` survives.
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut chars = input.chars();
+    while let Some(character) = chars.next() {
+        match character {
+            '\\' => {
+                current.push('\\');
+                if let Some(escaped) = chars.next() {
+                    current.push(escaped);
+                }
+            }
+            ',' => segments.push(std::mem::take(&mut current)),
+            other => current.push(other),
+        }
+    }
+    segments.push(current);
+    segments
+        .iter()
+        .map(|segment| unescape_stop_sequence(segment.trim()))
+        .filter(|sequence| !sequence.is_empty())
+        .collect()
+}
+
+fn unescape_stop_sequence(segment: &str) -> String {
+    let mut sequence = String::new();
+    let mut chars = segment.chars();
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            sequence.push(character);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => sequence.push('\n'),
+            Some('t') => sequence.push('\t'),
+            Some(other) => sequence.push(other),
+            None => sequence.push('\\'),
+        }
+    }
+    sequence
+}
+
+fn format_stop_sequences(sequences: &[String]) -> String {
+    sequences
+        .iter()
+        .map(|sequence| {
+            sequence
+                .replace('\\', "\\\\")
+                .replace('\n', "\\n")
+                .replace('\t', "\\t")
+                .replace(',', "\\,")
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Splits `/api/show`'s `parameters` text, one `name value` pair per line.
+fn model_parameters(parameters: &str) -> impl Iterator<Item = (&str, String)> {
+    parameters.lines().filter_map(|line| {
+        let (name, value) = line.trim().split_once(char::is_whitespace)?;
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        Some((name, value.to_string()))
+    })
+}
+
+impl SamplingSettings {
+    /// The value shown in the option's text field.
+    pub fn text(&self, option: SamplingOption) -> String {
+        match option {
+            SamplingOption::TopP => format_optional(self.top_p),
+            SamplingOption::TopK => format_optional(self.top_k),
+            SamplingOption::MinP => format_optional(self.min_p),
+            SamplingOption::RepeatPenalty => format_optional(self.repeat_penalty),
+            SamplingOption::RepeatLastN => format_optional(self.repeat_last_n),
+            SamplingOption::PresencePenalty => format_optional(self.presence_penalty),
+            SamplingOption::FrequencyPenalty => format_optional(self.frequency_penalty),
+            SamplingOption::Seed => format_optional(self.seed),
+            SamplingOption::Mirostat => format_optional(self.mirostat),
+            SamplingOption::MirostatTau => format_optional(self.mirostat_tau),
+            SamplingOption::MirostatEta => format_optional(self.mirostat_eta),
+            SamplingOption::Stop => format_stop_sequences(&self.stop),
+        }
+    }
+
+    /// Parses and stores `input`. Empty input returns the option to the
+    /// model's default; invalid input leaves the current value unchanged.
+    pub fn set_text(&mut self, option: SamplingOption, input: &str) -> Result<(), String> {
+        match option {
+            SamplingOption::TopP => self.top_p = within(parse_optional(input)?, 0.0, 1.0)?,
+            SamplingOption::TopK => self.top_k = parse_optional(input)?,
+            SamplingOption::MinP => self.min_p = within(parse_optional(input)?, 0.0, 1.0)?,
+            SamplingOption::RepeatPenalty => {
+                self.repeat_penalty = within(parse_optional(input)?, 0.0, 10.0)?;
+            }
+            SamplingOption::RepeatLastN => {
+                let value = parse_optional::<i32>(input)?;
+                if value.is_some_and(|value| value < -1) {
+                    return Err(
+                        "Use -1 for the whole context, 0 to disable, or a token count.".to_string(),
+                    );
+                }
+                self.repeat_last_n = value;
+            }
+            SamplingOption::PresencePenalty => {
+                self.presence_penalty = within(parse_optional(input)?, -2.0, 2.0)?;
+            }
+            SamplingOption::FrequencyPenalty => {
+                self.frequency_penalty = within(parse_optional(input)?, -2.0, 2.0)?;
+            }
+            SamplingOption::Seed => self.seed = parse_optional(input)?,
+            SamplingOption::Mirostat => {
+                let value = parse_optional::<u8>(input)?;
+                if value.is_some_and(|value| value > 2) {
+                    return Err("Mirostat must be 0, 1, or 2.".to_string());
+                }
+                self.mirostat = value;
+            }
+            SamplingOption::MirostatTau => self.mirostat_tau = parse_optional(input)?,
+            SamplingOption::MirostatEta => self.mirostat_eta = parse_optional(input)?,
+            SamplingOption::Stop => self.stop = parse_stop_sequences(input),
+        }
+        Ok(())
+    }
+
+    /// Adds every set option to an Ollama `options` object.
+    pub fn apply_to(&self, options: &mut serde_json::Value) {
+        let Some(options) = options.as_object_mut() else {
+            return;
+        };
+        let values = [
+            (
+                SamplingOption::TopP,
+                self.top_p.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::TopK,
+                self.top_k.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::MinP,
+                self.min_p.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::RepeatPenalty,
+                self.repeat_penalty.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::RepeatLastN,
+                self.repeat_last_n.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::PresencePenalty,
+                self.presence_penalty.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::FrequencyPenalty,
+                self.frequency_penalty.map(serde_json::Value::from),
+            ),
+            (SamplingOption::Seed, self.seed.map(serde_json::Value::from)),
+            (
+                SamplingOption::Mirostat,
+                self.mirostat.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::MirostatTau,
+                self.mirostat_tau.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::MirostatEta,
+                self.mirostat_eta.map(serde_json::Value::from),
+            ),
+            (
+                SamplingOption::Stop,
+                (!self.stop.is_empty()).then(|| serde_json::json!(self.stop)),
+            ),
+        ];
+        for (option, value) in values {
+            if let Some(value) = value {
+                options.insert(option.api_name().to_string(), value);
+            }
+        }
+    }
+
+    /// The model's own defaults, read from `/api/show`'s `parameters`.
+    /// Options the Modelfile does not set stay unset.
+    pub fn from_model_parameters(parameters: &str) -> Self {
+        let mut settings = Self::default();
+        for (name, value) in model_parameters(parameters) {
+            if name == SamplingOption::Stop.api_name() {
+                if !value.is_empty() {
+                    settings.stop.push(value);
+                }
+            } else if let Some(option) = SamplingOption::ALL
+                .into_iter()
+                .find(|option| option.api_name() == name)
+            {
+                let _ = settings.set_text(option, &value);
+            }
+        }
+        settings
+    }
+}
+
+/// The temperature from `/api/show`'s `parameters`, if the Modelfile sets one.
+pub fn model_temperature(parameters: &str) -> Option<f32> {
+    model_parameters(parameters)
+        .find(|(name, _)| *name == "temperature")
+        .and_then(|(_, value)| value.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SamplingOption, SamplingSettings, model_temperature};
+
+    #[test]
+    fn set_options_are_sent_and_unset_ones_left_to_the_model() {
+        let mut settings = SamplingSettings::default();
+        settings.set_text(SamplingOption::TopK, "40").unwrap();
+        settings.set_text(SamplingOption::Seed, "-7").unwrap();
+        settings
+            .set_text(SamplingOption::Stop, r"</s>, User:, \n\n")
+            .unwrap();
+        let mut options = serde_json::json!({"temperature": 0.7});
+        settings.apply_to(&mut options);
+        assert_eq!(options["top_k"], 40);
+        assert_eq!(options["seed"], -7);
+        assert_eq!(
+            options["stop"],
+            serde_json::json!(["</s>", "User:", "\n\n"])
+        );
+        assert!(options.get("top_p").is_none());
+        assert_eq!(options["temperature"], 0.7);
+    }
+
+    #[test]
+    fn invalid_input_is_rejected_and_empty_input_clears() {
+        let mut settings = SamplingSettings::default();
+        settings.set_text(SamplingOption::TopP, "0.9").unwrap();
+        assert!(settings.set_text(SamplingOption::TopP, "1.5").is_err());
+        assert!(settings.set_text(SamplingOption::Mirostat, "3").is_err());
+        assert!(settings.set_text(SamplingOption::TopK, "many").is_err());
+        assert_eq!(settings.top_p, Some(0.9));
+        settings.set_text(SamplingOption::TopP, " ").unwrap();
+        assert_eq!(settings.top_p, None);
+    }
+
+    #[test]
+    fn stop_sequences_round_trip_through_their_text_field() {
+        let settings = SamplingSettings {
+            stop: vec!["a, b".into(), "\n".into()],
+            ..SamplingSettings::default()
+        };
+        let text = settings.text(SamplingOption::Stop);
+        let mut parsed = SamplingSettings::default();
+        parsed.set_text(SamplingOption::Stop, &text).unwrap();
+        assert_eq!(parsed.stop, settings.stop);
+    }
+
+    #[test]
+    fn model_defaults_are_read_from_show_parameters() {
+        let parameters = "num_ctx                        4096\nrepeat_penalty                 1.1\nstop                           \"<|start_header_id|>\"\nstop                           \"<|eot_id|>\"\ntemperature                    0.6\ntop_p                          0.9";
+        let settings = SamplingSettings::from_model_parameters(parameters);
+        assert_eq!(settings.repeat_penalty, Some(1.1));
+        assert_eq!(settings.top_p, Some(0.9));
+        assert_eq!(settings.top_k, None);
+        assert_eq!(settings.stop, ["<|start_header_id|>", "<|eot_id|>"]);
+        assert_eq!(model_temperature(parameters), Some(0.6));
+    }
+}
@@ -0,0 +1,114 @@
+//! Named Ollama servers the user can switch between.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::app::HostLocation;
+
+/// Which PEM file of a profile a file dialog fills in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CertificateKind {
+    Authority,
+    Client,
+}
+
+impl CertificateKind {
+    pub fn path(self, host: &mut HostLocation) -> &mut Option<String> {
+        match self {
+            Self::Authority => &mut host.ca_certificate,
+            Self::Client => &mut host.client_certificate,
+        }
+    }
+}
+
+/// A profile as offered by the switcher. Names are not required to be
+/// unique, so the choice carries the profile's position.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProfileChoice {
+    pub index: usize,
+    pub name: String,
+}
+
+impl fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+pub fn profile_choices(profiles: &[HostLocation]) -> Vec<ProfileChoice> {
+    profiles
+        .iter()
+        .enumerate()
+        .map(|(index, profile)| ProfileChoice {
+            index,
+            name: profile.name.clone(),
+        })
+        .collect()
+}
+
+/// Reads the saved profiles, falling back to a single local server. The
+/// active index is clamped so a hand-edited settings file cannot point past
+/// the list.
+pub fn load_profiles(
+    profiles: Option<&Value>,
+    active: Option<&Value>,
+) -> (Vec<HostLocation>, usize) {
+    let mut profiles = profiles
+        .cloned()
+        .and_then(|value| serde_json::from_value::<Vec<HostLocation>>(value).ok())
+        .unwrap_or_default();
+    if profiles.is_empty() {
+        profiles.push(HostLocation::default());
+    }
+    let active = active
+        .and_then(Value::as_u64)
+        .map_or(0, |index| index as usize)
+        .min(profiles.len() - 1);
+    (profiles, active)
+}
+
+/// Returns `base`, or `base 2`, `base 3`… when a profile already uses it.
+pub fn unique_profile_name(profiles: &[HostLocation], base: &str) -> String {
+    let taken = |name: &str| profiles.iter().any(|profile| profile.name == name);
+    if !taken(base) {
+        return base.to_string();
+    }
+    (2..)
+        .map(|index| format!("{base} {index}"))
+        .find(|name| !taken(name))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_or_broken_profiles_fall_back_to_the_local_server() {
+        let (profiles, active) = load_profiles(None, None);
+        assert_eq!(profiles, vec![HostLocation::default()]);
+        assert_eq!(active, 0);
+
+        let saved = serde_json::json!([
+            {"name": "Local"},
+            {"name": "Workstation", "ip": "192.168.1.20", "scheme": "https"},
+        ]);
+        let (profiles, active) = load_profiles(Some(&saved), Some(&serde_json::json!(7)));
+        assert_eq!(active, 1);
+        assert_eq!(profiles[1].base_url(), "https://192.168.1.20:11434");
+    }
+
+    #[test]
+    fn new_profiles_get_unused_names() {
+        let profiles = vec![
+            HostLocation::default(),
+            HostLocation {
+                name: "Server".to_string(),
+                ..HostLocation::default()
+            },
+        ];
+        assert_eq!(unique_profile_name(&profiles, "Remote"), "Remote");
+        assert_eq!(unique_profile_name(&profiles, "Server"), "Server 2");
+    }
+}
@@ -0,0 +1,409 @@
+//! Structured output: the `format` a chat sends to Ollama and the check of
+//! the answer against it.
+//!
+//! Ollama constrains generation with a grammar built from the schema, but a
+//! truncated or reasoning-prefixed answer can still miss it, so finished
+//! responses are validated locally and the result is shown under the answer.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// What a chat asks Ollama to produce through the `format` parameter: any
+/// JSON value, or one matching a JSON Schema.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ResponseFormat {
+    Json,
+    Schema(Value),
+}
+
+/// The result of checking a structured response against its format.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum FormatCheck {
+    Valid,
+    Invalid(Vec<String>),
+}
+
+impl ResponseFormat {
+    /// Reads the text of the structured-output panel. Blank text turns
+    /// structured output off.
+    pub fn parse(text: &str) -> Result<Option<Self>, String> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(None);
+        }
+        if text.eq_ignore_ascii_case("json") {
+            return Ok(Some(Self::Json));
+        }
+        match serde_json::from_str::<Value>(text) {
+            Ok(schema @ Value::Object(_)) => Ok(Some(Self::Schema(schema))),
+            Ok(_) => Err("A JSON Schema must be an object.".to_string()),
+            Err(error) => Err(format!("The schema is not valid JSON: {error}")),
+        }
+    }
+
+    pub fn api_value(&self) -> Value {
+        match self {
+            Self::Json => Value::String("json".to_string()),
+            Self::Schema(schema) => schema.clone(),
+        }
+    }
+
+    /// Checks a response, without any reasoning, against this format.
+    pub fn check(&self, response: &str) -> FormatCheck {
+        let instance = match serde_json::from_str::<Value>(response.trim()) {
+            Ok(instance) => instance,
+            Err(error) => {
+                return FormatCheck::Invalid(vec![format!(
+                    "The response is not valid JSON: {error}"
+                )]);
+            }
+        };
+        let Self::Schema(schema) = self else {
+            return FormatCheck::Valid;
+        };
+        let mut errors = Vec::new();
+        validate(schema, &mut Vec::new(), schema, &instance, "$", &mut errors);
+        if errors.is_empty() {
+            FormatCheck::Valid
+        } else {
+            FormatCheck::Invalid(errors)
+        }
+    }
+}
+
+/// Validates the subset of JSON Schema that Ollama enforces through its
+/// grammar: types, objects, arrays, enums, bounds, combinators and local
+/// `$ref`s. Unknown keywords are ignored rather than failing the response.
+/// `expanding` holds the `$ref`s being followed at each path, so a schema
+/// that refers back to itself is reported instead of recursing forever.
+fn validate<'a>(
+    root: &'a Value,
+    expanding: &mut Vec<(&'a str, String)>,
+    schema: &'a Value,
+    instance: &Value,
+    path: &str,
+    errors: &mut Vec<String>,
+) {
+    let Value::Object(schema) = schema else {
+        if schema == &Value::Bool(false) {
+            errors.push(format!("{path} is not allowed"));
+        }
+        return;
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let key = (reference, path.to_string());
+        if expanding.contains(&key) {
+            errors.push(format!("{path}: cyclic $ref {reference}"));
+            return;
+        }
+        match resolve_reference(root, reference) {
+            Some(target) => {
+                expanding.push(key);
+                validate(root, expanding, target, instance, path, errors);
+                expanding.pop();
+            }
+            None => errors.push(format!("{path}: cannot resolve {reference}")),
+        }
+        return;
+    }
+
+    if let Some(expected) = schema.get("type") {
+        let allowed: Vec<&str> = match expected {
+            Value::String(name) => vec![name.as_str()],
+            Value::Array(names) => names.iter().filter_map(Value::as_str).collect(),
+            _ => Vec::new(),
+        };
+        if !allowed.is_empty() && !allowed.iter().any(|name| has_type(instance, name)) {
+            errors.push(format!(
+                "{path} should be {}, found {}",
+                allowed.join(" or "),
+                type_name(instance)
+            ));
+            return;
+        }
+    }
+
+    if let Some(expected) = schema.get("const")
+        && expected != instance
+    {
+        errors.push(format!("{path} should be {expected}"));
+    }
+    if let Some(Value::Array(options)) = schema.get("enum")
+        && !options.contains(instance)
+    {
+        let options = options
+            .iter()
+            .map(Value::to_string)
+            .collect::<Vec<_>>()
+            .join(", ");
+        errors.push(format!("{path} should be one of {options}"));
+    }
+
+    match instance {
+        Value::Object(object) => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for required in schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+            {
+                if !object.contains_key(required) {
+                    errors.push(format!("{path} is missing \"{required}\""));
+                }
+            }
+            for (key, value) in object {
+                let child = format!("{path}.{key}");
+                match properties.and_then(|properties| properties.get(key)) {
+                    Some(property) => validate(root, expanding, property, value, &child, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{child} is not an allowed property"));
+                        }
+                        Some(extra @ Value::Object(_)) => {
+                            validate(root, expanding, extra, value, &child, errors);
+                        }
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            check_count(
+                schema,
+                "minItems",
+                "maxItems",
+                items.len(),
+                path,
+                "items",
+                errors,
+            );
+            if let Some(item_schema) = schema.get("items") {
+                for (index, item) in items.iter().enumerate() {
+                    validate(
+                        root,
+                        expanding,
+                        item_schema,
+                        item,
+                        &format!("{path}[{index}]"),
+                        errors,
+                    );
+                }
+            }
+        }
+        Value::String(text) => {
+            let length = text.chars().count();
+            check_count(
+                schema,
+                "minLength",
+                "maxLength",
+                length,
+                path,
+                "characters",
+                errors,
+            );
+        }
+        Value::Number(number) => {
+            let number = number.as_f64().unwrap_or_default();
+            let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+            if let Some(minimum) = bound("minimum")
+                && number < minimum
+            {
+                errors.push(format!("{path} should be at least {minimum}"));
+            }
+            if let Some(maximum) = bound("maximum")
+                && number > maximum
+            {
+                errors.push(format!("{path} should be at most {maximum}"));
+            }
+            if let Some(minimum) = bound("exclusiveMinimum")
+                && number <= minimum
+            {
+                errors.push(format!("{path} should be greater than {minimum}"));
+            }
+            if let Some(maximum) = bound("exclusiveMaximum")
+                && number >= maximum
+            {
+                errors.push(format!("{path} should be less than {maximum}"));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Array(all)) = schema.get("allOf") {
+        for part in all {
+            validate(root, expanding, part, instance, path, errors);
+        }
+    }
+    for (keyword, exactly_one) in [("anyOf", false), ("oneOf", true)] {
+        let Some(Value::Array(options)) = schema.get(keyword) else {
+            continue;
+        };
+        let matching = options
+            .iter()
+            .filter(|option| {
+                let mut option_errors = Vec::new();
+                validate(root, expanding, option, instance, path, &mut option_errors);
+                option_errors.is_empty()
+            })
+            .count();
+        if matching == 0 || (exactly_one && matching > 1) {
+            errors.push(format!("{path} does not match {keyword}"));
+        }
+    }
+}
+
+fn check_count(
+    schema: &serde_json::Map<String, Value>,
+    min_keyword: &str,
+    max_keyword: &str,
+    count: usize,
+    path: &str,
+    unit: &str,
+    errors: &mut Vec<String>,
+) {
+    if let Some(minimum) = schema.get(min_keyword).and_then(Value::as_u64)
+        && (count as u64) < minimum
+    {
+        errors.push(format!("{path} should have at least {minimum} {unit}"));
+    }
+    if let Some(maximum) = schema.get(max_keyword).and_then(Value::as_u64)
+        && (count as u64) > maximum
+    {
+        errors.push(format!("{path} should have at most {maximum} {unit}"));
+    }
+}
+
+fn resolve_reference<'a>(root: &'a Value, reference: &str) -> Option<&'a Value> {
+    let pointer = reference.strip_prefix('#')?;
+    if pointer.is_empty() {
+        Some(root)
+    } else {
+        root.pointer(pointer)
+    }
+}
+
+fn has_type(instance: &Value, name: &str) -> bool {
+    match name {
+        "integer" => instance
+            .as_f64()
+            .is_some_and(|number| number.fract() == 0.0),
+        "number" => instance.is_number(),
+        other => type_name(instance) == other,
+    }
+}
+
+fn type_name(instance: &Value) -> &'static str {
+    match instance {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_schema() -> ResponseFormat {
+        ResponseFormat::parse(
+            r##"{
+                "type": "object",
+                "properties": {
+                    "name": {"type": "string", "minLength": 1},
+                    "age": {"type": "integer", "minimum": 0},
+                    "tags": {"type": "array", "items": {"$ref": "#/$defs/tag"}}
+                },
+                "required": ["name", "age"],
+                "additionalProperties": false,
+                "$defs": {"tag": {"enum": ["admin", "guest"]}}
+            }"##,
+        )
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn panel_text_selects_the_format() {
+        assert_eq!(ResponseFormat::parse("  ").unwrap(), None);
+        assert_eq!(
+            ResponseFormat::parse("JSON").unwrap(),
+            Some(ResponseFormat::Json)
+        );
+        assert_eq!(
+            ResponseFormat::Json.api_value(),
+            Value::String("json".to_string())
+        );
+        assert!(ResponseFormat::parse("[1, 2]").is_err());
+        assert!(ResponseFormat::parse("{\"type\": ").is_err());
+    }
+
+    #[test]
+    fn matching_responses_are_valid() {
+        assert_eq!(
+            person_schema().check(r#" {"name": "Ada", "age": 36, "tags": ["admin"]} "#),
+            FormatCheck::Valid
+        );
+        assert_eq!(ResponseFormat::Json.check("[1, 2, 3]"), FormatCheck::Valid);
+    }
+
+    #[test]
+    fn every_violation_is_reported_with_its_path() {
+        let FormatCheck::Invalid(errors) =
+            person_schema().check(r#"{"name": "", "age": 1.5, "tags": ["root"], "extra": 1}"#)
+        else {
+            panic!("response should be invalid");
+        };
+        assert_eq!(
+            errors,
+            vec![
+                "$.name should have at least 1 characters",
+                "$.age should be integer, found number",
+                "$.tags[0] should be one of \"admin\", \"guest\"",
+                "$.extra is not an allowed property",
+            ]
+        );
+    }
+
+    #[test]
+    fn cyclic_references_are_reported_instead_of_recursing() {
+        for schema in [
+            r##"{"$ref": "#"}"##,
+            r##"{"allOf": [{"$ref": "#"}]}"##,
+            r##"{"$ref": "#/$defs/a", "$defs": {"a": {"$ref": "#/$defs/b"}, "b": {"$ref": "#/$defs/a"}}}"##,
+        ] {
+            let format = ResponseFormat::parse(schema).unwrap().unwrap();
+            assert!(matches!(
+                format.check("{}"),
+                FormatCheck::Invalid(errors) if errors.iter().any(|error| error.contains("cyclic $ref"))
+            ));
+        }
+
+        let tree = ResponseFormat::parse(
+            r##"{"type": "object", "properties": {"children": {"type": "array", "items": {"$ref": "#"}}}}"##,
+        )
+        .unwrap()
+        .unwrap();
+        assert_eq!(
+            tree.check(r#"{"children": [{"children": []}]}"#),
+            FormatCheck::Valid
+        );
+    }
+
+    #[test]
+    fn non_json_responses_are_invalid() {
+        assert!(matches!(
+            ResponseFormat::Json.check("Sure! Here is the JSON:"),
+            FormatCheck::Invalid(errors) if errors.len() == 1
+        ));
+        assert!(matches!(
+            person_schema().check(r#"{"name": "Ada"}"#),
+            FormatCheck::Invalid(errors) if errors == ["$ is missing \"age\""]
+        ));
+    }
+}